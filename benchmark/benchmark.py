@@ -17,6 +17,7 @@
 # Rust: starlark benchmark.py
 
 REPEAT_100M = 100000000
+REPEAT_10M = 10000000
 
 
 def benchmark_loop():
@@ -31,6 +32,22 @@ def benchmark_multiply():
     return y
 
 
+def benchmark_bigint_square():
+    # Squaring modulo a Mersenne prime keeps all intermediates big.
+    m = (1 << 521) - 1
+    y = 3 << 500
+    for _x in range(REPEAT_10M):
+        y = y * y % m
+    return y
+
+
+def benchmark_bigint_shift():
+    y = 3 << 500
+    for _x in range(REPEAT_10M):
+        y = (y << 7) >> 7
+    return y
+
+
 def benchmark_call_native_len():
     y = 0
     xs = []
@@ -0,0 +1,360 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Benchmarks of Rust APIs which cannot be called from `benchmark/benchmark.py`.
+//!
+//! Run with `cargo bench -p starlark`, or `cargo bench -p starlark -- <name>`
+//! to only run benchmarks with names containing `<name>`.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::hint::black_box;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use starlark::environment::FrozenModule;
+use starlark::environment::Globals;
+use starlark::environment::LibraryExtension;
+use starlark::environment::Module;
+use starlark::eval::Evaluator;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+use starlark::values::list::ListRef;
+use starlark::values::structs::sort_by_struct_field;
+use starlark::values::structs::AllocStruct;
+use starlark::values::structs::StructPool;
+use starlark::values::structs::StructRef;
+use starlark::values::FrozenValue;
+use starlark::values::Heap;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+
+/// Allocator counting allocations, to report them in benchmarks.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Print the number of allocations and reallocations made by one call of `f`.
+fn count_allocations<R>(name: &str, f: impl FnOnce() -> R) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{:<48} {:>12}", name, allocations);
+}
+
+/// Run `f` repeatedly and print the best time of one call.
+fn bench<R>(name: &str, mut f: impl FnMut() -> R) {
+    bench_with_setup(name, || (), |()| f());
+}
+
+/// Like [`bench`], but only the time of `f` is measured, not of `setup`.
+fn bench_with_setup<S, R>(name: &str, mut setup: impl FnMut() -> S, mut f: impl FnMut(S) -> R) {
+    const ITERATIONS: u32 = 20;
+    let mut best = Duration::MAX;
+    for _ in 0..5 {
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..ITERATIONS {
+            let input = setup();
+            let start = Instant::now();
+            black_box(f(input));
+            elapsed += start.elapsed();
+        }
+        best = best.min(elapsed / ITERATIONS);
+    }
+    println!("{:<48} {:>12.3?}", name, best);
+}
+
+/// Create a struct with fields `names`, then set each field to `1`, one field at a time,
+/// either with [`StructRef::with_field`] or by copying all the fields each time.
+fn set_fields_one_by_one<'v>(heap: &'v Heap, names: &[String], copy: bool) -> Value<'v> {
+    let mut s = heap.alloc(AllocStruct(names.iter().map(|k| (k.as_str(), 0))));
+    let one = heap.alloc(1);
+    for name in names {
+        let fields = StructRef::from_value(s).unwrap();
+        s = if copy {
+            heap.alloc(AllocStruct(
+                fields
+                    .iter()
+                    .map(|(k, v)| (k, if k.as_str() == name { one } else { v })),
+            ))
+        } else {
+            fields.with_field(heap, name, one)
+        };
+    }
+    s
+}
+
+/// Functional update of a 1000-field struct: copy-on-write, which copies
+/// all the fields each time, against [`StructRef::with_field`], which shares them.
+///
+/// The copy is made with the public API, which inserts the fields one by one,
+/// so it is somewhat slower than cloning the field map.
+fn benchmark_struct_with_field() {
+    let names: Vec<String> = (0..1000).map(|i| format!("f{}", i)).collect();
+    bench("struct_with_field/copy", || {
+        let heap = Heap::new();
+        black_box(set_fields_one_by_one(&heap, &names, true));
+    });
+    bench("struct_with_field/shared", || {
+        let heap = Heap::new();
+        black_box(set_fields_one_by_one(&heap, &names, false));
+    });
+
+    // Reads of shared fields do more lookups.
+    let heap = Heap::new();
+    let copied = set_fields_one_by_one(&heap, &names, true);
+    let shared = set_fields_one_by_one(&heap, &names, false);
+    assert!(copied.equals(shared).unwrap());
+    for (name, s) in [("copy", copied), ("shared", shared)] {
+        bench(&format!("struct_with_field/{}/read_all", name), || {
+            for name in &names {
+                black_box(s.get_attr(name, &heap).unwrap());
+            }
+        });
+    }
+}
+
+/// Evaluate `code` in `module` with the standard globals and `struct`.
+///
+/// Values are only kept alive by the garbage collector if they are assigned
+/// to module variables, so get the results with [`Module::get`].
+fn eval_module(module: &Module, code: &str) {
+    let ast = AstModule::parse("bench.star", code.to_owned(), &Dialect::Standard).unwrap();
+    let mut eval = Evaluator::new(module);
+    let globals = Globals::extended_by(&[LibraryExtension::StructType]);
+    eval.eval_module(ast, &globals).unwrap();
+}
+
+/// Sorting 10000 structs by a field: [`sort_by_struct_field`] against
+/// `sorted` with a Starlark key function, which is called for every element.
+fn benchmark_sort_by_struct_field() {
+    let module = Module::new();
+    eval_module(
+        &module,
+        r#"
+xs = [struct(name = str(i), f = (i * 7919) % 10000) for i in range(10000)]
+def by_key(xs):
+    return sorted(xs, key = lambda s: s.f)
+"#,
+    );
+    let xs = module.get("xs").unwrap();
+    let by_key = module.get("by_key").unwrap();
+    let items = ListRef::from_value(xs).unwrap().content().to_vec();
+
+    bench("sort_by_struct_field/rust", || {
+        let mut items = items.clone();
+        sort_by_struct_field(&mut items, "f").unwrap();
+        items
+    });
+    let mut eval = Evaluator::new(&module);
+    eval.disable_gc();
+    bench("sort_by_struct_field/sorted_key", || {
+        eval.eval_function(by_key, &[xs], &[]).unwrap()
+    });
+}
+
+/// Summing a list of 10000 small integers from Rust: unpacking to `i64`,
+/// which checks the inline representation first, against unpacking
+/// through the generic integer conversion (as `i128` does),
+/// and against adding the Starlark values.
+fn benchmark_int_sum() {
+    let heap = Heap::new();
+    let xs: Vec<Value> = (0..10000).map(|i| heap.alloc(i)).collect();
+    bench("int_sum/unpack_i64", || {
+        let mut sum: i64 = 0;
+        for x in &xs {
+            sum = sum.checked_add(i64::unpack_value(*x).unwrap()).unwrap();
+        }
+        sum
+    });
+    bench("int_sum/unpack_i128", || {
+        let mut sum: i128 = 0;
+        for x in &xs {
+            sum = sum.checked_add(i128::unpack_value(*x).unwrap()).unwrap();
+        }
+        sum
+    });
+    bench("int_sum/value_add", || {
+        let mut sum = heap.alloc(0);
+        for x in &xs {
+            sum = sum.add(*x, &heap).unwrap();
+        }
+        sum.unpack_i32()
+    });
+}
+
+/// Evaluating 100 modules which produce mostly equal structs, frozen with and
+/// without [`StructPool`]: prints the total size of the frozen heaps and the time.
+fn benchmark_struct_pool() {
+    const CODE: &str = r#"
+configs = [
+    struct(
+        name = "target" + str(i % 4),
+        deps = struct(a = 1, b = 2.5, c = None),
+        tags = struct(x = "x" * 100),
+    )
+    for i in range(100)
+]
+"#;
+    let eval_freeze = |pool: Option<&StructPool>| {
+        let module = Module::new();
+        eval_module(&module, CODE);
+        match pool {
+            None => module.freeze().unwrap(),
+            Some(pool) => module.freeze_with_struct_pool(pool).unwrap(),
+        }
+    };
+    for (name, pool) in [("no_pool", None), ("pool", Some(StructPool::new()))] {
+        let modules: Vec<FrozenModule> = (0..100).map(|_| eval_freeze(pool.as_ref())).collect();
+        let bytes: usize = modules
+            .iter()
+            .map(|m| m.frozen_heap().allocated_bytes())
+            .sum();
+        println!(
+            "{:<48} {:>12}",
+            format!("struct_pool/{}/bytes", name),
+            bytes
+        );
+        bench(&format!("struct_pool/{}/eval_freeze", name), || {
+            eval_freeze(pool.as_ref())
+        });
+    }
+}
+
+/// Freezing a module with a 1000-field struct of frozen values, which reuses
+/// the field map, against the same struct with one unfrozen value,
+/// which freezes the fields one by one and rebuilds the map.
+fn benchmark_struct_freeze() {
+    let names: Vec<String> = (0..1000).map(|i| format!("f{}", i)).collect();
+    for (name, all_frozen) in [("all_frozen", true), ("one_unfrozen", false)] {
+        let setup = || {
+            let module = Module::new();
+            let heap = module.heap();
+            let fields = names.iter().enumerate().map(|(i, k)| {
+                let v = if all_frozen || i != 0 {
+                    FrozenValue::new_none().to_value()
+                } else {
+                    heap.alloc_str("unfrozen").to_value()
+                };
+                (module.frozen_heap().alloc_str(k), v)
+            });
+            module.set("s", heap.alloc(AllocStruct(fields)));
+            module
+        };
+        bench_with_setup(&format!("struct_freeze/{}", name), setup, |module| {
+            module.freeze().unwrap()
+        });
+    }
+}
+
+/// JSON built by joining the strings of nested values, for comparison
+/// with the serializer, which writes into a single buffer.
+fn to_json_joined(value: Value) -> String {
+    if let Some(xs) = ListRef::from_value(value) {
+        let items: Vec<String> = xs.iter().map(to_json_joined).collect();
+        format!("[{}]", items.join(","))
+    } else if let Some(s) = StructRef::from_value(value) {
+        let fields: Vec<String> = s
+            .iter()
+            .map(|(k, v)| format!("{}:{}", k.to_value().to_json().unwrap(), to_json_joined(v)))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    } else {
+        value.to_json().unwrap()
+    }
+}
+
+/// Converting a nested structure of about 10000 values to JSON: joining the strings
+/// of nested values, serializing with `serde` (`to_json` used to do that),
+/// [`Value::to_json`], and [`Value::write_json_fmt`] appending to a reused buffer.
+/// Prints allocations of one conversion and the time.
+fn benchmark_json() {
+    let module = Module::new();
+    eval_module(
+        &module,
+        r#"
+def node(depth):
+    if depth == 0:
+        return [1, "x", None]
+    return struct(left = node(depth - 1), right = node(depth - 1), depth = depth)
+x = node(11)
+"#,
+    );
+    let x = module.get("x").unwrap();
+    let json = x.to_json().unwrap();
+    assert_eq!(json, to_json_joined(x));
+    assert_eq!(json, serde_json::to_string(&x).unwrap());
+    let mut buffer = String::with_capacity(json.len());
+    let mut write_json_fmt = || {
+        buffer.clear();
+        x.write_json_fmt(&mut buffer).unwrap();
+    };
+
+    count_allocations("json/joined/allocations", || to_json_joined(x));
+    count_allocations("json/serde/allocations", || serde_json::to_string(&x));
+    count_allocations("json/to_json/allocations", || x.to_json().unwrap());
+    count_allocations("json/write_json_fmt/allocations", &mut write_json_fmt);
+
+    bench("json/joined", || to_json_joined(x));
+    bench("json/serde", || serde_json::to_string(&x));
+    bench("json/to_json", || x.to_json().unwrap());
+    bench("json/write_json_fmt", write_json_fmt);
+}
+
+const BENCHMARKS: &[(&str, fn())] = &[
+    ("struct_with_field", benchmark_struct_with_field),
+    ("sort_by_struct_field", benchmark_sort_by_struct_field),
+    ("int_sum", benchmark_int_sum),
+    ("struct_pool", benchmark_struct_pool),
+    ("struct_freeze", benchmark_struct_freeze),
+    ("json", benchmark_json),
+];
+
+fn main() {
+    // `cargo bench` passes `--bench`, other arguments are name filters.
+    let filters: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    for (name, benchmark) in BENCHMARKS {
+        if filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str())) {
+            benchmark();
+        }
+    }
+}
@@ -42,6 +42,7 @@ use crate::syntax::AstModule;
 use crate::syntax::Dialect;
 use crate::values::none::NoneType;
 use crate::values::structs::AllocStruct;
+use crate::values::structs::Schema;
 use crate::values::tuple::UnpackTuple;
 use crate::values::typing::type_compiled::compiled::TypeCompiled;
 use crate::values::AllocValue;
@@ -159,11 +160,6 @@ pub(crate) fn test_functions(builder: &mut GlobalsBuilder) {
         Ok(AllocStruct::EMPTY)
     }
 
-    // Approximate version of a method used by the Go test suite
-    fn set<'v>(xs: Value<'v>) -> anyhow::Result<Value<'v>> {
-        Ok(xs)
-    }
-
     fn assert_eq<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
         assert_equals(a, b)
     }
@@ -651,3 +647,86 @@ pub fn pass(program: &str) -> OwnedFrozenValue {
 pub fn pass_module(program: &str) -> FrozenModule {
     Assert::new().pass_module(program)
 }
+
+/// Check the value is a struct matching the schema, see [`Schema::validate`].
+///
+/// On mismatch, panic with a message listing all the mismatches with their paths.
+///
+/// ```
+/// use starlark::assert;
+/// use starlark::values::structs::Schema;
+/// use starlark::values::structs::SchemaType;
+///
+/// let config = assert::pass("struct(host = 'localhost', port = 80)");
+/// let schema = Schema::new()
+///     .required("host", SchemaType::Str)
+///     .required("port", SchemaType::Int);
+/// assert::struct_matches(config.value(), &schema);
+/// ```
+pub fn struct_matches(value: Value, schema: &Schema) {
+    if let Err(message) = struct_mismatches(value, schema) {
+        panic!("{}", message);
+    }
+}
+
+fn struct_mismatches(value: Value, schema: &Schema) -> Result<(), String> {
+    let Err(errors) = schema.validate(value) else {
+        return Ok(());
+    };
+    let mut message = format!(
+        "starlark::assert::struct_matches, value does not match the schema!\nValue:\n{}\nMismatches:",
+        value
+    );
+    for error in errors {
+        message.push_str(&format!("\n  {}", error));
+    }
+    Err(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::assert::struct_mismatches;
+    use crate::values::structs::Schema;
+    use crate::values::structs::SchemaType;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .required("host", SchemaType::Str)
+            .required("port", SchemaType::Int)
+            .optional("tags", SchemaType::List(Box::new(SchemaType::Str)))
+    }
+
+    #[test]
+    fn test_struct_matches() {
+        let v = assert::pass("struct(host = 'localhost', port = 80, tags = ['a'])");
+        assert::struct_matches(v.value(), &schema());
+        let v = assert::pass("struct(host = 'localhost', port = 80)");
+        assert::struct_matches(v.value(), &schema());
+    }
+
+    #[test]
+    fn test_struct_matches_failure_message() {
+        let v = assert::pass("struct(port = '80', tags = ['a', 1], debug = True)");
+        assert_eq!(
+            "starlark::assert::struct_matches, value does not match the schema!\n\
+             Value:\n\
+             struct(port=\"80\", tags=[\"a\", 1], debug=True)\n\
+             Mismatches:\n  \
+             $.host: missing required field\n  \
+             $.port: expected `int`, got value of type `string`\n  \
+             $.tags[1]: expected `str`, got value of type `int`\n  \
+             $.debug: unknown field",
+            struct_mismatches(v.value(), &schema()).unwrap_err()
+        );
+        let v = assert::pass("[1]");
+        assert_eq!(
+            "starlark::assert::struct_matches, value does not match the schema!\n\
+             Value:\n\
+             [1]\n\
+             Mismatches:\n  \
+             $: expected `struct`, got value of type `list`",
+            struct_mismatches(v.value(), &schema()).unwrap_err()
+        );
+    }
+}
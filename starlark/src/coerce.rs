@@ -27,6 +27,7 @@ use std::ptr;
 
 pub use starlark_derive::Coerce;
 use starlark_map::small_map::SmallMap;
+use starlark_map::small_set::SmallSet;
 
 /// A marker trait such that the existence of `From: Coerce<To>` implies
 /// that `From` can be treat as `To` without any data manipulation.
@@ -109,6 +110,8 @@ where
 {
 }
 
+unsafe impl<From, To> Coerce<SmallSet<To>> for SmallSet<From> where From: CoerceKey<To> {}
+
 /// Safely convert between types which have a `Coerce` relationship.
 /// Often the second type argument will need to be given explicitly,
 /// e.g. `coerce::<_, ToType>(x)`.
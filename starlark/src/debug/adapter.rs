@@ -31,6 +31,7 @@ use crate::syntax::AstModule;
 use crate::values::dict::DictRef;
 use crate::values::layout::heap::heap_type::Heap;
 use crate::values::layout::value::Value;
+use crate::values::structs::StructRef;
 
 mod implementation;
 mod tests;
@@ -148,6 +149,21 @@ impl PathSegment {
     }
 }
 
+/// Attributes shown as children of a struct-like value.
+///
+/// Builtin struct methods like `to_json` are not data, so only struct fields are listed.
+fn struct_like_attrs<'v>(v: Value<'v>) -> Vec<String> {
+    match StructRef::from_value(v) {
+        Some(s) => {
+            let mut attrs: Vec<String> = s.iter().map(|(k, _)| k.as_str().to_owned()).collect();
+            // Same order as `dir_attr`.
+            attrs.sort();
+            attrs
+        }
+        None => v.dir_attr(),
+    }
+}
+
 impl Variable {
     /// Helper to convert to the DAP Variable type.
     pub fn to_dap(self) -> debugserver_types::Variable {
@@ -185,7 +201,7 @@ impl Variable {
     }
 
     fn struct_like_value_as_str<'v>(v: Value<'v>) -> String {
-        let attrs = v.dir_attr();
+        let attrs = struct_like_attrs(v);
         format!("<type:{}, size={}>", v.get_type(), attrs.len())
     }
 
@@ -298,8 +314,7 @@ impl InspectVariableInfo {
 
     fn try_from_struct_like<'v>(v: Value<'v>, heap: &'v Heap) -> crate::Result<Self> {
         Ok(Self {
-            sub_values: v
-                .dir_attr()
+            sub_values: struct_like_attrs(v)
                 .into_iter()
                 .map(|child_name| {
                     let child_value = v.get_attr_error(&child_name, heap)?;
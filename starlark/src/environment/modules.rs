@@ -50,6 +50,8 @@ use crate::eval::ProfileData;
 use crate::values::layout::heap::heap_type::HeapKind;
 use crate::values::layout::heap::profile::aggregated::AggregateHeapProfileInfo;
 use crate::values::layout::heap::profile::aggregated::RetainedHeapProfile;
+use crate::values::structs::pool::StructPoolFreezer;
+use crate::values::structs::StructPool;
 use crate::values::Freeze;
 use crate::values::Freezer;
 use crate::values::FrozenHeap;
@@ -400,6 +402,18 @@ impl Module {
 
     /// Freeze the environment, all its value will become immutable afterwards.
     pub fn freeze(self) -> anyhow::Result<FrozenModule> {
+        self.freeze_impl(None)
+    }
+
+    /// Freeze the environment like [`freeze`](Module::freeze), reusing equal structs
+    /// already in the pool instead of allocating them again, and adding new ones to the pool.
+    ///
+    /// Useful to reduce memory when many modules produce the same structs.
+    pub fn freeze_with_struct_pool(self, pool: &StructPool) -> anyhow::Result<FrozenModule> {
+        self.freeze_impl(Some(pool))
+    }
+
+    fn freeze_impl(self, struct_pool: Option<&StructPool>) -> anyhow::Result<FrozenModule> {
         let Module {
             names,
             slots,
@@ -415,8 +429,9 @@ impl Module {
         // Note that we even freeze anonymous slots, since they are accessed by
         // slot-index in the code, and we don't walk into them, so don't know if
         // they are used.
-        let freezer = Freezer::new(frozen_heap);
-        let slots = slots.freeze(&freezer)?;
+        let mut freezer = Freezer::new(frozen_heap);
+        freezer.struct_pool = struct_pool.map(|pool| StructPoolFreezer::new(pool.dupe()));
+        let slots = slots.freeze(&freezer, &names)?;
         let extra_value = extra_value.into_inner().freeze(&freezer)?;
         let stacks = if let Some(mode) = heap_profile_on_freeze.get() {
             // TODO(nga): retained heap profile does not store information about data
@@ -443,8 +458,14 @@ impl Module {
         // but can now be dropped
         mem::drop(heap);
 
+        let struct_pool = freezer.struct_pool.take();
+        let frozen_heap = freezer.into_ref();
+        if let Some(struct_pool) = struct_pool {
+            struct_pool.commit(&frozen_heap);
+        }
+
         Ok(FrozenModule {
-            heap: freezer.into_ref(),
+            heap: frozen_heap,
             module: frozen_module_ref,
             extra_value,
             eval_duration: start.elapsed() + eval_duration.get(),
@@ -19,8 +19,10 @@ use std::cell::RefCell;
 use std::cell::RefMut;
 
 use allocative::Allocative;
+use anyhow::Context;
 use dupe::Dupe;
 
+use crate::environment::names::MutableNames;
 use crate::values::Freeze;
 use crate::values::Freezer;
 use crate::values::FrozenValue;
@@ -86,8 +88,27 @@ impl<'v> MutableSlots<'v> {
             .collect()
     }
 
-    pub(crate) fn freeze(self, freezer: &Freezer) -> anyhow::Result<FrozenSlots> {
-        let slots = self.0.into_inner().freeze(freezer)?;
+    /// Freeze the slots, naming the variable in the error if its value fails to freeze.
+    pub(crate) fn freeze(
+        self,
+        freezer: &Freezer,
+        names: &MutableNames,
+    ) -> anyhow::Result<FrozenSlots> {
+        let slots = self
+            .0
+            .into_inner()
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| {
+                v.freeze(freezer).with_context(|| {
+                    let slot = ModuleSlotId::new(u32::try_from(i).unwrap());
+                    match names.get_slot(slot) {
+                        Some(name) => format!("failed to freeze variable `{}`", name.as_str()),
+                        None => format!("failed to freeze module slot {}", i),
+                    }
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
         Ok(FrozenSlots(slots))
     }
 }
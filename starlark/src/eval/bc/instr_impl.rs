@@ -54,7 +54,7 @@ use crate::eval::compiler::def::FrozenDef;
 use crate::eval::compiler::def::ParameterCompiled;
 use crate::eval::compiler::def::ParametersCompiled;
 use crate::eval::compiler::expr::get_attr_hashed_bind;
-use crate::eval::compiler::expr::get_attr_hashed_raw;
+use crate::eval::compiler::expr::get_attr_hashed_raw_eval;
 use crate::eval::compiler::expr::EvalError;
 use crate::eval::compiler::expr_throw_starlark_result;
 use crate::eval::compiler::stmt::add_assign;
@@ -73,6 +73,8 @@ use crate::eval::ParametersSpec;
 use crate::values::dict::Dict;
 use crate::values::int::PointerI32;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
+use crate::values::strict_numeric_equality::equals_strict;
+use crate::values::strict_numeric_equality::is_in_strict;
 use crate::values::string::dot_format::format_one;
 use crate::values::string::interpolation::percent_s_one;
 use crate::values::types::known_methods::KnownMethod;
@@ -373,7 +375,7 @@ impl InstrNoFlowImpl for InstrObjectFieldImpl {
         (object, field, target): &(BcSlotIn, Symbol, BcSlotOut),
     ) -> crate::Result<()> {
         let object = frame.get_bc_slot(*object);
-        let value = get_attr_hashed_bind(object, field, eval.heap())?;
+        let value = get_attr_hashed_bind(object, field, eval)?;
         frame.set_bc_slot(*target, value);
         Ok(())
     }
@@ -451,16 +453,41 @@ pub(crate) struct InstrEqPtrImpl;
 pub(crate) struct InstrEqStrImpl;
 pub(crate) struct InstrEqIntImpl;
 
-pub(crate) type InstrEq = InstrBinOp<InstrEqImpl>;
+pub(crate) type InstrEq = InstrNoFlow<InstrEqImpl>;
 pub(crate) type InstrEqConst = InstrNoFlow<InstrEqConstImpl>;
 pub(crate) type InstrEqPtr = InstrNoFlow<InstrEqPtrImpl>;
 pub(crate) type InstrEqStr = InstrNoFlow<InstrEqStrImpl>;
 pub(crate) type InstrEqInt = InstrNoFlow<InstrEqIntImpl>;
 
-impl InstrBinOpImpl for InstrEqImpl {
+/// `a == b` in the numeric equality mode of the evaluator.
+#[inline(always)]
+fn equals_in_mode<'v>(
+    eval: &Evaluator<'v, '_, '_>,
+    a: Value<'v>,
+    b: Value<'v>,
+) -> crate::Result<bool> {
+    if eval.strict_numeric_equality {
+        equals_strict(a, b)
+    } else {
+        a.equals(b)
+    }
+}
+
+impl InstrNoFlowImpl for InstrEqImpl {
+    type Arg = (BcSlotIn, BcSlotIn, BcSlotOut);
+
     #[inline(always)]
-    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, _heap: &'v Heap) -> crate::Result<Value<'v>> {
-        v0.equals(v1).map(Value::new_bool)
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        _ip: BcPtrAddr,
+        (v0, v1, target): &(BcSlotIn, BcSlotIn, BcSlotOut),
+    ) -> crate::Result<()> {
+        let v0 = frame.get_bc_slot(*v0);
+        let v1 = frame.get_bc_slot(*v1);
+        let r = equals_in_mode(eval, v0, v1)?;
+        frame.set_bc_slot(*target, Value::new_bool(r));
+        Ok(())
     }
 }
 
@@ -469,13 +496,17 @@ impl InstrNoFlowImpl for InstrEqConstImpl {
 
     #[inline(always)]
     fn run_with_args<'v>(
-        _eval: &mut Evaluator<'v, '_, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
         frame: BcFramePtr<'v>,
         _ip: BcPtrAddr,
         (a, b, target): &(BcSlotIn, FrozenValueNotSpecial, BcSlotOut),
     ) -> crate::Result<()> {
         let a = frame.get_bc_slot(*a);
-        let r = b.equals(a)?;
+        let r = if eval.strict_numeric_equality {
+            equals_strict(b.to_value(), a)?
+        } else {
+            b.equals(a)?
+        };
         frame.set_bc_slot(*target, Value::new_bool(r));
         Ok(())
     }
@@ -503,7 +534,7 @@ impl InstrNoFlowImpl for InstrEqIntImpl {
 
     #[inline(always)]
     fn run_with_args<'v>(
-        _eval: &mut Evaluator<'v, '_, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
         frame: BcFramePtr<'v>,
         _ip: BcPtrAddr,
         (a, b, target): &(BcSlotIn, FrozenValueTyped<'static, PointerI32>, BcSlotOut),
@@ -512,7 +543,7 @@ impl InstrNoFlowImpl for InstrEqIntImpl {
         let r = if let Some(a) = a.unpack_int_value() {
             a.as_ref() == b.as_ref()
         } else {
-            b.equals(a)?
+            equals_in_mode(eval, b.to_value(), a)?
         };
         frame.set_bc_slot(*target, Value::new_bool(r));
         Ok(())
@@ -654,7 +685,7 @@ pub(crate) type InstrBitOrAssign = InstrBinOp<InstrBitOrAssignImpl>;
 pub(crate) type InstrBitXor = InstrBinOp<InstrBitXorImpl>;
 pub(crate) type InstrLeftShift = InstrBinOp<InstrLeftShiftImpl>;
 pub(crate) type InstrRightShift = InstrBinOp<InstrRightShiftImpl>;
-pub(crate) type InstrIn = InstrBinOp<InstrInImpl>;
+pub(crate) type InstrIn = InstrNoFlow<InstrInImpl>;
 
 impl InstrBinOpImpl for InstrAddImpl {
     #[inline(always)]
@@ -747,10 +778,25 @@ impl InstrBinOpImpl for InstrRightShiftImpl {
     }
 }
 
-impl InstrBinOpImpl for InstrInImpl {
+impl InstrNoFlowImpl for InstrInImpl {
+    type Arg = (BcSlotIn, BcSlotIn, BcSlotOut);
+
     #[inline(always)]
-    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, _heap: &'v Heap) -> crate::Result<Value<'v>> {
-        Ok(Value::new_bool(v1.is_in(v0)?))
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        _ip: BcPtrAddr,
+        (v0, v1, target): &(BcSlotIn, BcSlotIn, BcSlotOut),
+    ) -> crate::Result<()> {
+        let v0 = frame.get_bc_slot(*v0);
+        let v1 = frame.get_bc_slot(*v1);
+        let r = if eval.strict_numeric_equality {
+            is_in_strict(v1, v0)?
+        } else {
+            v1.is_in(v0)?
+        };
+        frame.set_bc_slot(*target, Value::new_bool(r));
+        Ok(())
     }
 }
 
@@ -1611,7 +1657,7 @@ fn call_method_common<'v>(
     target: BcSlotOut,
 ) -> crate::Result<()> {
     // TODO: wrong span: should be span of `object.method`, not of the whole expression
-    let method = get_attr_hashed_raw(this, symbol, eval.heap())?;
+    let method = get_attr_hashed_raw_eval(this, symbol, eval)?;
     let r = method.invoke(this, span, arguments, eval)?;
     frame.set_bc_slot(target, r);
     Ok(())
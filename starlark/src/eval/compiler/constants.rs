@@ -65,7 +65,7 @@ impl Constants {
                 typing_callable: {
                     let typing = g.get_frozen("typing").unwrap();
                     let typing = FrozenStructRef::from_value(typing).unwrap();
-                    BuiltinFn(*typing.0.fields.get("Callable").unwrap())
+                    BuiltinFn(typing.0.get("Callable").unwrap().unpack_frozen().unwrap())
                 },
             }
         });
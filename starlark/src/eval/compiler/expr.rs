@@ -63,6 +63,9 @@ use crate::eval::Evaluator;
 use crate::values::function::BoundMethodGen;
 use crate::values::function::FrozenBoundMethod;
 use crate::values::list::ListRef;
+use crate::values::strict_numeric_equality::equals_strict;
+use crate::values::strict_numeric_equality::is_in_strict;
+use crate::values::strict_numeric_equality::same_in_both_modes;
 use crate::values::string::interpolation::parse_percent_s_one;
 use crate::values::types::bool::StarlarkBool;
 use crate::values::types::dict::Dict;
@@ -203,11 +206,17 @@ pub(crate) enum Builtin2 {
 }
 
 impl Builtin2 {
-    fn eval<'v>(self, a: Value<'v>, b: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match self {
-            Builtin2::Equals => a.equals(b).map(Value::new_bool),
+    /// Evaluate the operator at compile time. `None` if evaluation fails
+    /// or the result depends on the strict numeric equality mode.
+    fn eval<'v>(self, a: Value<'v>, b: Value<'v>, heap: &'v Heap) -> Option<Value<'v>> {
+        let r = match self {
+            Builtin2::Equals => {
+                return same_in_both_modes(a.equals(b), equals_strict(a, b)).map(Value::new_bool);
+            }
             Builtin2::Compare(cmp) => a.compare(b).map(|c| Value::new_bool(cmp.apply(c))),
-            Builtin2::In => b.is_in(a).map(Value::new_bool),
+            Builtin2::In => {
+                return same_in_both_modes(b.is_in(a), is_in_strict(b, a)).map(Value::new_bool);
+            }
             Builtin2::Sub => a.sub(b, heap),
             Builtin2::Add => a.add(b, heap),
             Builtin2::Multiply => a.mul(b, heap),
@@ -220,7 +229,8 @@ impl Builtin2 {
             Builtin2::LeftShift => a.left_shift(b, heap),
             Builtin2::RightShift => a.right_shift(b, heap),
             Builtin2::ArrayIndex => a.at(b, heap),
-        }
+        };
+        r.ok()
     }
 }
 
@@ -568,7 +578,8 @@ impl ExprCompiled {
         let span = l.span.merge(&r.span);
         if let (Some(l), Some(r)) = (l.as_value(), r.as_value()) {
             // If comparison fails, let it fail in runtime.
-            if let Ok(r) = l.equals(r.to_value()) {
+            let (l, r) = (l.to_value(), r.to_value());
+            if let Some(r) = same_in_both_modes(l.equals(r), equals_strict(l, r)) {
                 return IrSpanned {
                     span,
                     node: ExprCompiled::Value(FrozenValue::new_bool(r)),
@@ -715,7 +726,7 @@ impl ExprCompiled {
         // Binary operators should have no side effects,
         // but to avoid possible problems, we only fold binary operators on builtin types.
         if let (Some(l), Some(r)) = (l.as_builtin_value(), r.as_builtin_value()) {
-            if let Ok(v) = bin_op.eval(l.to_value(), r.to_value(), ctx.heap()) {
+            if let Some(v) = bin_op.eval(l.to_value(), r.to_value(), ctx.heap()) {
                 if let Some(v) = ExprCompiled::try_value(span, v, ctx.frozen_heap()) {
                     return v;
                 }
@@ -1140,19 +1151,50 @@ impl<'v, 'a> MemberOrValue<'v, 'a> {
     }
 }
 
+/// Get the attribute without side effects, `None` if there is no such attribute
+/// or it can only be computed by [`StarlarkValue::get_attr_eval`].
 #[inline(always)]
-pub(crate) fn get_attr_hashed_raw<'v>(
+fn get_attr_hashed_raw_opt<'v>(
     x: Value<'v>,
     attribute: &Symbol,
     heap: &'v Heap,
-) -> crate::Result<MemberOrValue<'v, 'static>> {
+) -> Option<MemberOrValue<'v, 'static>> {
     let aref = x.get_ref();
     if let Some(methods) = aref.vtable().methods() {
         if let Some(v) = methods.get_frozen_symbol(attribute) {
-            return Ok(MemberOrValue::Member(v));
+            if aref.vtable().attrs_shadow_methods() {
+                if let Some(x) = aref.get_attr_hashed(attribute.as_str_hashed(), heap) {
+                    return Some(MemberOrValue::Value(x));
+                }
+            }
+            return Some(MemberOrValue::Member(v));
         }
     }
-    match aref.get_attr_hashed(attribute.as_str_hashed(), heap) {
+    aref.get_attr_hashed(attribute.as_str_hashed(), heap)
+        .map(MemberOrValue::Value)
+}
+
+/// Get the attribute without side effects, for compile time evaluation.
+#[inline(always)]
+pub(crate) fn get_attr_hashed_raw<'v>(
+    x: Value<'v>,
+    attribute: &Symbol,
+    heap: &'v Heap,
+) -> crate::Result<MemberOrValue<'v, 'static>> {
+    get_attr_hashed_raw_opt(x, attribute, heap).ok_or_else(|| get_attr_no_attr_error(x, attribute))
+}
+
+/// Get the attribute for attribute access from Starlark code.
+#[inline(always)]
+pub(crate) fn get_attr_hashed_raw_eval<'v>(
+    x: Value<'v>,
+    attribute: &Symbol,
+    eval: &mut Evaluator<'v, '_, '_>,
+) -> crate::Result<MemberOrValue<'v, 'static>> {
+    if let Some(v) = get_attr_hashed_raw_opt(x, attribute, eval.heap()) {
+        return Ok(v);
+    }
+    match x.get_ref().get_attr_eval(attribute.as_str(), eval)? {
         None => Err(get_attr_no_attr_error(x, attribute)),
         Some(x) => Ok(MemberOrValue::Value(x)),
     }
@@ -1161,22 +1203,30 @@ pub(crate) fn get_attr_hashed_raw<'v>(
 pub(crate) fn get_attr_hashed_bind<'v>(
     x: Value<'v>,
     attribute: &Symbol,
-    heap: &'v Heap,
+    eval: &mut Evaluator<'v, '_, '_>,
 ) -> crate::Result<Value<'v>> {
+    let heap = eval.heap();
     let aref = x.get_ref();
     if let Some(methods) = aref.vtable().methods() {
         if let Some(v) = methods.get_frozen_symbol(attribute) {
+            if aref.vtable().attrs_shadow_methods() {
+                if let Some(x) = aref.get_attr_hashed(attribute.as_str_hashed(), heap) {
+                    return Ok(x);
+                }
+            }
             return v.bind(x, heap);
         }
     }
-    match aref.get_attr_hashed(attribute.as_str_hashed(), heap) {
-        None => Err(get_attr_no_attr_error(x, attribute)),
-        Some(x) => {
-            // Only `get_methods` is allowed to return unbound methods or attributes.
-            // Both types are crate private, so we assume `get_attr` never returns them.
-            Ok(x)
-        }
-    }
+    let value = match aref.get_attr_hashed(attribute.as_str_hashed(), heap) {
+        Some(x) => x,
+        None => match aref.get_attr_eval(attribute.as_str(), eval)? {
+            Some(x) => x,
+            None => return Err(get_attr_no_attr_error(x, attribute)),
+        },
+    };
+    // Only `get_methods` is allowed to return unbound methods or attributes.
+    // Both types are crate private, so we assume `get_attr` never returns them.
+    Ok(value)
 }
 
 impl<'v, 'a, 'e> Compiler<'v, 'a, 'e, '_> {
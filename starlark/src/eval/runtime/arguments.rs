@@ -325,6 +325,26 @@ impl<'v, 'a> Arguments<'v, 'a> {
         }
     }
 
+    /// Like [`names_map`](Arguments::names_map), but a name repeated in `**kwargs`
+    /// overwrites the value of the named argument instead of failing.
+    pub(crate) fn names_map_overwrite(
+        &self,
+    ) -> crate::Result<SmallMap<StringValue<'v>, Value<'v>>> {
+        let kwargs = self.unpack_kwargs()?;
+        let mut result =
+            SmallMap::with_capacity(self.0.names.len() + kwargs.as_ref().map_or(0, |k| k.len()));
+        for (k, v) in self.0.names.iter().zip(self.0.named) {
+            result.insert_hashed(Hashed::new_unchecked(k.0.small_hash(), k.1), *v);
+        }
+        if let Some(kwargs) = kwargs {
+            for (k, v) in kwargs.iter_hashed() {
+                let s = Arguments::unpack_kwargs_key_as_value(*k.key())?;
+                result.insert_hashed(Hashed::new_unchecked(k.hash(), s), v);
+            }
+        }
+        Ok(result)
+    }
+
     /// The number of arguments, where those inside a args/kwargs are counted as multiple arguments.
     ///
     /// This operation fails if the `kwargs` is not a dictionary, or `args` does not support `len`.
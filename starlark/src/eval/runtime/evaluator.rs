@@ -136,6 +136,8 @@ pub struct Evaluator<'v, 'a, 'e> {
     pub(crate) next_gc_level: usize,
     /// Run static typechecking of the module being evaluated.
     pub(crate) static_typechecking: bool,
+    /// `int` and `float` values are never equal.
+    pub(crate) strict_numeric_equality: bool,
     // Profiling or instrumentation enabled.
     pub(crate) profile_or_instrumentation_mode: ProfileOrInstrumentationMode,
     // Used for line profiling
@@ -161,6 +163,10 @@ pub struct Evaluator<'v, 'a, 'e> {
     pub(crate) soft_error_handler: &'a (dyn SoftErrorHandler + 'a),
     /// Max size of starlark stack
     pub(crate) max_callstack_size: Option<usize>,
+    /// Max number of fields of a struct created by `struct()`.
+    pub(crate) max_struct_fields: Option<usize>,
+    /// `struct()` lets `**kwargs` overwrite named arguments instead of failing.
+    pub(crate) struct_duplicate_fields_overwrite: bool,
     // The Starlark-level call-stack of functions.
     // Must go last because it's quite a big structure
     pub(crate) call_stack: CheapCallStack<'v>,
@@ -233,7 +239,10 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             soft_error_handler: &HardErrorSoftErrorHandler,
             verbose_gc: false,
             static_typechecking: false,
+            strict_numeric_equality: false,
             max_callstack_size: None,
+            max_struct_fields: None,
+            struct_duplicate_fields_overwrite: false,
         }
     }
 
@@ -260,6 +269,19 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.static_typechecking = enable;
     }
 
+    /// Enable strict numeric equality for the `==`, `!=` and `in` operators:
+    /// an `int` is never equal to a `float`, so `1 == 1.0` is `False`.
+    /// This also applies to values nested in structs, lists, tuples and dict values,
+    /// and `in` compares elements of lists and tuples the same way.
+    /// Ordering comparisons like `1 < 1.5` are not affected. Disabled by default.
+    ///
+    /// Dict keys and set elements are always compared with regular equality,
+    /// so `{1: "a"}[1.0]` is `"a"`, and a dict never has both `1` and `1.0` as keys.
+    /// Builtin functions and methods, like `list.index`, also use regular equality.
+    pub fn enable_strict_numeric_equality(&mut self, enable: bool) {
+        self.strict_numeric_equality = enable;
+    }
+
     /// Set the [`FileLoader`] used to resolve `load()` statements.
     /// A list of all load statements can be obtained through
     /// [`AstModule::loads`](crate::syntax::AstModule::loads).
@@ -845,6 +867,26 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.max_callstack_size = Some(stack_size);
         Ok(())
     }
+
+    /// Sets max number of fields of structs created with `struct()`,
+    /// creating a struct with more fields fails. Unlimited by default.
+    ///
+    /// Only `struct()` calls are checked. Structs produced by `+` of two structs,
+    /// by [`StructRef::with_field`](crate::values::structs::StructRef::with_field)
+    /// or by [`StructBuilder`](crate::values::structs::StructBuilder) are not limited.
+    pub fn set_max_struct_fields(&mut self, max_fields: Option<usize>) {
+        self.max_struct_fields = max_fields;
+    }
+
+    /// By default `struct()` fails if a field name is passed both as a named argument
+    /// and in `**kwargs`, like `struct(a = 1, **{"a": 2})`. If set, the value
+    /// from `**kwargs` is used instead, keeping the position of the named argument.
+    ///
+    /// Repeated literal named arguments, like `struct(a = 1, a = 2)`,
+    /// are rejected at compile time regardless.
+    pub fn set_struct_duplicate_fields_overwrite(&mut self, overwrite: bool) {
+        self.struct_duplicate_fields_overwrite = overwrite;
+    }
 }
 
 pub(crate) trait EvaluationCallbacks {
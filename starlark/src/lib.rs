@@ -389,6 +389,14 @@
 //! # }
 //! # fn main(){ run().unwrap(); }
 //! ```
+//!
+//! ## Optional features
+//!
+//! Cargo features enabling integration with other crates, all disabled by default:
+//!
+//! * `chrono`: unpack integers as Unix timestamps, e.g. `starlark::values::int::UnixSeconds`.
+//! * `ciborium`: convert values to and from [CBOR](https://cbor.io/)
+//!   with `Value::to_cbor` and `Value::from_cbor`.
 
 // Features we use
 #![allow(stable_features)]
@@ -482,5 +490,10 @@ pub mod __derive_refs {
         pub use serde::Serialize;
         pub use serde::Serializer;
     }
+    pub use anyhow;
     pub use inventory;
+
+    pub use crate::values::types::structs::access::struct_ref;
+    pub use crate::values::types::structs::access::unpack_struct_field;
+    pub use crate::values::types::structs::access::unpack_struct_field_opt;
 }
@@ -215,3 +215,29 @@ macro_rules! starlark_simple_value {
         }
     };
 }
+
+/// Build a struct at runtime, expanding to a sequence of
+/// [`StructBuilder`](crate::values::structs::StructBuilder) calls.
+///
+/// Keys are expressions evaluating to anything which is `AsRef<str>`,
+/// values are anything which is [`AllocValue`](crate::values::AllocValue).
+///
+/// ```
+/// # use starlark::struct_of;
+/// # use starlark::values::Heap;
+/// # let heap = Heap::new();
+/// let name = format!("port_{}", 1);
+/// let s = struct_of! { &heap; "host" => "localhost", name => 80 };
+/// assert_eq!("struct(host=\"localhost\", port_1=80)", s.to_repr());
+/// ```
+#[macro_export]
+macro_rules! struct_of {
+    ($heap:expr $(; $($key:expr => $val:expr),* $(,)?)?) => {{
+        #[allow(unused_mut)]
+        let mut builder = $crate::values::structs::StructBuilder::new($heap);
+        $($(
+            builder.add(::std::convert::AsRef::<str>::as_ref(&$key), $val);
+        )*)?
+        builder.build()
+    }};
+}
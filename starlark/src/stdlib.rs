@@ -30,7 +30,9 @@ mod funcs;
 pub(crate) mod internal;
 pub(crate) mod json;
 pub(crate) mod list;
+pub(crate) mod math;
 pub(crate) mod partial;
+pub(crate) mod set;
 pub(crate) mod string;
 pub(crate) mod structs;
 
@@ -54,14 +56,17 @@ pub(crate) fn standard_environment() -> GlobalsBuilder {
 /// The extra library definitions available in this Starlark implementation, but not in the standard.
 #[derive(PartialEq, Eq, Copy, Clone, Dupe)]
 pub enum LibraryExtension {
-    /// Definitions to support the `struct` type, the `struct()` constructor.
+    /// Definitions to support the `struct` type, the `struct()` constructor
+    /// and the `columnar()` function.
     StructType,
     /// Definitions to support the `record` type, the `record()` constructor and `field()` function.
     RecordType,
     /// Definitions to support the `enum` type, the `enum()` constructor.
     EnumType,
+    /// Definitions to support the `set` type, the `set()` constructor.
+    /// Sets are not part of the Starlark specification.
+    SetType,
     /// A function `map(f, xs)` which applies `f` to each element of `xs` and returns the result.
-    // TODO(nga): add set: https://www.internalfb.com/tasks/?t=184017710
     Map,
     /// A function `filter(f, xs)` which applies `f` to each element of `xs` and returns those for which `f` returns `True`.
     /// As a special case, `filter(None, xs)` removes all `None` values.
@@ -94,6 +99,10 @@ pub enum LibraryExtension {
     /// Add a function `call_stack()` which returns a string representation of
     /// the current call stack.
     CallStack,
+    /// Add integer math functions `is_perfect_square(n)`, `is_perfect_power(n)`,
+    /// `as_integer_ratio(f)`, `comb(n, k)`, `perm(n, k)`, `divmod(x, y)`, `mod_inverse(a, m)`,
+    /// `exact_log2(n)`, `exact_log10(n)` and `rand(seed, lo, hi)`.
+    Math,
     // Make sure if you add anything new, you add it to `all` below.
 }
 
@@ -102,8 +111,8 @@ impl LibraryExtension {
     pub(crate) fn all() -> &'static [Self] {
         use LibraryExtension::*;
         &[
-            StructType, RecordType, EnumType, Map, Filter, Partial, Debug, Print, Pprint, Pstr,
-            Prepr, Breakpoint, Json, Typing, Internal, CallStack,
+            StructType, RecordType, EnumType, SetType, Map, Filter, Partial, Debug, Print, Pprint,
+            Pstr, Prepr, Breakpoint, Json, Typing, Internal, CallStack, Math,
         ]
     }
 
@@ -114,6 +123,7 @@ impl LibraryExtension {
             StructType => structs::global(builder),
             RecordType => register_record(builder),
             EnumType => register_enum(builder),
+            SetType => set::global(builder),
             Map => extra::map(builder),
             Filter => extra::filter(builder),
             Partial => partial::partial(builder),
@@ -127,6 +137,7 @@ impl LibraryExtension {
             Typing => typing::globals::register_typing(builder),
             Internal => register_internal(builder),
             CallStack => call_stack::global(builder),
+            Math => math::global(builder),
         }
     }
 }
@@ -350,15 +350,19 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     /// getattr("banana", "split")("a") == ["b", "n", "n", ""] # equivalent to "banana".split("a")
     /// # "#);
     /// ```
-    #[starlark(speculative_exec_safe)]
     fn getattr<'v>(
         #[starlark(require = pos)] a: Value<'v>,
         #[starlark(require = pos)] attr: &str,
         #[starlark(require = pos)] default: Option<Value<'v>>,
-        heap: &'v Heap,
+        eval: &mut Evaluator<'v, '_, '_>,
     ) -> starlark::Result<Value<'v>> {
         // TODO(nga): this doesn't cache string hash, so it is suboptimal.
-        match a.get_attr(attr, heap)? {
+        // Not `speculative_exec_safe`: `get_attr_eval` may call Starlark code.
+        let v = match a.get_attr(attr, eval.heap())? {
+            Some(v) => Some(v),
+            None => a.get_ref().get_attr_eval(attr, eval)?,
+        };
+        match v {
             Some(v) => Ok(v),
             None => match default {
                 Some(x) => Ok(x),
@@ -717,6 +721,7 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     /// reversed(range(5))                     == [4, 3, 2, 1, 0]
     /// reversed("stressed".elems())           == ["d", "e", "s", "s", "e", "r", "t", "s"]
     /// reversed({"one": 1, "two": 2}.keys())  == ["two", "one"]
+    /// reversed(struct(a=1, b=2))             == ["b", "a"]
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
@@ -952,6 +957,29 @@ noop(hash)(foo)
         assert::eq("-2147483649", "int('-2147483649')");
     }
 
+    #[test]
+    fn test_int_parse_huge() {
+        let digits = "9".repeat(1000);
+        assert::eq(&digits, &format!("int('{}')", digits));
+        assert::eq(&format!("-{}", digits), &format!("int('-{}')", digits));
+        assert::eq("1000", &format!("len(str(int('{}')))", digits));
+    }
+
+    #[test]
+    fn test_int_parse_malformed() {
+        assert::fail(
+            "int('abc')",
+            "Cannot parse `abc` as an integer in base 10: invalid digit `a`",
+        );
+        assert::fail(
+            &format!("int('{}x')", "9".repeat(1000)),
+            "invalid digit `x`",
+        );
+        assert::fail("int('12', 2)", "invalid digit `2`");
+        assert::fail("int('')", "Cannot parse empty string as an integer");
+        assert::fail("int('0x')", "Cannot parse empty string as an integer");
+    }
+
     #[test]
     fn test_tuple() {
         let mut a = Assert::new();
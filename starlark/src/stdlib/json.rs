@@ -36,6 +36,7 @@ use crate::values::AllocValue;
 use crate::values::FrozenHeap;
 use crate::values::FrozenValue;
 use crate::values::Heap;
+use crate::values::JsonReaderOptions;
 use crate::values::Value;
 
 impl StarlarkTypeRepr for serde_json::Number {
@@ -215,10 +216,21 @@ pub(crate) fn json(globals: &mut GlobalsBuilder) {
             x.to_json()
         }
 
+        /// Decode a JSON string.
+        ///
+        /// JSON objects are decoded as dicts, or as structs if `objects_as_structs` is true.
         fn decode<'v>(
             #[starlark(require = pos)] x: &str,
+            #[starlark(require = named, default = false)] objects_as_structs: bool,
             heap: &'v Heap,
         ) -> anyhow::Result<Value<'v>> {
+            if objects_as_structs {
+                let options = JsonReaderOptions {
+                    objects_as_structs,
+                    ..JsonReaderOptions::default()
+                };
+                return heap.alloc_json_reader_with_options(x.as_bytes(), &options);
+            }
             Ok(heap.alloc(serde_json::from_str::<serde_json::Value>(x)?))
         }
     }
@@ -239,6 +251,21 @@ mod tests {
         a.eq("'[10]'", "json.encode([10])");
     }
 
+    #[test]
+    fn test_json_encode_cycle() {
+        let a = Assert::new();
+        a.fail(
+            "x = [1]\nx.append(x)\njson.encode(x)",
+            "Cycle detected when serializing value of type `list` to JSON",
+        );
+        a.fail(
+            "x = {}\nx['k'] = [x]\njson.encode(x)",
+            "Cycle detected when serializing value of type `dict` to JSON",
+        );
+        // Shared values which are not cycles are fine.
+        a.eq("'[[1],[1]]'", "x = [1]\njson.encode([x, x])");
+    }
+
     #[test]
     fn test_json_decode() {
         let a = Assert::new();
@@ -247,10 +274,29 @@ mod tests {
             "json.decode('[10, null, false, {\"k\": \"v\"}]')",
         );
 
+        // Object keys keep their order with the `preserve_order` feature.
+        #[cfg(feature = "preserve_order")]
+        a.eq("['z', 'a']", "list(json.decode('{\"z\": 1, \"a\": 2}'))");
+        #[cfg(not(feature = "preserve_order"))]
+        a.eq("['a', 'z']", "list(json.decode('{\"z\": 1, \"a\": 2}'))");
         a.eq("3.142", "json.decode('3.142')");
+        // Integers which do not fit into `u64` or `i64` are decoded as floats.
         a.eq(
-            "123456789123456789123456789",
+            "123456789123456789123456789.0",
             "json.decode('123456789123456789123456789')",
         );
     }
+
+    #[test]
+    fn test_json_decode_objects_as_structs() {
+        let a = Assert::new();
+        a.eq(
+            "struct(a = 1, b = [struct(c = None)])",
+            "json.decode('{\"a\": 1, \"b\": [{\"c\": null}]}', objects_as_structs = True)",
+        );
+        a.eq(
+            "{'a': 1}",
+            "json.decode('{\"a\": 1}', objects_as_structs = False)",
+        );
+    }
 }
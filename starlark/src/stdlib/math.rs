@@ -0,0 +1,449 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Integer math functions.
+
+use either::Either;
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::float::StarlarkFloat;
+use crate::values::none::NoneOr;
+use crate::values::types::int_or_big::StarlarkInt;
+use crate::values::types::int_or_big::StarlarkIntRef;
+
+#[starlark_module]
+pub(crate) fn global(builder: &mut GlobalsBuilder) {
+    /// Check if an integer is a square of an integer.
+    ///
+    /// Negative integers are never perfect squares.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// is_perfect_square(0)
+    /// is_perfect_square(49)
+    /// not is_perfect_square(50)
+    /// not is_perfect_square(-4)
+    /// # "#);
+    /// ```
+    fn is_perfect_square(#[starlark(require = pos)] n: StarlarkIntRef) -> anyhow::Result<bool> {
+        Ok(n.is_perfect_square())
+    }
+
+    /// Check if an integer is `base ** exponent` for some integer `base`
+    /// and some `exponent >= 2`.
+    ///
+    /// With `with_root = True`, return a tuple `(base, exponent)` with the largest
+    /// such exponent, or `None` if the integer is not a perfect power.
+    /// Negative integers are perfect powers only with odd exponents.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// is_perfect_power(8)
+    /// not is_perfect_power(12)
+    /// is_perfect_power(64, with_root = True) == (2, 6)
+    /// is_perfect_power(-27, with_root = True) == (-3, 3)
+    /// is_perfect_power(-16, with_root = True) == None
+    /// # "#);
+    /// ```
+    fn is_perfect_power(
+        #[starlark(require = pos)] n: StarlarkIntRef,
+        #[starlark(require = named, default = false)] with_root: bool,
+    ) -> anyhow::Result<Either<bool, NoneOr<(StarlarkInt, u32)>>> {
+        let power = n.perfect_power();
+        if with_root {
+            Ok(Either::Right(match power {
+                Some(power) => NoneOr::Other(power),
+                None => NoneOr::None,
+            }))
+        } else {
+            Ok(Either::Left(power.is_some()))
+        }
+    }
+
+    /// Get a pair of integers whose ratio is exactly equal to the float,
+    /// with a positive denominator, in lowest terms.
+    ///
+    /// Like Python's `float.as_integer_ratio`. Fails for infinities and NaN.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// as_integer_ratio(0.5) == (1, 2)
+    /// as_integer_ratio(-2.0) == (-2, 1)
+    /// as_integer_ratio(0.1) == (3602879701896397, 36028797018963968)
+    /// # "#);
+    /// ```
+    fn as_integer_ratio(
+        #[starlark(require = pos)] f: StarlarkFloat,
+    ) -> anyhow::Result<(StarlarkInt, StarlarkInt)> {
+        StarlarkInt::integer_ratio_f64(f.0)
+    }
+
+    /// Number of ways to choose `k` items from `n` items without repetition and without order,
+    /// like Python's `math.comb`.
+    ///
+    /// Returns zero if `k > n`, fails if `n` or `k` is negative.
+    /// Unlike Python, fails if both `k` and `n - k` are greater than `100000`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// comb(5, 2) == 10
+    /// comb(5, 0) == 1
+    /// comb(2, 5) == 0
+    /// # "#);
+    /// ```
+    fn comb(
+        #[starlark(require = pos)] n: StarlarkIntRef,
+        #[starlark(require = pos)] k: StarlarkIntRef,
+    ) -> anyhow::Result<StarlarkInt> {
+        StarlarkIntRef::comb(n, k)
+    }
+
+    /// Number of ways to choose `k` items from `n` items without repetition and with order,
+    /// like Python's `math.perm`.
+    ///
+    /// Returns zero if `k > n`, fails if `n` or `k` is negative.
+    /// Unlike Python, fails if `k` is greater than `100000`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// perm(5, 2) == 20
+    /// perm(5, 5) == 120
+    /// perm(2, 5) == 0
+    /// # "#);
+    /// ```
+    fn perm(
+        #[starlark(require = pos)] n: StarlarkIntRef,
+        #[starlark(require = pos)] k: StarlarkIntRef,
+    ) -> anyhow::Result<StarlarkInt> {
+        StarlarkIntRef::perm(n, k)
+    }
+
+    /// Quotient and remainder of integer division, `(x // y, x % y)`, like Python's `divmod`.
+    ///
+    /// The quotient is rounded towards negative infinity, so the remainder
+    /// has the sign of `y`. Fails if `y` is zero.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// divmod(7, 2) == (3, 1)
+    /// divmod(-7, 2) == (-4, 1)
+    /// divmod(7, -2) == (-4, -1)
+    /// # "#);
+    /// ```
+    fn divmod(
+        #[starlark(require = pos)] x: StarlarkIntRef,
+        #[starlark(require = pos)] y: StarlarkIntRef,
+    ) -> anyhow::Result<(StarlarkInt, StarlarkInt)> {
+        x.divmod(y)
+    }
+
+    /// Modular multiplicative inverse of `a` modulo `m`: the `x` in `[0, m)`
+    /// such that `a * x % m == 1`.
+    ///
+    /// Fails if `m` is not positive, or if `a` and `m` are not coprime,
+    /// so no inverse exists.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// mod_inverse(3, 11) == 4
+    /// mod_inverse(-3, 11) == 7
+    /// mod_inverse(5, 1) == 0
+    /// # "#);
+    /// ```
+    fn mod_inverse(
+        #[starlark(require = pos)] a: StarlarkIntRef,
+        #[starlark(require = pos)] m: StarlarkIntRef,
+    ) -> anyhow::Result<StarlarkInt> {
+        StarlarkIntRef::mod_inverse(a, m)
+    }
+
+    /// Return `k` if the integer is exactly `2 ** k`, otherwise `None`.
+    ///
+    /// Zero and negative integers are not powers of two.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// exact_log2(1) == 0
+    /// exact_log2(1024) == 10
+    /// exact_log2(1 << 200) == 200
+    /// exact_log2(1023) == None
+    /// exact_log2(0) == None
+    /// exact_log2(-8) == None
+    /// # "#);
+    /// ```
+    fn exact_log2(#[starlark(require = pos)] n: StarlarkIntRef) -> anyhow::Result<Option<u64>> {
+        Ok(n.exact_log2())
+    }
+
+    /// Return `k` if the integer is exactly `10 ** k`, otherwise `None`.
+    ///
+    /// Zero and negative integers are not powers of ten.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// exact_log10(1) == 0
+    /// exact_log10(1000) == 3
+    /// exact_log10(1001) == None
+    /// exact_log10(-10) == None
+    /// # "#);
+    /// ```
+    fn exact_log10(#[starlark(require = pos)] n: StarlarkIntRef) -> anyhow::Result<Option<u64>> {
+        Ok(n.exact_log10())
+    }
+
+    /// Return a pseudo-random integer `r` with `lo <= r < hi`, determined by `seed` only.
+    ///
+    /// There is no external entropy: the same arguments always give the same result,
+    /// on every platform. The generator is
+    /// [SplitMix64](https://prng.di.unimi.it/splitmix64.c), seeded from the
+    /// two's complement of `seed`, with rejection sampling for uniform results.
+    /// Seeds and bounds can be arbitrarily large. Fails if `lo >= hi`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// rand(42, 8000, 9000) == rand(42, 8000, 9000)
+    /// rand(42, 8000, 9000) in range(8000, 9000)
+    /// rand(7, 0, 1) == 0
+    /// 0 <= rand(1, 0, 1 << 100) and rand(1, 0, 1 << 100) < 1 << 100
+    /// # "#);
+    /// ```
+    fn rand(
+        #[starlark(require = pos)] seed: StarlarkIntRef,
+        #[starlark(require = pos)] lo: StarlarkIntRef,
+        #[starlark(require = pos)] hi: StarlarkIntRef,
+    ) -> anyhow::Result<StarlarkInt> {
+        StarlarkIntRef::seeded_random(seed, lo, hi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_is_perfect_square_big() {
+        assert::all_true(
+            r#"
+is_perfect_square(123456789123456789 * 123456789123456789)
+not is_perfect_square(123456789123456789 * 123456789123456789 + 1)
+not is_perfect_square(123456789123456789 * 123456789123456789 - 1)
+not is_perfect_square(-(123456789123456789 * 123456789123456789))
+is_perfect_square(1)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_is_perfect_power_big() {
+        assert::pass(
+            r#"
+def pow(base, exponent):
+    r = 1
+    for _ in range(exponent):
+        r *= base
+    return r
+assert_eq(is_perfect_power(pow(3, 41), with_root = True), (3, 41))
+assert_eq(is_perfect_power(pow(6, 35), with_root = True), (6, 35))
+assert_true(not is_perfect_power(pow(6, 35) * 5))
+assert_eq(is_perfect_power(1 << 100, with_root = True), (2, 100))
+assert_true(not is_perfect_power((1 << 100) + 1))
+assert_eq(is_perfect_power(-(1 << 99), with_root = True), (-2, 99))
+assert_eq(is_perfect_power(-(1 << 100), with_root = True), (-16, 25))
+assert_eq(is_perfect_power(0, with_root = True), (0, 2))
+assert_eq(is_perfect_power(1, with_root = True), (1, 2))
+assert_eq(is_perfect_power(-1, with_root = True), (-1, 3))
+assert_true(not is_perfect_power(2))
+assert_true(not is_perfect_power(-2))
+assert_eq(is_perfect_power(pow(12, 30), with_root = True), (12, 30))
+assert_eq(is_perfect_power(-pow(10, 45), with_root = True), (-10, 45))
+assert_eq(is_perfect_power(pow(pow(7, 11) * 2, 4), with_root = True), (pow(7, 11) * 2, 4))
+# Only prime exponents are tried.
+assert_true(not is_perfect_power((1 << 2000) + 1))
+"#,
+        );
+    }
+    #[test]
+    fn test_as_integer_ratio() {
+        assert::all_true(
+            r#"
+as_integer_ratio(0.0) == (0, 1)
+as_integer_ratio(-0.0) == (0, 1)
+as_integer_ratio(3.0) == (3, 1)
+as_integer_ratio(-0.75) == (-3, 4)
+as_integer_ratio(1e300) == (int(1e300), 1)
+as_integer_ratio(1e22) == (10000000000000000000000, 1)
+as_integer_ratio(5e-324) == (1, 1 << 1074)
+"#,
+        );
+        assert::pass(
+            r#"
+for f in [0.1, 1.0 / 3, -123.456, 1e-10, 6.02214076e23, 1.7976931348623157e308]:
+    n, d = as_integer_ratio(f)
+    assert_eq(float(n) / float(d), f)
+"#,
+        );
+        assert::fail(
+            "as_integer_ratio(float('inf'))",
+            "Cannot convert float `inf` to integer ratio",
+        );
+        assert::fail(
+            "as_integer_ratio(float('nan'))",
+            "Cannot convert float `NaN` to integer ratio",
+        );
+    }
+
+    #[test]
+    fn test_comb_perm_big() {
+        assert::all_true(
+            r#"
+comb(100, 50) == 100891344545564193334812497256
+comb(1000, 3) == 166167000
+comb(1000, 997) == 166167000
+perm(30, 30) == 265252859812191058636308480000000
+perm(100, 20) == 1303995018204712451095685346159820800000
+comb(123456789123456789, 2) == 7620789390336839196082915813366866
+comb(123456789123456789, 123456789123456789) == 1
+perm(123456789123456789, 0) == 1
+comb(0, 0) == 1
+comb(1, 2) == 0
+perm(1 << 70, (1 << 70) + 1) == 0
+comb(1 << 70, (1 << 70) - 1) == 1 << 70
+comb(100001, 100000) == 100001
+"#,
+        );
+        assert::fail(
+            "perm(1 << 70, 1 << 69)",
+            "`k` is too large: 590295810358705651712, at most 100000 is supported",
+        );
+        assert::fail(
+            "comb(1 << 70, 1 << 69)",
+            "`k` is too large: 590295810358705651712, at most 100000 is supported",
+        );
+        assert::fail(
+            "perm(200000, 100001)",
+            "`k` is too large: 100001, at most 100000 is supported",
+        );
+        assert::fail(
+            "comb(-1, 2)",
+            "`n` must be a non-negative integer, got `-1`",
+        );
+        assert::fail(
+            "perm(5, -2)",
+            "`k` must be a non-negative integer, got `-2`",
+        );
+    }
+
+    #[test]
+    fn test_divmod_big_mixed_signs() {
+        assert::pass(
+            r#"
+a = 123456789123456789123456789
+b = 987654321987
+values = [a, -a, b, -b, a * b + 1, -(a * b) - 1, 7, -7, 1 << 64, -(1 << 64)]
+for x in values:
+    for y in values:
+        q, r = divmod(x, y)
+        assert_eq(q, x // y)
+        assert_eq(r, x % y)
+        assert_eq(q * y + r, x)
+        assert_true(abs(r) < abs(y))
+        assert_true(r == 0 or (r < 0) == (y < 0))
+"#,
+        );
+        assert::eq(
+            "(-124999998861, 61688132605305)",
+            "divmod(-123456789123456789123456789, 987654321987654)",
+        );
+        assert::fail("divmod(1 << 70, 0)", "Floor division by zero");
+    }
+
+    #[test]
+    fn test_mod_inverse_big() {
+        assert::all_true(
+            r#"
+mod_inverse(123456789123456789, (1 << 127) - 1) == 33785341412481728823582264134804948706
+mod_inverse(2, 115792089237316195423570985008687907853269984665640564039457584007908834671663) == 57896044618658097711785492504343953926634992332820282019728792003954417335832
+mod_inverse(-1000000000000000000000000000000, 115792089237316195423570985008687907853269984665640564039457584007908834671663) == 59783570321893033769538072326774032414511924014186326055942936324569442420174
+123456789123456789 * mod_inverse(123456789123456789, 1 << 80) % (1 << 80) == 1
+"#,
+        );
+        assert::fail("mod_inverse(6, 9)", "`6` is not invertible modulo `9`");
+        assert::fail("mod_inverse(1 << 70, 1 << 65)", "is not invertible modulo");
+        assert::fail("mod_inverse(3, 0)", "Modulus must be positive, got `0`");
+        assert::fail("mod_inverse(3, -(1 << 70))", "Modulus must be positive");
+    }
+
+    #[test]
+    fn test_exact_log_big() {
+        assert::all_true(
+            r#"
+exact_log2(1 << 30) == 30
+exact_log2(2147483648) == 31
+exact_log2(2147483647) == None
+exact_log2(1 << 1000) == 1000
+exact_log2((1 << 1000) + 1) == None
+exact_log2((1 << 1000) - 1) == None
+exact_log2(3 << 1000) == None
+exact_log2(-(1 << 1000)) == None
+exact_log10(1000000000) == 9
+exact_log10(10000000000) == 10
+exact_log10(int("1" + "0" * 300)) == 300
+exact_log10(int("1" + "0" * 300) + 1) == None
+exact_log10(int("1" + "0" * 300) - 1) == None
+exact_log10(int("2" + "0" * 300)) == None
+exact_log10(1 << 300) == None
+exact_log10(-int("1" + "0" * 300)) == None
+"#,
+        );
+    }
+
+    #[test]
+    fn test_rand() {
+        // Fixed values: the algorithm must not change between platforms or versions.
+        assert::all_true(
+            r#"
+rand(42, 8000, 9000) == 8105
+rand(0, 0, 100) == 47
+rand(-1, 0, 100) == 90
+rand(12345, 0, 2) == 0
+rand(1 << 100, -(1 << 200), 1 << 200) == -1567220063369173475072784028977001190512732354302535918585551
+rand(-(1 << 70), 0, 1000000000000000000000000000000) == 820210857523048115476334952880
+"#,
+        );
+        assert::pass(
+            r#"
+seen = {}
+for seed in range(1000):
+    r = rand(seed, -3, 7)
+    assert_true(r >= -3 and r < 7)
+    seen[r] = True
+assert_eq(sorted(seen), list(range(-3, 7)))
+for seed in range(100):
+    r = rand(seed, 1 << 64, (1 << 64) + 3)
+    assert_true(r >= 1 << 64 and r < (1 << 64) + 3)
+    assert_eq(r, rand(seed, 1 << 64, (1 << 64) + 3))
+"#,
+        );
+        assert::fail(
+            "rand(1, 5, 5)",
+            "Empty range for random integer: `lo` is 5, `hi` is 5",
+        );
+        assert::fail("rand(1, 1 << 70, 0)", "Empty range for random integer");
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `set()` constructor and methods of the `set` type.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::collections::SmallSet;
+use crate::environment::GlobalsBuilder;
+use crate::environment::MethodsBuilder;
+use crate::values::error::ValueError;
+use crate::values::none::NoneType;
+use crate::values::set::value::FrozenSet;
+use crate::values::set::Set;
+use crate::values::set::SetMut;
+use crate::values::Heap;
+use crate::values::Value;
+
+#[starlark_module]
+pub(crate) fn global(builder: &mut GlobalsBuilder) {
+    /// Create a set, optionally from the elements of an iterable.
+    ///
+    /// The elements must be hashable. Duplicates are dropped,
+    /// and the set iterates in the order elements were first seen.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// len(set()) == 0
+    /// list(set([3, 1, 3, 2])) == [3, 1, 2]
+    /// set("abc".elems()) == set(["c", "b", "a"])
+    /// # "#);
+    /// ```
+    #[starlark(as_type = FrozenSet, speculative_exec_safe)]
+    fn set<'v>(
+        #[starlark(require = pos)] a: Option<Value<'v>>,
+        heap: &'v Heap,
+    ) -> starlark::Result<Set<'v>> {
+        let mut content = SmallSet::new();
+        if let Some(a) = a {
+            for x in a.iterate(heap)? {
+                content.insert_hashed(x.get_hashed()?);
+            }
+        }
+        Ok(Set::new(content))
+    }
+}
+
+#[starlark_module]
+pub(crate) fn set_methods(registry: &mut MethodsBuilder) {
+    /// Add an element to the set.
+    ///
+    /// Adding an element already present does not change its position.
+    /// Fails if the element is unhashable, or the set is frozen or has active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.add(3)
+    /// x.add(1)
+    /// list(x) == [1, 2, 3]
+    /// # "#);
+    /// ```
+    fn add<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> starlark::Result<NoneType> {
+        let value = value.get_hashed()?;
+        SetMut::from_value(this)?.insert_hashed(value);
+        Ok(NoneType)
+    }
+
+    /// Remove an element from the set.
+    ///
+    /// Fails if the element is not present or unhashable,
+    /// or the set is frozen or has active iterators.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2, 3])
+    /// x.remove(2)
+    /// list(x) == [1, 3]
+    /// # "#);
+    /// ```
+    ///
+    /// ```
+    /// # starlark::assert::fail(r#"
+    /// set([1]).remove(2) # error: not found
+    /// # "#, "not found");
+    /// ```
+    fn remove<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> starlark::Result<NoneType> {
+        let hashed = value.get_hashed()?;
+        if SetMut::from_value(this)?.remove_hashed(hashed) {
+            Ok(NoneType)
+        } else {
+            Err(crate::Error::new_other(ValueError::KeyNotFound(
+                value.to_repr(),
+            )))
+        }
+    }
+}
@@ -26,7 +26,9 @@ use crate as starlark;
 use crate::codemap::Span;
 use crate::codemap::Spanned;
 use crate::environment::GlobalsBuilder;
+use crate::environment::MethodsBuilder;
 use crate::eval::Arguments;
+use crate::eval::Evaluator;
 use crate::typing::callable::TyCallable;
 use crate::typing::error::TypingOrInternalError;
 use crate::typing::function::Arg;
@@ -36,10 +38,21 @@ use crate::typing::structs::TyStruct;
 use crate::typing::Param;
 use crate::typing::ParamSpec;
 use crate::typing::Ty;
+use crate::values::dict::AllocDict;
 use crate::values::layout::heap::profile::arc_str::ArcStr;
+use crate::values::list_or_tuple::UnpackListOrTuple;
 use crate::values::structs::value::FrozenStruct;
 use crate::values::structs::value::Struct;
+use crate::values::structs::StructRef;
 use crate::values::Heap;
+use crate::values::StringValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum StructError {
+    #[error("Cannot create a struct with {0} fields, the maximum is {1}")]
+    TooManyFields(usize, usize),
+}
 
 #[derive(
     Allocative, Clone, Copy, Dupe, Debug, Eq, PartialEq, Hash, Ord, PartialOrd
@@ -93,12 +106,107 @@ pub fn global(builder: &mut GlobalsBuilder) {
         ty_custom_function = StructType,
         as_type = FrozenStruct,
     )]
-    fn r#struct<'v>(args: &Arguments<'v, '_>, heap: &'v Heap) -> starlark::Result<Struct<'v>> {
-        args.no_positional_args(heap)?;
+    fn r#struct<'v>(
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Struct<'v>> {
+        args.no_positional_args(eval.heap())?;
         // TODO(nga): missing optimization: practically most `struct` invocations are
         //   performed with fixed named arguments, e.g. `struct(a = 1, b = 2)`.
         //   In this case we can avoid allocating the map, but instead
         //   allocate field index once at compilation time and store field values in a vector.
-        Ok(Struct::new(args.names_map()?))
+        if let Some(max) = eval.max_struct_fields {
+            // Check before building the map. Repeated names are counted twice:
+            // such calls fail anyway, unless `**kwargs` may overwrite named arguments,
+            // and then the limit applies to the number of arguments.
+            let len = args.0.names.len() + args.unpack_kwargs()?.map_or(0, |kwargs| kwargs.len());
+            if len > max {
+                return Err(crate::Error::new_other(StructError::TooManyFields(
+                    len, max,
+                )));
+            }
+        }
+        let fields = if eval.struct_duplicate_fields_overwrite {
+            args.names_map_overwrite()?
+        } else {
+            args.names_map()?
+        };
+        Ok(Struct::new(fields))
+    }
+
+    /// Transpose a list of structs into a struct of lists, one list per field.
+    ///
+    /// Fields are ordered as in the first struct. By default all structs must have
+    /// the same fields. With `fill_missing = True`, the result has all fields
+    /// of all structs, and `None` is used where a struct does not have a field.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// columnar([struct(a = 1, b = 2), struct(a = 3, b = 4)]) == struct(a = [1, 3], b = [2, 4])
+    /// columnar([struct(a = 1), struct(b = 2)], fill_missing = True) == struct(a = [1, None], b = [None, 2])
+    /// # "#);
+    /// ```
+    fn columnar<'v>(
+        #[starlark(require = pos)] structs: UnpackListOrTuple<Value<'v>>,
+        #[starlark(require = named, default = false)] fill_missing: bool,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        crate::values::structs::columnar(heap, &structs.items, fill_missing)
+    }
+}
+
+#[starlark_module]
+pub(crate) fn struct_methods(builder: &mut MethodsBuilder) {
+    /// Serialize the struct to a JSON string.
+    ///
+    /// Equivalent to `json.encode(s)`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// struct(a = 1, b = "x").to_json() == '{"a":1,"b":"x"}'
+    /// # "#);
+    /// ```
+    fn to_json<'v>(this: Value<'v>) -> anyhow::Result<String> {
+        this.to_json()
+    }
+
+    /// Create a new dict with the struct fields as entries.
+    ///
+    /// Keys are the field names listed by `dir(s)`, in field order rather than sorted.
+    /// The dict is a copy: changing it does not change the struct.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// struct(b = 1, a = "x").to_dict() == {"b": 1, "a": "x"}
+    /// list(struct(b = 1, a = "x").to_dict()) == ["b", "a"]
+    /// struct().to_dict() == {}
+    /// # "#);
+    /// ```
+    fn to_dict<'v>(this: StructRef<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc(AllocDict(this.iter())))
+    }
+
+    /// List the struct field names, in field order or, with `sorted = True`, sorted.
+    ///
+    /// Unlike `dir(s)`, the result does not include struct methods.
+    /// Fields shadow methods, so on a struct with a field named `fields`,
+    /// `s.fields` is that field.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// struct(b = 1, a = 2).fields() == ["b", "a"]
+    /// struct(b = 1, a = 2).fields(sorted = True) == ["a", "b"]
+    /// struct().fields() == []
+    /// # "#);
+    /// ```
+    fn fields<'v>(
+        this: StructRef<'v>,
+        #[starlark(require = named, default = false)] sorted: bool,
+    ) -> anyhow::Result<Vec<StringValue<'v>>> {
+        let mut names: Vec<StringValue<'v>> = this.iter().map(|(k, _)| k).collect();
+        if sorted {
+            names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        }
+        Ok(names)
     }
 }
@@ -20,6 +20,8 @@ mod attrs;
 mod docs;
 mod freeze;
 mod module;
+mod struct_access;
 mod trace;
+mod unpack_struct;
 mod unpack_value;
 mod unpack_value_attr;
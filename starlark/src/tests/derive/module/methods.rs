@@ -29,6 +29,7 @@ use crate::environment::MethodsStatic;
 use crate::values::AllocFrozenValue;
 use crate::values::FrozenHeap;
 use crate::values::FrozenValue;
+use crate::values::Heap;
 use crate::values::StarlarkValue;
 use crate::values::Value;
 use crate::values::ValueLike;
@@ -59,6 +60,10 @@ impl<'v> StarlarkValue<'v> for Applaud {
         static RES: MethodsStatic = MethodsStatic::new();
         RES.methods(methods)
     }
+
+    fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
+        (attribute == "test_method").then(|| heap.alloc(self.value))
+    }
 }
 
 impl AllocFrozenValue for Applaud {
@@ -73,3 +78,17 @@ fn test_receiver_can_be_named_anything() {
     a.globals_add(|g| g.set("x", g.alloc(Applaud { value: 10 })));
     a.eq("13", "x.test_method(this=3)");
 }
+
+#[test]
+fn test_methods_shadow_attrs() {
+    // Unlike struct fields, attributes of other types do not shadow methods.
+    let mut a = Assert::new();
+    a.globals_add(|g| g.set("x", g.alloc(Applaud { value: 10 })));
+    a.all_true(
+        r#"
+x.test_method(this=3) == 13
+type(x.test_method) == "function"
+type(getattr(x, "test_method")) == "function"
+"#,
+    );
+}
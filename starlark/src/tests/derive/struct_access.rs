@@ -0,0 +1,72 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::values::list::ListRef;
+use crate::values::structs::StructAccess;
+use crate::values::structs::StructAccessField;
+use crate::values::Value;
+
+#[derive(StructAccess)]
+struct ServerConfig<'v> {
+    #[starlark(value)]
+    value: Value<'v>,
+    host: StructAccessField<&'v str>,
+    #[starlark(field = "port")]
+    port_number: StructAccessField<u32>,
+    tags: StructAccessField<&'v ListRef<'v>>,
+    r#type: StructAccessField<String>,
+    missing: StructAccessField<i32>,
+}
+
+#[test]
+fn test_struct_access() {
+    let a = Assert::new();
+    let v = a.pass("struct(host = 'localhost', port = 8080, tags = ['a'], type = 'web')");
+    let config = ServerConfig::new(v.value()).unwrap();
+    assert!(!config.port_number.is_cached());
+    assert_eq!(8080, *config.port_number().unwrap());
+    assert!(config.port_number.is_cached());
+    assert_eq!(8080, *config.port_number().unwrap());
+    assert_eq!("localhost", *config.host().unwrap());
+    assert_eq!(1, config.tags().unwrap().len());
+    assert_eq!("web", config.r#type().unwrap());
+    assert!(!config.missing.is_cached());
+    assert_eq!(
+        "Struct has no field `missing`",
+        config.missing().unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_struct_access_errors() {
+    let a = Assert::new();
+    let v = a.pass("struct(host = 1, port = 'x')");
+    let config = ServerConfig::new(v.value()).unwrap();
+    assert_eq!(
+        "Expected `str` for struct field `host`, got value of type `int`",
+        config.host().unwrap_err().to_string()
+    );
+    assert!(config.port_number().is_err());
+
+    let v = a.pass("{'host': 'localhost'}");
+    assert_eq!(
+        "Expected `struct`, got value of type `dict`",
+        ServerConfig::new(v.value()).err().unwrap().to_string()
+    );
+}
@@ -0,0 +1,107 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::values::list::UnpackList;
+use crate::values::structs::StructRef;
+use crate::values::structs::UnpackStruct;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::UnpackValue;
+
+#[derive(UnpackStruct, Debug, PartialEq)]
+struct ServerConfig<'v> {
+    host: &'v str,
+    #[starlark(field = "port")]
+    port_number: u32,
+    r#type: String,
+}
+
+#[derive(UnpackStruct, Debug, PartialEq)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[test]
+fn test_unpack_struct() {
+    let a = Assert::new();
+    let v = a.pass("struct(host = 'localhost', port = 8080, type = 'web', extra = [])");
+    assert_eq!(
+        Some(ServerConfig {
+            host: "localhost",
+            port_number: 8080,
+            r#type: "web".to_owned(),
+        }),
+        ServerConfig::unpack_value(v.value())
+    );
+    assert_eq!(
+        StructRef::starlark_type_repr(),
+        ServerConfig::starlark_type_repr()
+    );
+}
+
+#[test]
+fn test_unpack_struct_errors() {
+    let a = Assert::new();
+    let v = a.pass("struct(x = 1)");
+    assert_eq!(None, Point::unpack_value(v.value()));
+    assert_eq!(
+        "Struct has no field `y`",
+        Point::unpack_value_err(v.value()).unwrap_err().to_string()
+    );
+
+    let v = a.pass("struct(x = 1, y = 'a')");
+    assert_eq!(None, Point::unpack_value(v.value()));
+    assert_eq!(
+        "Expected `int` for struct field `y`, got value of type `string`",
+        Point::unpack_value_err(v.value()).unwrap_err().to_string()
+    );
+
+    let v = a.pass("{'x': 1, 'y': 2}");
+    assert_eq!(
+        "Expected `struct`, got value of type `dict`",
+        Point::unpack_value_err(v.value()).unwrap_err().to_string()
+    );
+}
+
+#[starlark_module]
+fn point_functions(builder: &mut GlobalsBuilder) {
+    fn sum_points(points: UnpackList<Point>) -> anyhow::Result<i32> {
+        Ok(points.items.iter().map(|p| p.x + p.y).sum())
+    }
+
+    fn norm1(p: Point) -> anyhow::Result<i32> {
+        Ok(p.x.abs() + p.y.abs())
+    }
+}
+
+#[test]
+fn test_unpack_struct_param() {
+    let mut a = Assert::new();
+    a.globals_add(point_functions);
+    a.eq(
+        "10",
+        "sum_points([struct(x = 1, y = 2), struct(x = 3, y = 4)])",
+    );
+    a.eq("3", "norm1(struct(x = -1, y = 2))");
+    a.fail("norm1(struct(x = 1))", "Struct has no field `y`");
+    a.fail("norm1(p = struct(x = 1, y = None))", "for struct field `y`");
+}
@@ -35,8 +35,8 @@ impl<'v> Freeze for Test<Value<'v>> {
             field: self.field.freeze(freezer)?,
         };
         let members = ListRef::from_value(test.field.to_value()).unwrap();
-        assert_eq!(members[0].unpack_num().unwrap().as_int().unwrap(), 1);
-        assert_eq!(members[1].unpack_num().unwrap().as_int().unwrap(), 2);
+        assert_eq!(members[0].unpack_num_ref().unwrap().as_int().unwrap(), 1);
+        assert_eq!(members[1].unpack_num_ref().unwrap().as_int().unwrap(), 2);
         Ok(test)
     }
 }
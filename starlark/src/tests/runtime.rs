@@ -25,11 +25,16 @@ use std::sync::atomic::Ordering;
 use derive_more::Display;
 use once_cell::sync::Lazy;
 use starlark_derive::starlark_module;
+use starlark_syntax::syntax::AstModule;
+use starlark_syntax::syntax::Dialect;
 
 use crate as starlark;
 use crate::assert;
 use crate::assert::Assert;
+use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
+use crate::environment::LibraryExtension;
+use crate::environment::Module;
 use crate::eval::Evaluator;
 use crate::values::any::StarlarkAny;
 use crate::values::FrozenHeap;
@@ -233,3 +238,143 @@ fn test_display_debug() {
     assert_eq!(format!("{:?}", v), "FrozenValue(\"test\")");
     assert_eq!(format!("{:#?}", v), "FrozenValue(\n    \"test\",\n)");
 }
+
+#[test]
+fn test_strict_numeric_equality() {
+    fn eval(strict: bool) -> String {
+        let ast = AstModule::parse(
+            "strict.star",
+            r#"
+def f(a, b):
+    return a == b
+
+s = struct(x = 1, y = struct(z = [2.5]))
+[
+    1 == 1.0,
+    1.0 != 1,
+    s == struct(x = 1.0, y = struct(z = [2.5])),
+    s == struct(x = 1, y = struct(z = [2.5])),
+    {1: "a"} == {1.0: "a"},
+    1.0 in [1],
+    (1 << 70) == float(1 << 70),
+    1 < 1.5,
+    f(2, 2.0),
+    len(dict([(1, "a"), (1.0, "b")])) == 1,
+    {1: "a"}.get(1.0) == "a",
+    1.0 in {1: "a"},
+]
+"#
+            .to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let module = Module::new();
+        let globals = Globals::extended_by(&[LibraryExtension::StructType]);
+        let mut eval = Evaluator::new(&module);
+        eval.enable_strict_numeric_equality(strict);
+        eval.eval_module(ast, &globals).unwrap().to_repr()
+    }
+
+    assert_eq!(
+        "[True, False, True, True, True, True, True, True, True, True, True, True]",
+        eval(false)
+    );
+    // Dict keys are compared with regular equality, so the dict comparison is `True`.
+    assert_eq!(
+        "[False, True, False, True, True, False, False, True, False, True, True, True]",
+        eval(true)
+    );
+}
+
+#[test]
+fn test_strict_numeric_equality_eval_function() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    let f = eval
+        .eval_module(
+            AstModule::parse(
+                "f.star",
+                "lambda a, b: a == b".to_owned(),
+                &Dialect::Extended,
+            )
+            .unwrap(),
+            &Globals::standard(),
+        )
+        .unwrap();
+    let one = eval.heap().alloc(1);
+    let one_float = eval.heap().alloc(1.0);
+    assert!(eval
+        .eval_function(f, &[one, one_float], &[])
+        .unwrap()
+        .to_bool());
+    eval.enable_strict_numeric_equality(true);
+    assert!(!eval
+        .eval_function(f, &[one, one_float], &[])
+        .unwrap()
+        .to_bool());
+    // `Value::equals` always uses regular equality.
+    assert!(one.equals(one_float).unwrap());
+}
+
+#[test]
+fn test_max_struct_fields() {
+    let globals = Globals::extended_by(&[LibraryExtension::StructType]);
+    let eval = |code: &str| {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_max_struct_fields(Some(2));
+        eval.eval_module(
+            AstModule::parse("max.star", code.to_owned(), &Dialect::Extended).unwrap(),
+            &globals,
+        )
+        .map(|v| v.to_repr())
+        .map_err(|e| e.to_string())
+    };
+    assert_eq!(
+        Ok("struct(a=1, b=2)".to_owned()),
+        eval("struct(a = 1, b = 2)")
+    );
+    let err = eval("struct(a = 1, b = 2, c = 3)").unwrap_err();
+    assert!(
+        err.contains("Cannot create a struct with 3 fields, the maximum is 2"),
+        "{err}"
+    );
+    let err = eval("struct(**{str(i): i for i in range(100)})").unwrap_err();
+    assert!(
+        err.contains("Cannot create a struct with 100 fields, the maximum is 2"),
+        "{err}"
+    );
+    let err = eval("struct(a = 1, **{'b': 2, 'c': 3})").unwrap_err();
+    assert!(
+        err.contains("Cannot create a struct with 3 fields, the maximum is 2"),
+        "{err}"
+    );
+    assert_eq!(
+        Ok("struct(a=1, b=2)".to_owned()),
+        eval("struct(a = 1, **{'b': 2})")
+    );
+}
+
+#[test]
+fn test_struct_duplicate_fields_overwrite() {
+    let globals = Globals::extended_by(&[LibraryExtension::StructType]);
+    let eval = |overwrite: bool, code: &str| {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_struct_duplicate_fields_overwrite(overwrite);
+        let ast = AstModule::parse("dup.star", code.to_owned(), &Dialect::Extended)
+            .map_err(|e| e.to_string())?;
+        eval.eval_module(ast, &globals)
+            .map(|v| v.to_repr())
+            .map_err(|e| e.to_string())
+    };
+    let code = "struct(a = 1, b = 2, **{'c': 3, 'a': 4})";
+    let err = eval(false, code).unwrap_err();
+    assert!(err.contains("Argument `a` occurs more than once"), "{err}");
+    assert_eq!(Ok("struct(a=4, b=2, c=3)".to_owned()), eval(true, code));
+    // Literal repeats are a compile error in both modes.
+    for overwrite in [false, true] {
+        let err = eval(overwrite, "struct(a = 1, a = 2)").unwrap_err();
+        assert!(err.contains("repeated named argument"), "{err}");
+    }
+}
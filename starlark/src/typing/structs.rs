@@ -30,6 +30,7 @@ use crate::typing::TyBasic;
 use crate::typing::TypingBinOp;
 use crate::typing::TypingOracleCtx;
 use crate::values::layout::heap::profile::arc_str::ArcStr;
+use crate::values::structs::value::struct_methods;
 use crate::values::structs::StructRef;
 use crate::values::typing::type_compiled::alloc::TypeMatcherAlloc;
 use crate::values::typing::type_compiled::matcher::TypeMatcher;
@@ -53,6 +54,23 @@ impl TyStruct {
             extra: true,
         }
     }
+
+    /// Type of `self + other`, where fields of `other` override fields of `self`.
+    fn merge(&self, other: &TyStruct) -> TyStruct {
+        let mut fields = Vec::new();
+        for (k, v) in self.fields.iter() {
+            if !other.fields.contains_key(k) {
+                // Extra fields of `other` might override this field with any type.
+                let ty = if other.extra { Ty::any() } else { v.clone() };
+                fields.push((k.dupe(), ty));
+            }
+        }
+        fields.extend(other.fields.iter().map(|(k, v)| (k.dupe(), v.clone())));
+        TyStruct {
+            fields: SortedMap::from_iter(fields),
+            extra: self.extra || other.extra,
+        }
+    }
 }
 
 impl TyCustomImpl for TyStruct {
@@ -70,15 +88,39 @@ impl TyCustomImpl for TyStruct {
                     Err(())
                 }
             }
+            TypingBinOp::Add => {
+                if let TyBasic::Custom(rhs) = rhs {
+                    if let Some(rhs) = rhs.0.as_any().downcast_ref::<TyStruct>() {
+                        return Ok(Ty::custom(self.merge(rhs)));
+                    }
+                }
+                if ctx.intersects_basic(&TyBasic::custom(TyStruct::any()), rhs) {
+                    Ok(Ty::custom(TyStruct::any()))
+                } else {
+                    Err(())
+                }
+            }
             _ => Err(()),
         }
     }
 
+    fn iter_item(&self) -> Result<Ty, ()> {
+        // Iterating a struct yields field names.
+        Ok(Ty::string())
+    }
+
     fn attribute(&self, attr: &str) -> Result<Ty, ()> {
-        match self.fields.get(attr) {
-            Some(ty) => Ok(ty.clone()),
-            None if self.extra => Ok(Ty::any()),
-            _ => Err(()),
+        // Fields shadow methods, same as at runtime.
+        if let Some(ty) = self.fields.get(attr) {
+            return Ok(ty.clone());
+        }
+        if self.extra {
+            // Unknown fields may shadow methods.
+            return Ok(Ty::any());
+        }
+        match struct_methods().and_then(|methods| methods.get(attr)) {
+            Some(method) => Ok(Ty::of_value(method)),
+            None => Err(()),
         }
     }
 
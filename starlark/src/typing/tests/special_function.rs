@@ -28,6 +28,31 @@ def test():
     );
 }
 
+#[test]
+fn test_special_function_struct_add() {
+    TypeCheck::new().ty("x").ty("y").check(
+        "struct_add",
+        r#"
+def test(s: struct):
+    x = struct(a = 1, b = "test") + struct(a = "x", c = [1])
+    y = struct(a = 1) + s
+    struct(a = 1) + 1
+"#,
+    );
+}
+
+#[test]
+fn test_special_function_struct_iter() {
+    TypeCheck::new().ty("x").ty("y").check(
+        "struct_iter",
+        r#"
+def test(s: struct):
+    x = [k for k in struct(a = 1)]
+    y = [k for k in s]
+"#,
+    );
+}
+
 #[test]
 fn test_test_new_syntax_without_dot_type() {
     TypeCheck::new().check(
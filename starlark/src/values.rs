@@ -51,6 +51,9 @@ pub use crate::values::freeze::Freeze;
 pub use crate::values::frozen_ref::FrozenRef;
 pub use crate::values::frozen_ref::OwnedFrozenRef;
 pub use crate::values::iter::StarlarkIterator;
+pub use crate::values::json_reader::JsonReaderOptions;
+pub use crate::values::json_writer::WriteJsonError;
+pub use crate::values::json_writer::JSON_WRITE_CHUNK_SIZE;
 pub use crate::values::layout::complex::ValueTypedComplex;
 pub use crate::values::layout::heap::heap_type::Freezer;
 pub use crate::values::layout::heap::heap_type::FrozenHeap;
@@ -89,6 +92,7 @@ pub use crate::values::types::list_or_tuple;
 pub use crate::values::types::none;
 pub use crate::values::types::range;
 pub use crate::values::types::record;
+pub use crate::values::types::set;
 pub use crate::values::types::starlark_value_as_type;
 pub use crate::values::types::string;
 pub use crate::values::types::structs;
@@ -99,19 +103,29 @@ pub use crate::values::value_of_unchecked::FrozenValueOfUnchecked;
 pub use crate::values::value_of_unchecked::ValueOfUnchecked;
 
 mod alloc_value;
+#[cfg(feature = "ciborium")]
+mod cbor;
 mod comparison;
+mod deep_size;
+mod deeply_frozen;
 pub(crate) mod demand;
+mod equals_explain;
+mod equals_json;
 pub(crate) mod error;
 mod freeze;
 pub(crate) mod frozen_ref;
 mod index;
 pub(crate) mod iter;
+mod json_reader;
+mod json_writer;
 pub(crate) mod layout;
 pub(crate) mod num;
 mod owned;
 pub(crate) mod recursive_repr_or_json_guard;
 mod stack_guard;
 pub(crate) mod starlark_type_id;
+pub(crate) mod strict_numeric_equality;
+mod total_cmp;
 mod trace;
 pub(crate) mod traits;
 pub mod type_repr;
@@ -0,0 +1,123 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! [CBOR](https://cbor.io/) conversion (requires the `ciborium` feature).
+
+use num_bigint::BigInt;
+use starlark_map::small_map::SmallMap;
+
+use crate::values::dict::Dict;
+use crate::values::types::int_or_big::StarlarkInt;
+use crate::values::Heap;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum CborError {
+    #[error("CBOR byte strings cannot be converted to Starlark values")]
+    Bytes,
+    #[error("Unsupported CBOR value: {0:?}")]
+    Unsupported(ciborium::Value),
+}
+
+pub(crate) fn to_cbor(x: Value) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    ciborium::into_writer(&x, &mut buf)?;
+    Ok(buf)
+}
+
+pub(crate) fn from_cbor<'v>(heap: &'v Heap, bytes: &[u8]) -> anyhow::Result<Value<'v>> {
+    let cbor: ciborium::Value = ciborium::from_reader(bytes)?;
+    alloc_cbor(heap, cbor)
+}
+
+fn alloc_cbor<'v>(heap: &'v Heap, cbor: ciborium::Value) -> anyhow::Result<Value<'v>> {
+    Ok(match cbor {
+        ciborium::Value::Null => Value::new_none(),
+        ciborium::Value::Bool(b) => Value::new_bool(b),
+        ciborium::Value::Integer(i) => heap.alloc(StarlarkInt::from(BigInt::from(i128::from(i)))),
+        ciborium::Value::Float(f) => heap.alloc(f),
+        ciborium::Value::Text(s) => heap.alloc(s),
+        ciborium::Value::Bytes(_) => return Err(CborError::Bytes.into()),
+        ciborium::Value::Array(xs) => {
+            let xs = xs
+                .into_iter()
+                .map(|x| alloc_cbor(heap, x))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            heap.alloc(xs)
+        }
+        ciborium::Value::Map(entries) => {
+            let mut map = SmallMap::with_capacity(entries.len());
+            for (k, v) in entries {
+                let k = alloc_cbor(heap, k)?
+                    .get_hashed()
+                    .map_err(|e| e.into_anyhow())?;
+                map.insert_hashed(k, alloc_cbor(heap, v)?);
+            }
+            heap.alloc(Dict::new(map))
+        }
+        cbor => return Err(CborError::Unsupported(cbor).into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::values::Heap;
+    use crate::values::Value;
+
+    #[test]
+    fn test_round_trip() {
+        let module = assert::pass_module(
+            r#"
+x = struct(
+    name = "server",
+    port = 8080,
+    negative = -17,
+    big = 123456789123,
+    ratio = 0.25,
+    nested = struct(tags = ["a", "b"], enabled = True, none = None),
+)
+"#,
+        );
+        let x = module.get("x").unwrap();
+        let bytes = x.value().to_cbor().unwrap();
+        let heap = Heap::new();
+        let y = Value::from_cbor(&heap, &bytes).unwrap();
+        // Structs are decoded as dicts, big ints are serialized as strings like in JSON.
+        assert_eq!(
+            concat!(
+                r#"{"name": "server", "port": 8080, "negative": -17, "big": "123456789123", "ratio": 0.25, "#,
+                r#""nested": {"tags": ["a", "b"], "enabled": True, "none": None}}"#
+            ),
+            y.to_repr()
+        );
+        assert_eq!(bytes, y.to_cbor().unwrap());
+    }
+
+    #[test]
+    fn test_from_cbor_invalid() {
+        let heap = Heap::new();
+        assert!(Value::from_cbor(&heap, &[0xff]).is_err());
+        // Byte string `h'01'`.
+        assert_eq!(
+            "CBOR byte strings cannot be converted to Starlark values",
+            Value::from_cbor(&heap, &[0x41, 0x01])
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
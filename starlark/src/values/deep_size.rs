@@ -0,0 +1,86 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Estimate memory used by a value and everything reachable from it.
+
+use std::collections::HashSet;
+
+use crate::values::dict::DictRef;
+use crate::values::list::ListRef;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::Value;
+
+/// Sum of [`total_memory`](crate::values::layout::vtable::AValueDyn::total_memory)
+/// of `value` and all the values reachable from it through structs, lists, tuples and dicts.
+///
+/// Each heap value is counted once, no matter how many times it is referenced.
+/// Inline values (small integers) take no heap memory and are counted as zero.
+pub(crate) fn deep_size(value: Value) -> usize {
+    let mut visited = HashSet::new();
+    let mut stack = vec![value];
+    let mut size = 0;
+    while let Some(value) = stack.pop() {
+        if value.unpack_inline_int().is_some() || !visited.insert(value.identity()) {
+            continue;
+        }
+        size += value.get_ref().total_memory();
+        if let Some(s) = StructRef::from_value(value) {
+            stack.extend(s.iter().flat_map(|(k, v)| [k.to_value(), v]));
+        } else if let Some(xs) = ListRef::from_value(value) {
+            stack.extend(xs.content());
+        } else if let Some(xs) = TupleRef::from_value(value) {
+            stack.extend(xs.content());
+        } else if let Some(d) = DictRef::from_value(value) {
+            stack.extend(d.iter().flat_map(|(k, v)| [k, v]));
+        }
+    }
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    /// Deep size of `x` defined by the program.
+    fn deep_size(program: &str) -> usize {
+        let module = assert::pass_module(program);
+        module.get("x").unwrap().value().deep_size()
+    }
+
+    #[test]
+    fn test_nested_larger_than_flat() {
+        let flat = deep_size("x = struct(a=1, b=2)");
+        let nested = deep_size("x = struct(a=struct(c=1, d=2), b=struct(e=3, f=4))");
+        assert!(flat > 0);
+        assert!(nested > flat, "{} > {}", nested, flat);
+    }
+
+    #[test]
+    fn test_shared_value_counted_once() {
+        let shallow = deep_size("x = struct(a=1, b=2)");
+        let list = deep_size("x = ['xx', 'yy']");
+        let shared = deep_size("l = ['xx', 'yy']\nx = struct(a=l, b=l)");
+        assert_eq!(shallow + list, shared);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let size = deep_size("x = []\nx.append(x)");
+        assert!(size > 0);
+    }
+}
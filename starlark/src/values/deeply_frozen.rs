@@ -0,0 +1,107 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Check a value contains no mutable values.
+
+use crate::values::float::StarlarkFloat;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::types::bigint::StarlarkBigInt;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// Check `value` and all the values reachable from it are immutable.
+///
+/// Frozen values are always immutable. Unfrozen values are immutable if they are
+/// strings or numbers, or structs and tuples of immutable values.
+/// Everything else (lists, dicts, and unfrozen values of other types) is considered mutable.
+pub(crate) fn is_deeply_frozen(value: Value) -> bool {
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        if value.unpack_frozen().is_some()
+            || value.unpack_str().is_some()
+            || value.downcast_ref::<StarlarkFloat>().is_some()
+            || value.downcast_ref::<StarlarkBigInt>().is_some()
+        {
+            continue;
+        }
+        if let Some(s) = StructRef::from_value(value) {
+            stack.extend(s.iter().map(|(_, v)| v));
+        } else if let Some(xs) = TupleRef::from_value(value) {
+            stack.extend(xs.content());
+        } else {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::values::list::AllocList;
+    use crate::values::structs::AllocStruct;
+    use crate::values::structs::FrozenStructRef;
+    use crate::values::FrozenHeap;
+    use crate::values::Heap;
+    use crate::values::Value;
+
+    #[test]
+    fn test_unfrozen() {
+        let heap = Heap::new();
+        let immutable = heap.alloc(AllocStruct([
+            ("s", heap.alloc("x")),
+            ("f", heap.alloc(1.5)),
+            ("big", heap.alloc(1i64 << 40)),
+            (
+                "t",
+                heap.alloc((1, heap.alloc(AllocStruct([("n", Value::new_none())])))),
+            ),
+        ]));
+        assert!(immutable.is_deeply_frozen());
+
+        let list = heap.alloc(AllocList([1, 2]));
+        assert!(!list.is_deeply_frozen());
+        let nested = heap.alloc(AllocStruct([(
+            "inner",
+            heap.alloc(AllocStruct([("t", heap.alloc((1, list)))])),
+        )]));
+        assert!(!nested.is_deeply_frozen());
+    }
+
+    #[test]
+    fn test_frozen_children() {
+        let frozen_heap = FrozenHeap::new();
+        let frozen_list = frozen_heap.alloc(AllocList([1, 2])).to_value();
+        let heap = Heap::new();
+        // The struct itself is not frozen, but it only references frozen values.
+        let s = heap.alloc(AllocStruct([("l", frozen_list)]));
+        assert!(s.unpack_frozen().is_none());
+        assert!(s.is_deeply_frozen());
+    }
+
+    #[test]
+    fn test_frozen_struct() {
+        let module = assert::pass_module("x = struct(l = [1], d = {'a': struct(m = [])})");
+        let x = module.get("x").unwrap();
+        let s = FrozenStructRef::from_value(x.value().unpack_frozen().unwrap()).unwrap();
+        for (_, v) in s.iter() {
+            assert!(v.to_value().is_deeply_frozen());
+        }
+        assert!(x.value().is_deeply_frozen());
+    }
+}
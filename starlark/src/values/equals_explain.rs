@@ -0,0 +1,160 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Equality which explains where two values differ.
+
+use std::fmt::Write;
+
+use crate::values::dict::DictRef;
+use crate::values::list::ListRef;
+use crate::values::stack_guard;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::Value;
+
+/// Compare `x` and `y`, returning a description of the first difference,
+/// prefixed with the path to it (starting with `path`).
+pub(crate) fn equals_explain<'v>(
+    path: &mut String,
+    x: Value<'v>,
+    y: Value<'v>,
+) -> Result<(), String> {
+    match x.equals(y) {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(e) => return Err(format!("{}: {}", path, e)),
+    }
+    let _guard = stack_guard::stack_guard().map_err(|e| format!("{}: {}", path, e))?;
+
+    if let (Some(xs), Some(ys)) = (StructRef::from_value(x), StructRef::from_value(y)) {
+        let xs = xs.attributes();
+        let ys = ys.attributes();
+        for (name, xv) in &xs {
+            let len = path.len();
+            write!(path, ".{}", name).unwrap();
+            match ys.get(name) {
+                None => return Err(format!("{}: missing on the right", path)),
+                Some(yv) => equals_explain(path, *xv, *yv)?,
+            }
+            path.truncate(len);
+        }
+        if let Some(name) = ys.keys().find(|name| !xs.contains_key(*name)) {
+            return Err(format!("{}.{}: missing on the left", path, name));
+        }
+    } else if let (Some(xs), Some(ys)) = (ListRef::from_value(x), ListRef::from_value(y)) {
+        equals_explain_slice(path, xs.content(), ys.content())?;
+    } else if let (Some(xs), Some(ys)) = (TupleRef::from_value(x), TupleRef::from_value(y)) {
+        equals_explain_slice(path, xs.content(), ys.content())?;
+    } else if let (Some(xs), Some(ys)) = (DictRef::from_value(x), DictRef::from_value(y)) {
+        for (k, xv) in xs.iter_hashed() {
+            let len = path.len();
+            write!(path, "[{}]", k.key().to_repr()).unwrap();
+            match ys.get_hashed(k) {
+                None => return Err(format!("{}: missing on the right", path)),
+                Some(yv) => equals_explain(path, xv, yv)?,
+            }
+            path.truncate(len);
+        }
+        if let Some(k) = ys.keys().find(|k| xs.get(*k).ok().flatten().is_none()) {
+            return Err(format!("{}[{}]: missing on the left", path, k.to_repr()));
+        }
+    }
+
+    // Either the values are not containers of the same kind,
+    // or containers are not equal, but no element-wise difference was found.
+    Err(format!("{}: {} != {}", path, x.to_repr(), y.to_repr()))
+}
+
+fn equals_explain_slice<'v>(
+    path: &mut String,
+    xs: &[Value<'v>],
+    ys: &[Value<'v>],
+) -> Result<(), String> {
+    for (i, (x, y)) in xs.iter().zip(ys).enumerate() {
+        let len = path.len();
+        write!(path, "[{}]", i).unwrap();
+        equals_explain(path, *x, *y)?;
+        path.truncate(len);
+    }
+    if xs.len() != ys.len() {
+        return Err(format!("{}: length {} != {}", path, xs.len(), ys.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    fn explain(x: &str, y: &str) -> Result<(), String> {
+        let module = assert::pass_module(&format!("x = {}\ny = {}", x, y));
+        let x = module.get("x").unwrap();
+        let y = module.get("y").unwrap();
+        x.value().equals_explain(y.value())
+    }
+
+    #[test]
+    fn test_equal() {
+        assert_eq!(
+            Ok(()),
+            explain("struct(a=[1, {2: 3}])", "struct(a=[1, {2: 3}])")
+        );
+        // Field order does not matter for struct equality.
+        assert_eq!(Ok(()), explain("struct(a=1, b=2)", "struct(b=2, a=1)"));
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(Err("$: 1 != 2".to_owned()), explain("1", "2"));
+        assert_eq!(Err("$: 1 != \"1\"".to_owned()), explain("1", "'1'"));
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        assert_eq!(
+            Err("$.b: missing on the right".to_owned()),
+            explain("struct(a=1, b=2)", "struct(a=1)")
+        );
+        assert_eq!(
+            Err("$.b: missing on the left".to_owned()),
+            explain("struct(a=1)", "struct(a=1, b=2)")
+        );
+        assert_eq!(
+            Err("$[\"k\"]: missing on the left".to_owned()),
+            explain("{}", "{'k': 1}")
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        assert_eq!(
+            Err("$.servers[0].port: 80 != 8080".to_owned()),
+            explain(
+                "struct(servers=[struct(host='a', port=80)])",
+                "struct(servers=[struct(host='a', port=8080)])",
+            )
+        );
+        assert_eq!(
+            Err("$.d[\"k\"][1]: 2 != 3".to_owned()),
+            explain("struct(d={'k': (1, 2)})", "struct(d={'k': (1, 3)})")
+        );
+        assert_eq!(
+            Err("$.l: length 1 != 2".to_owned()),
+            explain("struct(l=[1])", "struct(l=[1, 2])")
+        );
+    }
+}
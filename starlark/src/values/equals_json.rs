@@ -0,0 +1,196 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Comparison of values with JSON.
+
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+
+use crate::values::dict::DictRef;
+use crate::values::float::StarlarkFloat;
+use crate::values::list::ListRef;
+use crate::values::stack_guard;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::types::int_or_big::StarlarkIntRef;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// JSON number as either an exact integer or a float.
+enum JsonNumber {
+    Int(BigInt),
+    Float(f64),
+}
+
+impl JsonNumber {
+    fn new(n: &serde_json::Number) -> Option<JsonNumber> {
+        if let Some(i) = n.as_i64() {
+            Some(JsonNumber::Int(BigInt::from(i)))
+        } else if let Some(u) = n.as_u64() {
+            Some(JsonNumber::Int(BigInt::from(u)))
+        } else {
+            n.as_f64().map(JsonNumber::Float)
+        }
+    }
+}
+
+/// Integer equals float only when the float is integral and has exactly the same value.
+fn int_equals_float(i: &BigInt, f: f64) -> bool {
+    f.is_finite() && f.fract() == 0.0 && BigInt::from_f64(f).as_ref() == Some(i)
+}
+
+fn equals_json_int(i: StarlarkIntRef, expected: &serde_json::Value) -> bool {
+    match expected {
+        serde_json::Value::Number(n) => match JsonNumber::new(n) {
+            Some(JsonNumber::Int(j)) => i.to_big() == j,
+            Some(JsonNumber::Float(f)) => int_equals_float(&i.to_big(), f),
+            None => false,
+        },
+        // Big ints are written to JSON as decimal strings.
+        serde_json::Value::String(s) => match i {
+            StarlarkIntRef::Big(i) => i.get().to_string() == *s,
+            StarlarkIntRef::Small(_) => false,
+        },
+        _ => false,
+    }
+}
+
+fn equals_json_float(x: f64, expected: &serde_json::Value) -> bool {
+    match expected {
+        serde_json::Value::Number(n) => match JsonNumber::new(n) {
+            Some(JsonNumber::Int(j)) => int_equals_float(&j, x),
+            Some(JsonNumber::Float(f)) => x == f,
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+fn equals_json_slice(xs: &[Value], expected: &serde_json::Value) -> bool {
+    match expected {
+        serde_json::Value::Array(ys) => {
+            xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| equals_json(*x, y))
+        }
+        _ => false,
+    }
+}
+
+/// Compare `x` with `expected` structurally, without serializing `x`.
+pub(crate) fn equals_json(x: Value, expected: &serde_json::Value) -> bool {
+    let Ok(_guard) = stack_guard::stack_guard() else {
+        return false;
+    };
+
+    if x.is_none() {
+        expected.is_null()
+    } else if let Some(b) = x.unpack_bool() {
+        expected.as_bool() == Some(b)
+    } else if let Some(i) = StarlarkIntRef::unpack_value(x) {
+        equals_json_int(i, expected)
+    } else if let Some(f) = x.downcast_ref::<StarlarkFloat>() {
+        equals_json_float(f.0, expected)
+    } else if let Some(s) = x.unpack_str() {
+        expected.as_str() == Some(s)
+    } else if let Some(xs) = ListRef::from_value(x) {
+        equals_json_slice(xs.content(), expected)
+    } else if let Some(xs) = TupleRef::from_value(x) {
+        equals_json_slice(xs.content(), expected)
+    } else if let Some(xs) = StructRef::from_value(x) {
+        let Some(ys) = expected.as_object() else {
+            return false;
+        };
+        xs.iter().len() == ys.len()
+            && xs.iter().all(|(k, v)| match ys.get(k.as_str()) {
+                Some(y) => equals_json(v, y),
+                None => false,
+            })
+    } else if let Some(xs) = DictRef::from_value(x) {
+        let Some(ys) = expected.as_object() else {
+            return false;
+        };
+        xs.len() == ys.len()
+            && xs
+                .iter()
+                .all(|(k, v)| match k.unpack_str().and_then(|k| ys.get(k)) {
+                    Some(y) => equals_json(v, y),
+                    None => false,
+                })
+    } else {
+        // Other values, like records, compare by their JSON serialization.
+        x.to_json_value().is_ok_and(|x| x == *expected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::assert;
+
+    fn equals_json(x: &str, expected: serde_json::Value) -> bool {
+        let module = assert::pass_module(&format!("x = {}", x));
+        module.get("x").unwrap().value().equals_json(&expected)
+    }
+
+    #[test]
+    fn test_struct() {
+        let expected = json!({
+            "name": "web",
+            "ports": [80, 443],
+            "tls": {"enabled": true, "cert": null},
+            "weight": 0.5,
+        });
+        assert!(equals_json(
+            "struct(name='web', ports=[80, 443], tls=struct(enabled=True, cert=None), weight=0.5)",
+            expected.clone(),
+        ));
+        // Field order does not matter, like for struct equality.
+        assert!(equals_json(
+            "struct(weight=0.5, tls={'cert': None, 'enabled': True}, ports=(80, 443), name='web')",
+            expected.clone(),
+        ));
+        assert!(!equals_json(
+            "struct(name='web', ports=[80, 443], tls=struct(enabled=True, cert=None))",
+            expected.clone(),
+        ));
+        assert!(!equals_json(
+            "struct(name='web', ports=[80, 8080], tls=struct(enabled=True, cert=None), weight=0.5)",
+            expected,
+        ));
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert!(equals_json("1", json!(1)));
+        assert!(equals_json("1", json!(1.0)));
+        assert!(equals_json("1.0", json!(1)));
+        assert!(!equals_json("0.5", json!(0)));
+        assert!(!equals_json("True", json!(1)));
+        assert!(equals_json("1 << 40", json!(1u64 << 40)));
+        assert!(equals_json("1 << 63", json!(1u64 << 63)));
+        assert!(equals_json("-(1 << 63)", json!(i64::MIN)));
+        assert!(equals_json("1 << 100", json!((2.0f64).powi(100))));
+        assert!(equals_json(
+            "1 << 100",
+            json!("1267650600228229401496703205376")
+        ));
+        assert!(!equals_json("1", json!("1")));
+        // `2 ** 53 + 1` is not representable as `f64`.
+        assert!(!equals_json("(1 << 53) + 1", json!((2.0f64).powi(53))));
+    }
+}
@@ -0,0 +1,307 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Decode JSON from a reader directly into values on a heap.
+
+use std::fmt;
+use std::io;
+use std::io::BufReader;
+use std::io::Read;
+
+use serde::de;
+use serde::de::DeserializeSeed;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::Deserializer;
+
+use crate::values::dict::AllocDict;
+use crate::values::list::AllocList;
+use crate::values::structs::StructBuilder;
+use crate::values::Heap;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum JsonReaderError {
+    #[error("Failed to decode JSON after reading {0} bytes: {1}")]
+    Decode(u64, serde_json::Error),
+    #[error("JSON document is larger than {0} bytes")]
+    TooLarge(u64),
+    #[error("JSON nesting is deeper than {0}")]
+    TooDeep(usize),
+}
+
+/// Options for [`Heap::alloc_json_reader_with_options`].
+#[derive(Debug, Clone)]
+pub struct JsonReaderOptions {
+    /// Decode JSON objects as structs instead of dicts.
+    pub objects_as_structs: bool,
+    /// Maximum nesting of arrays and objects. Default is 64.
+    ///
+    /// Regardless of this option, the JSON parser itself fails on nesting deeper than 128.
+    pub max_depth: usize,
+    /// Maximum number of bytes to read, or `None` for no limit (the default).
+    pub max_bytes: Option<u64>,
+}
+
+impl Default for JsonReaderOptions {
+    fn default() -> Self {
+        JsonReaderOptions {
+            objects_as_structs: false,
+            max_depth: 64,
+            max_bytes: None,
+        }
+    }
+}
+
+/// Reader counting consumed bytes, and failing after the limit.
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+    max_bytes: Option<u64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        if let Some(max_bytes) = self.max_bytes {
+            if self.count > max_bytes {
+                return Err(io::Error::other(JsonReaderError::TooLarge(max_bytes)));
+            }
+        }
+        Ok(n)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct JsonSeed<'v, 'a> {
+    heap: &'v Heap,
+    options: &'a JsonReaderOptions,
+    depth: usize,
+}
+
+impl<'v, 'a> JsonSeed<'v, 'a> {
+    fn nested<E: de::Error>(self) -> Result<Self, E> {
+        if self.depth >= self.options.max_depth {
+            return Err(E::custom(JsonReaderError::TooDeep(self.options.max_depth)));
+        }
+        Ok(JsonSeed {
+            depth: self.depth + 1,
+            ..self
+        })
+    }
+}
+
+impl<'de, 'v, 'a> DeserializeSeed<'de> for JsonSeed<'v, 'a> {
+    type Value = Value<'v>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value<'v>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'v, 'a> Visitor<'de> for JsonSeed<'v, 'a> {
+    type Value = Value<'v>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value<'v>, E> {
+        Ok(Value::new_none())
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value<'v>, E> {
+        Ok(Value::new_bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc_str(v).to_value())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value<'v>, A::Error> {
+        let seed = self.nested()?;
+        let mut xs = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(x) = seq.next_element_seed(seed)? {
+            xs.push(x);
+        }
+        Ok(self.heap.alloc(AllocList(xs)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value<'v>, A::Error> {
+        let seed = self.nested()?;
+        if self.options.objects_as_structs {
+            let mut builder = StructBuilder::new(self.heap);
+            while let Some(k) = map.next_key::<String>()? {
+                let v = map.next_value_seed(seed)?;
+                builder.add(&k, v);
+            }
+            Ok(builder.build())
+        } else {
+            let mut entries = Vec::new();
+            while let Some(k) = map.next_key::<String>()? {
+                let v = map.next_value_seed(seed)?;
+                entries.push((self.heap.alloc_str(&k), v));
+            }
+            Ok(self.heap.alloc(AllocDict(entries)))
+        }
+    }
+}
+
+pub(crate) fn alloc_json_reader<'v>(
+    heap: &'v Heap,
+    r: impl Read,
+    options: &JsonReaderOptions,
+) -> anyhow::Result<Value<'v>> {
+    // `serde_json` reads byte by byte, so the counter is exact,
+    // and buffering must be done below it.
+    let mut reader = CountingReader {
+        inner: BufReader::new(r),
+        count: 0,
+        max_bytes: options.max_bytes,
+    };
+    let mut deserializer = serde_json::Deserializer::from_reader(&mut reader);
+    let seed = JsonSeed {
+        heap,
+        options,
+        depth: 0,
+    };
+    let res = seed
+        .deserialize(&mut deserializer)
+        .and_then(|v| deserializer.end().map(|()| v));
+    match res {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            if let Some(max_bytes) = options.max_bytes {
+                if reader.count > max_bytes {
+                    return Err(JsonReaderError::TooLarge(max_bytes).into());
+                }
+            }
+            Err(JsonReaderError::Decode(reader.count, e).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::Heap;
+    use crate::values::JsonReaderOptions;
+
+    fn decode(json: &str, options: &JsonReaderOptions) -> anyhow::Result<String> {
+        let heap = Heap::new();
+        Ok(heap
+            .alloc_json_reader_with_options(json.as_bytes(), options)?
+            .to_repr())
+    }
+
+    #[test]
+    fn test_alloc_json_reader() {
+        let heap = Heap::new();
+        let v = heap
+            .alloc_json_reader(
+                r#"[10, null, false, {"k": "v", "f": 1.5, "big": 10000000000}]"#.as_bytes(),
+            )
+            .unwrap();
+        assert_eq!(
+            r#"[10, None, False, {"k": "v", "f": 1.5, "big": 10000000000}]"#,
+            v.to_repr()
+        );
+    }
+
+    #[test]
+    fn test_objects_as_structs() {
+        let options = JsonReaderOptions {
+            objects_as_structs: true,
+            ..JsonReaderOptions::default()
+        };
+        assert_eq!(
+            r#"struct(server=struct(port=80, hosts=["a", "b"]), x=struct())"#,
+            decode(
+                r#"{"server": {"port": 80, "hosts": ["a", "b"]}, "x": {}}"#,
+                &options
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_large_nested_document() {
+        let mut json = String::from("[");
+        for i in 0..10000 {
+            if i != 0 {
+                json.push(',');
+            }
+            json.push_str(&format!(
+                r#"{{"id": {}, "tags": ["t{}", {{"depth": [[[{}]]]}}]}}"#,
+                i, i, i
+            ));
+        }
+        json.push(']');
+        let heap = Heap::new();
+        let v = heap.alloc_json_reader(json.as_bytes()).unwrap();
+        assert_eq!(10000, v.length().unwrap());
+        let last = v.at(heap.alloc(-1), &heap).unwrap();
+        assert_eq!(
+            r#"{"id": 9999, "tags": ["t9999", {"depth": [[[9999]]]}]}"#,
+            last.to_repr()
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let options = JsonReaderOptions::default();
+        assert_eq!(
+            "Failed to decode JSON after reading 8 bytes: trailing comma at line 1 column 8",
+            decode(r#"[1, 2, ]"#, &options).unwrap_err().to_string()
+        );
+        assert!(decode(r#"[1] 2"#, &options)
+            .unwrap_err()
+            .to_string()
+            .contains("trailing characters"));
+        let max_depth = JsonReaderOptions {
+            max_depth: 2,
+            ..JsonReaderOptions::default()
+        };
+        assert_eq!("[[1]]", decode("[[1]]", &max_depth).unwrap());
+        assert!(decode("[[[1]]]", &max_depth)
+            .unwrap_err()
+            .to_string()
+            .contains("JSON nesting is deeper than 2"));
+        let max_bytes = JsonReaderOptions {
+            max_bytes: Some(5),
+            ..JsonReaderOptions::default()
+        };
+        assert_eq!("[1, 2]", decode("[1,2]", &max_bytes).unwrap());
+        assert_eq!(
+            "JSON document is larger than 5 bytes",
+            decode("[1, 2]", &max_bytes).unwrap_err().to_string()
+        );
+    }
+}
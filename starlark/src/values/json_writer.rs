@@ -0,0 +1,466 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write JSON to a writer without building the whole string.
+
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::mem;
+use std::str;
+
+use crate::values::dict::DictRef;
+use crate::values::layout::value::ToJsonCycleError;
+use crate::values::list::ListRef;
+use crate::values::recursive_repr_or_json_guard::json_stack_push;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::Value;
+
+/// Size of the chunks [`Value::write_json`] passes to the underlying writer.
+///
+/// Output is buffered and written one chunk at a time,
+/// so at most this many bytes are buffered regardless of the size of the value.
+pub const JSON_WRITE_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Error of the writer passed to [`Value::write_json`].
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to write JSON after {bytes_written} bytes")]
+pub struct WriteJsonError {
+    /// Number of bytes the writer accepted before failing, which are a prefix of the JSON.
+    pub bytes_written: u64,
+    /// The error of the writer, e.g. [`io::ErrorKind::WouldBlock`].
+    #[source]
+    pub error: io::Error,
+}
+
+/// Buffer writes into chunks of [`JSON_WRITE_CHUNK_SIZE`] bytes.
+///
+/// Serialization errors pass through `erased_serde`, which turns them into messages,
+/// so the first error of the underlying writer is kept here to be returned as is.
+/// After it, nothing more is written, so the output is always a prefix of the JSON.
+struct ChunkedWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+    /// Bytes accepted by `inner`.
+    written: u64,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    fn new(inner: W) -> Self {
+        ChunkedWriter {
+            inner,
+            buf: Vec::with_capacity(JSON_WRITE_CHUNK_SIZE),
+            written: 0,
+            error: None,
+        }
+    }
+
+    /// Like `write_all`, but counting the bytes written before an error.
+    fn write_all_counted(&mut self) -> io::Result<()> {
+        let mut buf = &self.buf[..];
+        while !buf.is_empty() {
+            match self.inner.write(buf) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.written += n as u64;
+                    buf = &buf[n..];
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_buf(&mut self) -> io::Result<()> {
+        if let Some(e) = &self.error {
+            return Err(e.kind().into());
+        }
+        let res = self.write_all_counted();
+        self.buf.clear();
+        res.map_err(|e| {
+            let kind = e.kind();
+            self.error = Some(e);
+            kind.into()
+        })
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let mut rem = data;
+        while !rem.is_empty() {
+            let n = rem.len().min(JSON_WRITE_CHUNK_SIZE - self.buf.len());
+            self.buf.extend_from_slice(&rem[..n]);
+            rem = &rem[n..];
+            if self.buf.len() == JSON_WRITE_CHUNK_SIZE {
+                self.write_buf()?;
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buf()?;
+        self.inner.flush()
+    }
+}
+
+/// Write the value like `serde_json::to_writer`.
+///
+/// `None`, `bool`, `int`, `str`, `list`, `tuple`, `struct` and `dict` with string keys
+/// are written directly: serializing a [`Value`] goes through `erased_serde`,
+/// which allocates for every value. Other values are serialized by `serde_json`.
+fn write_value<W: Write>(value: Value, w: &mut W) -> anyhow::Result<()> {
+    if value.is_none() {
+        w.write_all(b"null")?;
+    } else if let Some(b) = value.unpack_bool() {
+        w.write_all(if b { b"true" } else { b"false" })?;
+    } else if let Some(i) = value.unpack_i32() {
+        write!(w, "{}", i)?;
+    } else if let Some(s) = value.unpack_str() {
+        serde_json::to_writer(&mut *w, s)?;
+    } else if let Some(xs) = ListRef::from_value(value) {
+        write_seq(value, xs.content(), w)?;
+    } else if let Some(xs) = TupleRef::from_value(value) {
+        write_seq(value, xs.content(), w)?;
+    } else if let Some(s) = StructRef::from_value(value) {
+        write_map(value, s.iter().map(|(k, v)| (k.as_str(), v)), w)?;
+    } else if let Some(d) =
+        DictRef::from_value(value).filter(|d| d.keys().all(|k| k.unpack_str().is_some()))
+    {
+        write_map(
+            value,
+            d.iter().map(|(k, v)| (k.unpack_str().unwrap(), v)),
+            w,
+        )?;
+    } else {
+        serde_json::to_writer(&mut *w, &value)?;
+    }
+    Ok(())
+}
+
+fn write_seq<W: Write>(value: Value, items: &[Value], w: &mut W) -> anyhow::Result<()> {
+    let Ok(_guard) = json_stack_push(value) else {
+        return Err(ToJsonCycleError(value.get_type()).into());
+    };
+    w.write_all(b"[")?;
+    for (i, item) in items.iter().enumerate() {
+        if i != 0 {
+            w.write_all(b",")?;
+        }
+        write_value(*item, w)?;
+    }
+    w.write_all(b"]")?;
+    Ok(())
+}
+
+fn write_map<'v, W: Write>(
+    value: Value<'v>,
+    entries: impl Iterator<Item = (&'v str, Value<'v>)>,
+    w: &mut W,
+) -> anyhow::Result<()> {
+    let Ok(_guard) = json_stack_push(value) else {
+        return Err(ToJsonCycleError(value.get_type()).into());
+    };
+    w.write_all(b"{")?;
+    for (i, (k, v)) in entries.enumerate() {
+        if i != 0 {
+            w.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut *w, k)?;
+        w.write_all(b":")?;
+        write_value(v, w)?;
+    }
+    w.write_all(b"}")?;
+    Ok(())
+}
+
+pub(crate) fn to_json(value: Value) -> anyhow::Result<String> {
+    let mut out = Vec::new();
+    write_value(value, &mut out)?;
+    // `serde_json` only writes valid UTF-8.
+    Ok(String::from_utf8(out).unwrap())
+}
+
+pub(crate) fn write_json(value: Value, w: impl Write) -> anyhow::Result<()> {
+    let mut w = ChunkedWriter::new(w);
+    let res = write_value(value, &mut w).and_then(|()| Ok(w.flush()?));
+    match w.error {
+        Some(error) => Err(WriteJsonError {
+            bytes_written: w.written,
+            error,
+        }
+        .into()),
+        None => res,
+    }
+}
+
+/// Pass the UTF-8 output of `serde_json` to a [`fmt::Write`].
+///
+/// A write may end in the middle of a UTF-8 sequence, so its incomplete tail
+/// is kept and completed by the next write.
+struct FmtWriter<'a> {
+    inner: &'a mut dyn fmt::Write,
+    partial: Vec<u8>,
+    failed: bool,
+}
+
+impl<'a> FmtWriter<'a> {
+    /// Write the longest valid UTF-8 prefix of `data`, returning its length.
+    fn write_valid(&mut self, data: &[u8]) -> io::Result<usize> {
+        let s = match str::from_utf8(data) {
+            Ok(s) => s,
+            // Incomplete sequence at the end.
+            Err(e) if e.error_len().is_none() => str::from_utf8(&data[..e.valid_up_to()]).unwrap(),
+            Err(_) => return Err(io::ErrorKind::InvalidData.into()),
+        };
+        if self.inner.write_str(s).is_err() {
+            self.failed = true;
+            return Err(io::Error::other(fmt::Error));
+        }
+        Ok(s.len())
+    }
+}
+
+impl<'a> Write for FmtWriter<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.partial.is_empty() {
+            let n = self.write_valid(data)?;
+            self.partial.extend_from_slice(&data[n..]);
+        } else {
+            let mut bytes = mem::take(&mut self.partial);
+            bytes.extend_from_slice(data);
+            let n = self.write_valid(&bytes)?;
+            bytes.drain(..n);
+            self.partial = bytes;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.partial.is_empty() {
+            Ok(())
+        } else {
+            Err(io::ErrorKind::InvalidData.into())
+        }
+    }
+}
+
+pub(crate) fn write_json_fmt(value: Value, w: &mut dyn fmt::Write) -> anyhow::Result<()> {
+    let mut w = FmtWriter {
+        inner: w,
+        partial: Vec::new(),
+        failed: false,
+    };
+    let res = write_value(value, &mut w).and_then(|()| Ok(w.flush()?));
+    if w.failed {
+        return Err(fmt::Error.into());
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::io;
+    use std::io::Write;
+
+    use crate::assert::Assert;
+    use crate::values::dict::AllocDict;
+    use crate::values::json_writer::WriteJsonError;
+    use crate::values::json_writer::JSON_WRITE_CHUNK_SIZE;
+    use crate::values::list::AllocList;
+    use crate::values::list::ListRef;
+    use crate::values::Heap;
+
+    /// Accept at most `limit` bytes, a few at a time, then fail with `WouldBlock`.
+    struct LimitedWriter {
+        written: Vec<u8>,
+        limit: usize,
+        chunks: Vec<usize>,
+    }
+
+    impl Write for LimitedWriter {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            let n = data.len().min(self.limit - self.written.len()).min(1000);
+            if n == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            self.written.extend_from_slice(&data[..n]);
+            self.chunks.push(data.len());
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_json() {
+        let heap = Heap::new();
+        let value = heap.alloc(AllocList((0..10000).map(|i| format!("item{i}"))));
+        let expected = value.to_json().unwrap();
+        assert!(expected.len() > 3 * JSON_WRITE_CHUNK_SIZE);
+
+        let mut out = Vec::new();
+        value.write_json(&mut out).unwrap();
+        assert_eq!(expected.as_bytes(), out.as_slice());
+    }
+
+    #[test]
+    fn test_write_json_chunks() {
+        let heap = Heap::new();
+        let value = heap.alloc(AllocDict([("a", "x".repeat(3 * JSON_WRITE_CHUNK_SIZE))]));
+        let mut w = LimitedWriter {
+            written: Vec::new(),
+            limit: usize::MAX,
+            chunks: Vec::new(),
+        };
+        value.write_json(&mut w).unwrap();
+        assert_eq!(value.to_json().unwrap().as_bytes(), w.written.as_slice());
+        // Writes start with whole chunks, which the writer accepts 1000 bytes at a time.
+        assert!(w.chunks.iter().all(|n| *n <= JSON_WRITE_CHUNK_SIZE));
+        assert_eq!(Some(&JSON_WRITE_CHUNK_SIZE), w.chunks.first());
+    }
+
+    #[test]
+    fn test_write_json_error() {
+        let heap = Heap::new();
+        let value = heap.alloc(AllocList((0..10000).map(|i| format!("item{i}"))));
+        let expected = value.to_json().unwrap();
+        let mut w = LimitedWriter {
+            written: Vec::new(),
+            limit: JSON_WRITE_CHUNK_SIZE + 100,
+            chunks: Vec::new(),
+        };
+        let err = value.write_json(&mut w).unwrap_err();
+        let err = err.downcast_ref::<WriteJsonError>().unwrap();
+        assert_eq!(io::ErrorKind::WouldBlock, err.error.kind());
+        // The second chunk was written partially.
+        assert_eq!(JSON_WRITE_CHUNK_SIZE + 100, w.written.len());
+        assert_eq!(w.written.len() as u64, err.bytes_written);
+        assert!(expected.as_bytes().starts_with(&w.written));
+
+        // The value is unaffected and can be written again,
+        // skipping the bytes which were written before.
+        let mut out = Vec::new();
+        value.write_json(&mut out).unwrap();
+        assert_eq!(expected.as_bytes(), out.as_slice());
+        w.written
+            .extend_from_slice(&out[err.bytes_written as usize..]);
+        assert_eq!(expected.as_bytes(), w.written.as_slice());
+    }
+
+    #[test]
+    fn test_write_json_fmt() {
+        // A nested structure of over 10000 nodes.
+        let value = Assert::new().pass(
+            r#"
+x = {"leaf": "\u00e9\u4e2d\U0001f600"}
+for i in range(20):
+    x = [struct(i = i, prev = x)] + [{"k" + str(j): "v\u00e9"} for j in range(500)]
+x
+"#,
+        );
+        let expected = value.value().to_json().unwrap();
+        let mut out = String::new();
+        value.value().write_json_fmt(&mut out).unwrap();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn test_write_json_same_as_serde() {
+        let values = Assert::new().pass(
+            r#"
+cyclic = [1]
+cyclic.append(cyclic)
+def f(): pass
+[
+    None, True, False, 0, -17, 1 << 40, -(1 << 70), 1.0, -0.5, 1e100,
+    "", "quote\" backslash\\ newline\n \u00e9", [], (), {}, struct(),
+    (1, [2, (3,)]), struct(a = 1, b = struct(c = [None])),
+    {"a": 1, "b": {"c": 2}}, {1: "int key"}, {None: 1}, {"a": f}, [1, f],
+    float("nan"), cyclic, {"x": cyclic}, struct(x = cyclic),
+]
+"#,
+        );
+        for value in ListRef::from_value(values.value()).unwrap().iter() {
+            let expected = serde_json::to_string(&value).map_err(|e| e.to_string());
+            assert_eq!(expected, value.to_json().map_err(|e| e.to_string()));
+            let mut out = Vec::new();
+            let res = value.write_json(&mut out).map_err(|e| e.to_string());
+            let mut out_fmt = String::new();
+            let res_fmt = value
+                .write_json_fmt(&mut out_fmt)
+                .map_err(|e| e.to_string());
+            match expected {
+                Ok(expected) => {
+                    assert_eq!(Ok(()), res, "{}", value);
+                    assert_eq!(expected.as_bytes(), out.as_slice(), "{}", value);
+                    assert_eq!(Ok(()), res_fmt, "{}", value);
+                    assert_eq!(expected, out_fmt, "{}", value);
+                }
+                Err(expected) => {
+                    assert_eq!(Err(&expected), res.as_ref(), "{}", value);
+                    assert_eq!(Err(&expected), res_fmt.as_ref(), "{}", value);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_json_fmt_error() {
+        /// Accept `limit` bytes, then fail.
+        struct LimitedFmt {
+            written: String,
+            limit: usize,
+        }
+
+        impl fmt::Write for LimitedFmt {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                if self.written.len() + s.len() > self.limit {
+                    return Err(fmt::Error);
+                }
+                self.written.push_str(s);
+                Ok(())
+            }
+        }
+
+        let heap = Heap::new();
+        let value = heap.alloc(AllocList((0..100).map(|i| format!("item{i}"))));
+        let mut w = LimitedFmt {
+            written: String::new(),
+            limit: 100,
+        };
+        let err = value.write_json_fmt(&mut w).unwrap_err();
+        assert!(err.downcast_ref::<fmt::Error>().is_some());
+        assert!(value.to_json().unwrap().starts_with(&w.written));
+    }
+
+    #[test]
+    fn test_write_json_unsupported() {
+        let value = Assert::new().pass("def f(): pass\n[1, f]");
+        let mut out = Vec::new();
+        assert!(value.value().write_json(&mut out).is_err());
+        // The error happens before the first chunk is complete, so nothing is written.
+        assert!(out.is_empty());
+    }
+}
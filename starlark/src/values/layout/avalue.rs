@@ -23,8 +23,8 @@ use std::marker::PhantomData;
 use std::mem;
 
 use allocative::Allocative;
+use anyhow::Context;
 use derive_more::Display;
-use starlark_syntax::slice_vec_ext::SliceExt;
 
 use crate as starlark;
 use crate::any::ProvidesStaticType;
@@ -329,7 +329,15 @@ impl<'v> AValue<'v> for AValueTuple {
         );
 
         // TODO: this allocation is unnecessary
-        let frozen_values = content.try_map(|v| freezer.freeze(*v))?;
+        let frozen_values = content
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                freezer
+                    .freeze(*v)
+                    .with_context(|| format!("failed to freeze index {}", i))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
         r.fill(FrozenTuple::new(content.len()));
 
         let extra = &mut *extra;
@@ -436,8 +444,12 @@ impl<'v> AValue<'v> for AValueList {
         r.fill(ListGen(FrozenListData::new(content.len())));
         let extra = unsafe { &mut *extra };
         assert_eq!(extra.len(), content.len());
-        for (elem_place, elem) in extra.iter_mut().zip(content) {
-            elem_place.write(freezer.freeze(*elem)?);
+        for (i, (elem_place, elem)) in extra.iter_mut().zip(content).enumerate() {
+            elem_place.write(
+                freezer
+                    .freeze(*elem)
+                    .with_context(|| format!("failed to freeze index {}", i))?,
+            );
         }
         Ok(fv)
     }
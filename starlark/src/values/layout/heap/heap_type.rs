@@ -26,6 +26,7 @@ use std::fmt::Formatter;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::intrinsics::copy_nonoverlapping;
+use std::io::Read;
 use std::marker::PhantomData;
 use std::mem;
 use std::mem::MaybeUninit;
@@ -74,6 +75,7 @@ use crate::values::layout::heap::call_enter_exit::NoDrop;
 use crate::values::layout::heap::fast_cell::FastCell;
 use crate::values::layout::heap::maybe_uninit_slice_util::maybe_uninit_write_from_exact_size_iter;
 use crate::values::layout::heap::profile::by_type::HeapSummary;
+use crate::values::layout::heap::repr::AValueHeader;
 use crate::values::layout::heap::repr::AValueRepr;
 use crate::values::layout::static_string::constant_string;
 use crate::values::layout::typed::string::StringValueLike;
@@ -83,12 +85,15 @@ use crate::values::list::value::VALUE_EMPTY_FROZEN_LIST;
 use crate::values::string::intern::interner::FrozenStringValueInterner;
 use crate::values::string::intern::interner::StringValueInterner;
 use crate::values::string::str_type::StarlarkStr;
+use crate::values::structs::pool::StructPoolFreezer;
+use crate::values::structs::StructBuilder;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
 use crate::values::ComplexValue;
 use crate::values::FrozenRef;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValueTyped;
+use crate::values::JsonReaderOptions;
 use crate::values::StarlarkValue;
 use crate::values::StringValue;
 use crate::values::Trace;
@@ -511,6 +516,8 @@ pub struct Freezer {
     pub(crate) heap: FrozenHeap,
     /// Defs frozen by this freezer.
     pub(crate) frozen_defs: RefCell<Vec<FrozenRef<'static, FrozenDef>>>,
+    /// Pool to deduplicate frozen structs with.
+    pub(crate) struct_pool: Option<StructPoolFreezer>,
 }
 
 impl Freezer {
@@ -518,6 +525,7 @@ impl Freezer {
         Freezer {
             heap,
             frozen_defs: RefCell::new(Vec::new()),
+            struct_pool: None,
         }
     }
 
@@ -563,7 +571,15 @@ impl Freezer {
         let value = value.0.unpack_ptr().unwrap();
         match value.unpack_overwrite() {
             Either::Left(x) => Ok(unsafe { x.unpack_frozen_value() }),
-            Either::Right(v) => unsafe { v.heap_freeze(self) },
+            Either::Right(v) => {
+                if let (Some(pool), Some(header)) = (&self.struct_pool, value.unpack_header()) {
+                    let header = header as *const AValueHeader as *mut AValueHeader;
+                    if let Some(x) = unsafe { pool.freeze(header, self)? } {
+                        return Ok(x);
+                    }
+                }
+                unsafe { v.heap_freeze(self) }
+            }
         }
     }
 
@@ -796,6 +812,49 @@ impl Heap {
         self.alloc_str(res)
     }
 
+    /// Decode a JSON document from a reader, allocating values directly on this heap
+    /// without buffering the document.
+    ///
+    /// Decodes like `json.decode`: objects become dicts, integers which do not fit
+    /// into `i64` or `u64` become floats. Uses the default [`JsonReaderOptions`].
+    pub fn alloc_json_reader<'v>(&'v self, r: impl Read) -> anyhow::Result<Value<'v>> {
+        self.alloc_json_reader_with_options(r, &JsonReaderOptions::default())
+    }
+
+    /// Like [`alloc_json_reader`](Heap::alloc_json_reader), with explicit options.
+    ///
+    /// Decoding errors report the number of bytes read before the error.
+    pub fn alloc_json_reader_with_options<'v>(
+        &'v self,
+        r: impl Read,
+        options: &JsonReaderOptions,
+    ) -> anyhow::Result<Value<'v>> {
+        crate::values::json_reader::alloc_json_reader(self, r, options)
+    }
+
+    /// Allocate a struct with the given fields, in order.
+    ///
+    /// A field with a name which is already present replaces its value,
+    /// as with [`StructBuilder::add`].
+    ///
+    /// ```
+    /// # use starlark::values::Heap;
+    /// let heap = Heap::new();
+    /// let s = heap.alloc_struct([("host", heap.alloc("localhost")), ("port", heap.alloc(80))]);
+    /// assert_eq!("struct(host=\"localhost\", port=80)", s.to_repr());
+    /// ```
+    pub fn alloc_struct<'v, 'k, V: AllocValue<'v>>(
+        &'v self,
+        fields: impl IntoIterator<Item = (&'k str, V)>,
+    ) -> Value<'v> {
+        let fields = fields.into_iter();
+        let mut builder = StructBuilder::with_capacity(self, fields.size_hint().0);
+        for (k, v) in fields {
+            builder.add(k, v);
+        }
+        builder.build()
+    }
+
     /// Allocate a simple [`StarlarkValue`] on this heap.
     ///
     /// Simple value is any starlark value which:
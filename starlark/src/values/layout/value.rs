@@ -44,6 +44,7 @@ use num_bigint::BigInt;
 use serde::Serialize;
 use serde::Serializer;
 use starlark_map::Equivalent;
+use starlark_syntax::StarlarkResultExt;
 
 use crate as starlark;
 use crate::any::AnyLifetime;
@@ -121,6 +122,10 @@ use crate::values::ValueIdentity;
 enum ValueValueError {
     #[error("Expected value of type `{0}` but got `{1}`")]
     WrongType(&'static str, String),
+    #[error("Expected value of type `{0}` but got value of type `{1}`")]
+    UnpackWrongType(Ty, &'static str),
+    #[error("Expected `{1}` for attribute `{0}`, got value of type `{2}`")]
+    AttrWrongType(String, Ty, &'static str),
 }
 
 /// A Starlark value. The lifetime argument `'v` corresponds to the [`Heap`](crate::values::Heap) it is stored on.
@@ -329,8 +334,23 @@ impl<'v> Value<'v> {
         self.ptr_eq(Value::new_none())
     }
 
+    /// Obtain the value of an `int` or `float` as `f64`.
+    ///
+    /// Integers beyond 2<sup>53</sup> in magnitude are rounded to the nearest `f64`,
+    /// and integers too large for `f64` become infinity.
+    /// Use [`unpack_num_exact`](Value::unpack_num_exact) to reject such integers.
+    pub fn unpack_num(self) -> Option<f64> {
+        Some(self.unpack_num_ref()?.as_float())
+    }
+
+    /// Obtain the value of an `int` or `float` as `f64`,
+    /// returning `None` for integers which cannot be represented exactly as `f64`.
+    pub fn unpack_num_exact(self) -> Option<f64> {
+        self.unpack_num_ref()?.as_float_exact()
+    }
+
     /// Obtain the underlying numerical value, if it is one.
-    pub(crate) fn unpack_num(self) -> Option<NumRef<'v>> {
+    pub(crate) fn unpack_num_ref(self) -> Option<NumRef<'v>> {
         NumRef::unpack_value(self)
     }
 
@@ -418,6 +438,19 @@ impl<'v> Value<'v> {
         self.unpack_starlark_str().map(|s| s.as_str())
     }
 
+    /// Unpack the value into `T`, returning an error naming both the expected
+    /// type and the actual type of the value if unpacking fails.
+    ///
+    /// This is a `Result`-returning counterpart of [`UnpackValue::unpack_value`].
+    pub fn unpack_or_err<T: UnpackValue<'v>>(self) -> anyhow::Result<T> {
+        match T::unpack_value(self) {
+            Some(x) => Ok(x),
+            None => Err(
+                ValueValueError::UnpackWrongType(T::starlark_type_repr(), self.get_type()).into(),
+            ),
+        }
+    }
+
     /// Get a pointer to a [`AValue`].
     #[inline]
     pub(crate) fn get_ref(self) -> AValueDyn<'v> {
@@ -736,14 +769,67 @@ impl<'v> Value<'v> {
     ///
     /// Return an error if the value or any contained value does not support conversion to JSON.
     pub fn to_json(self) -> anyhow::Result<String> {
-        serde_json::to_string(&self).map_err(|e| anyhow::anyhow!(e))
+        crate::values::json_writer::to_json(self)
+    }
+
+    /// Write the value as JSON, like [`to_json`](Value::to_json), without building the string.
+    ///
+    /// Output is passed to `w` in chunks of
+    /// [`JSON_WRITE_CHUNK_SIZE`](crate::values::JSON_WRITE_CHUNK_SIZE) bytes.
+    /// If `w` fails, including with [`std::io::ErrorKind::WouldBlock`], writing stops
+    /// and a [`WriteJsonError`](crate::values::WriteJsonError) is returned, with the
+    /// error of `w` and the number of bytes written, which are a prefix of the JSON.
+    /// Serialization cannot be resumed where it stopped, but the output does not change
+    /// while the value is not modified, so writing again and skipping that many bytes
+    /// continues the output. Values which cannot be serialized fail like `to_json`,
+    /// possibly after part of the output was written.
+    pub fn write_json(self, w: impl std::io::Write) -> anyhow::Result<()> {
+        crate::values::json_writer::write_json(self, w)
+    }
+
+    /// Write the value as JSON to a [`fmt::Write`], like [`to_json`](Value::to_json),
+    /// without building the string, e.g. to append to an existing `String`.
+    ///
+    /// If `w` fails, writing stops and [`fmt::Error`] is returned;
+    /// the output already written is a prefix of the JSON.
+    pub fn write_json_fmt(self, w: &mut dyn fmt::Write) -> anyhow::Result<()> {
+        crate::values::json_writer::write_json_fmt(self, w)
+    }
+
+    /// Convert the value to pretty-printed JSON, indenting nested values by `indent` spaces.
+    ///
+    /// Forwards to [`StarlarkValue::to_json_pretty`].
+    pub fn to_json_pretty(self, indent: usize) -> anyhow::Result<String> {
+        self.get_ref().to_json_pretty(indent)
     }
 
     /// Convert the value to JSON value.
+    ///
+    /// The value is serialized directly, without writing a JSON string first.
+    /// Object keys of the result are sorted, unless the `preserve_order` feature
+    /// is enabled: then they keep the order of struct fields and dict entries.
     pub fn to_json_value(self) -> anyhow::Result<serde_json::Value> {
         serde_json::to_value(self).map_err(|e| anyhow::anyhow!(e))
     }
 
+    /// Convert the value to [CBOR](https://cbor.io/) using its serde serialization,
+    /// like [`to_json`](Value::to_json). Requires the `ciborium` feature.
+    ///
+    /// As with JSON, integers which do not fit into `i32` are serialized as decimal strings.
+    /// Return an error if the value or any contained value does not support serialization.
+    #[cfg(feature = "ciborium")]
+    pub fn to_cbor(self) -> anyhow::Result<Vec<u8>> {
+        crate::values::cbor::to_cbor(self)
+    }
+
+    /// Decode a [CBOR](https://cbor.io/) value. Requires the `ciborium` feature.
+    ///
+    /// Maps are decoded as dicts, arrays as lists. Byte strings and tags are not supported.
+    #[cfg(feature = "ciborium")]
+    pub fn from_cbor(heap: &'v Heap, bytes: &[u8]) -> anyhow::Result<Value<'v>> {
+        crate::values::cbor::from_cbor(heap, bytes)
+    }
+
     /// Forwards to [`StarlarkValue::set_attr`].
     pub fn set_attr(self, attribute: &str, alloc_value: Value<'v>) -> crate::Result<()> {
         self.get_ref().set_attr(attribute, alloc_value)
@@ -772,6 +858,18 @@ impl<'v> Value<'v> {
         ValueLike::get_hashed(self)
     }
 
+    /// Feed the hash of this value into a standard [`Hasher`](std::hash::Hasher),
+    /// for example to key [`HashMap`](std::collections::HashMap) on Starlark values.
+    ///
+    /// Equal values produce equal hashes, in particular structs with the same fields
+    /// in any order. Fails if the value is not hashable.
+    pub fn hash_into(self, hasher: &mut dyn std::hash::Hasher) -> crate::Result<()> {
+        let mut starlark_hasher = StarlarkHasher::new();
+        self.write_hash(&mut starlark_hasher)?;
+        hasher.write_u64(std::hash::Hasher::finish(&starlark_hasher));
+        Ok(())
+    }
+
     /// Are two values equal. If the values are of different types it will
     /// return [`false`]. It will only error if there is excessive recursion.
     #[inline]
@@ -786,6 +884,44 @@ impl<'v> Value<'v> {
         }
     }
 
+    /// Estimate memory used by this value and all the values transitively reachable
+    /// from it through structs, lists, tuples and dicts.
+    ///
+    /// Values referenced multiple times (for example, a frozen list shared between
+    /// two struct fields) are counted once. Intended for profiling, the result is approximate.
+    pub fn deep_size(self) -> usize {
+        crate::values::deep_size::deep_size(self)
+    }
+
+    /// Check this value contains no mutable values, and can be safely passed
+    /// to untrusted code.
+    ///
+    /// Frozen values are always deeply frozen. An unfrozen value is deeply frozen
+    /// if it is a string or a number, or a struct or tuple of deeply frozen values.
+    /// Lists, dicts and other unfrozen values are considered mutable.
+    pub fn is_deeply_frozen(self) -> bool {
+        crate::values::deeply_frozen::is_deeply_frozen(self)
+    }
+
+    /// Like [`equals`](Value::equals), but on inequality describe the first difference.
+    ///
+    /// Structs, lists, tuples and dicts are compared recursively, and the error
+    /// contains the path to the first differing element, for example
+    /// `$.servers[0].port: 80 != 8080`. Intended for test diagnostics.
+    pub fn equals_explain(self, other: Value<'v>) -> Result<(), String> {
+        crate::values::equals_explain::equals_explain(&mut "$".to_owned(), self, other)
+    }
+
+    /// Compare the value with JSON without serializing the value first. Intended for tests.
+    ///
+    /// Structs and dicts equal objects with the same keys, lists and tuples equal arrays.
+    /// Numbers are compared by exact value, so `1` equals both JSON `1` and `1.0`,
+    /// but `0.1` equals no JSON integer. Ints which do not fit `i32` also equal
+    /// their decimal string, as written by [`to_json`](Value::to_json).
+    pub fn equals_json(self, expected: &serde_json::Value) -> bool {
+        crate::values::equals_json::equals_json(self, expected)
+    }
+
     #[inline]
     fn equals_not_ptr_eq(self, other: Value<'v>) -> crate::Result<bool> {
         let _guard = stack_guard::stack_guard()?;
@@ -798,6 +934,17 @@ impl<'v> Value<'v> {
         ValueLike::compare(self, other)
     }
 
+    /// Total order over values of all types, for deterministic sorting of heterogeneous data.
+    ///
+    /// This is not the semantics of the language `<` operator: values are ordered
+    /// by type name first (so all floats sort before all ints), then within a type.
+    /// Structs, lists, tuples and dicts are compared element-wise with `total_cmp`,
+    /// struct fields in field name order. Other values are compared with
+    /// [`compare`](Value::compare), or by `repr` if the type is not comparable.
+    pub fn total_cmp(self, other: Value<'v>) -> Ordering {
+        crate::values::total_cmp::total_cmp(self, other)
+    }
+
     /// Describe the value, in order to get its metadata in a way that could be used
     /// to generate prototypes, help information or whatever other descriptive text
     /// is required.
@@ -827,6 +974,11 @@ impl<'v> Value<'v> {
         if let Some(methods) = aref.vtable().methods() {
             let attribute = Hashed::new(attribute);
             if let Some(v) = methods.get_hashed(attribute) {
+                if aref.vtable().attrs_shadow_methods() {
+                    if let Some(x) = aref.get_attr_hashed(attribute, heap) {
+                        return Ok(Some(x));
+                    }
+                }
                 return Ok(Some(v.bind(self, heap)?));
             }
             Ok(aref.get_attr_hashed(attribute, heap))
@@ -845,6 +997,31 @@ impl<'v> Value<'v> {
         }
     }
 
+    /// Return the attribute with the given name, unpacked to `V`.
+    ///
+    /// Like [`StructRef::get_field_typed`](crate::values::structs::StructRef::get_field_typed),
+    /// but works for any value, including methods. Returns `None` if there is no such
+    /// attribute, and an error naming the attribute and the expected type
+    /// if the attribute value cannot be unpacked to `V`.
+    pub fn get_attr_typed<V: UnpackValue<'v>>(
+        self,
+        attribute: &str,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Option<V>> {
+        let Some(value) = self.get_attr(attribute, heap).into_anyhow_result()? else {
+            return Ok(None);
+        };
+        match V::unpack_value(value) {
+            Some(v) => Ok(Some(v)),
+            None => Err(ValueValueError::AttrWrongType(
+                attribute.to_owned(),
+                V::starlark_type_repr(),
+                value.get_type(),
+            )
+            .into()),
+        }
+    }
+
     /// Query whether an attribute exists on a type. Should be equivalent to whether
     /// [`get_attr`](Value::get_attr) succeeds, but potentially more efficient.
     pub fn has_attr(self, attribute: &str, heap: &'v Heap) -> bool {
@@ -869,6 +1046,8 @@ impl<'v> Value<'v> {
             aref.dir_attr()
         };
         result.sort();
+        // An attribute may shadow a method of the same name.
+        result.dedup();
         result
     }
 
@@ -1218,7 +1397,7 @@ pub trait ValueLike<'v>:
 
 #[derive(Debug, thiserror::Error)]
 #[error("Cycle detected when serializing value of type `{0}` to JSON")]
-struct ToJsonCycleError(&'static str);
+pub(crate) struct ToJsonCycleError(pub(crate) &'static str);
 
 impl<'v> Sealed for Value<'v> {}
 
@@ -1334,14 +1513,21 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::hash::Hash;
+    use std::hash::Hasher;
+
     use num_bigint::BigInt;
 
     use crate::assert;
     use crate::values::list::AllocList;
     use crate::values::none::NoneType;
     use crate::values::string::str_type::StarlarkStr;
+    use crate::values::structs::AllocStruct;
     use crate::values::types::int::PointerI32;
     use crate::values::unpack::UnpackValue;
+    use crate::values::FrozenValue;
     use crate::values::Heap;
     use crate::values::Value;
     use crate::values::ValueLike;
@@ -1376,6 +1562,26 @@ mod tests {
         assert_eq!(Some(i32::MAX), value.unpack_i32());
     }
 
+    #[test]
+    fn test_unpack_or_err() {
+        let heap = Heap::new();
+        assert_eq!(17, heap.alloc(17).unpack_or_err::<i32>().unwrap());
+        assert_eq!(
+            "Expected value of type `int` but got value of type `string`",
+            heap.alloc("17")
+                .unpack_or_err::<i32>()
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Expected value of type `str` but got value of type `NoneType`",
+            Value::new_none()
+                .unpack_or_err::<&str>()
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
     #[test]
     fn test_unpack_frozen() {
         assert!(Value::new_none().unpack_frozen().is_some());
@@ -1390,6 +1596,52 @@ mod tests {
         assert_eq!(Some(BigInt::from(i64::MAX)), BigInt::unpack_value(value));
     }
 
+    #[test]
+    fn test_unpack_num() {
+        let heap = Heap::new();
+        assert_eq!(Some(17.0), heap.alloc(17).unpack_num());
+        assert_eq!(Some(2.5), heap.alloc(2.5).unpack_num());
+        assert_eq!(None, heap.alloc("17").unpack_num());
+        // `2^53 + 1` is rounded.
+        let big = heap.alloc(BigInt::from((1i64 << 53) + 1));
+        assert_eq!(Some((1i64 << 53) as f64), big.unpack_num());
+        let huge = heap.alloc(BigInt::from(1) << 2000);
+        assert_eq!(Some(f64::INFINITY), huge.unpack_num());
+    }
+
+    #[test]
+    fn test_unpack_num_exact() {
+        let heap = Heap::new();
+        assert_eq!(Some(17.0), heap.alloc(17).unpack_num_exact());
+        assert_eq!(Some(2.5), heap.alloc(2.5).unpack_num_exact());
+        assert_eq!(None, heap.alloc("17").unpack_num_exact());
+        let exact = heap.alloc(BigInt::from(1i64 << 60));
+        assert_eq!(Some((1i64 << 60) as f64), exact.unpack_num_exact());
+        let inexact = heap.alloc(BigInt::from((1i64 << 53) + 1));
+        assert_eq!(None, inexact.unpack_num_exact());
+        let huge = heap.alloc(BigInt::from(1) << 2000);
+        assert_eq!(None, huge.unpack_num_exact());
+    }
+
+    #[test]
+    fn test_get_attr_typed() {
+        let heap = Heap::new();
+        let s = heap.alloc(AllocStruct([("a", heap.alloc(1)), ("b", heap.alloc("x"))]));
+        assert_eq!(Some(1), s.get_attr_typed::<i32>("a", &heap).unwrap());
+        assert_eq!(Some("x"), s.get_attr_typed::<&str>("b", &heap).unwrap());
+        assert_eq!(None, s.get_attr_typed::<i32>("c", &heap).unwrap());
+        assert_eq!(
+            "Expected `int` for attribute `b`, got value of type `string`",
+            s.get_attr_typed::<i32>("b", &heap).unwrap_err().to_string()
+        );
+        // Methods are attributes too.
+        let list = heap.alloc(AllocList([1, 2]));
+        assert!(list
+            .get_attr_typed::<Value>("append", &heap)
+            .unwrap()
+            .is_some());
+    }
+
     #[test]
     fn test_to_json_value() {
         let value = assert::pass("{'a': 10}");
@@ -1413,4 +1665,36 @@ mod tests {
             list.display_for_type_error().to_string()
         );
     }
+
+    #[test]
+    fn test_hash_into_hash_map_key() {
+        struct Key(FrozenValue);
+
+        impl Hash for Key {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.to_value().hash_into(state).unwrap()
+            }
+        }
+
+        impl PartialEq for Key {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.to_value().equals(other.0.to_value()).unwrap()
+            }
+        }
+
+        impl Eq for Key {}
+
+        let module = assert::pass_module(
+            "a = struct(x = 1, y = 'z')\nb = struct(y = 'z', x = 1)\nc = struct(x = 2)",
+        );
+        let key = |name| Key(module.get(name).unwrap().value().unpack_frozen().unwrap());
+        let mut map = HashMap::new();
+        map.insert(key("a"), "a");
+        assert_eq!(Some(&"a"), map.get(&key("b")));
+        assert_eq!(None, map.get(&key("c")));
+
+        let heap = Heap::new();
+        let list = heap.alloc(AllocList([1]));
+        assert!(list.hash_into(&mut DefaultHasher::new()).is_err());
+    }
 }
@@ -248,6 +248,13 @@ impl AValueVTable {
         (self.starlark_value.get_methods)()
     }
 
+    /// Whether attributes of the type shadow methods of the same name,
+    /// see [`StarlarkValue::attrs_shadow_methods`].
+    #[inline]
+    pub(crate) fn attrs_shadow_methods(&'static self) -> bool {
+        self.starlark_value.HAS_attrs_shadow_methods
+    }
+
     pub(crate) fn drop_in_place(&'static self, value: StarlarkValueRawPtr) {
         (self.drop_in_place)(value)
     }
@@ -362,6 +369,15 @@ impl<'v> AValueDyn<'v> {
         (self.vtable.starlark_value.get_attr_hashed)(self.value, name, heap)
     }
 
+    #[inline]
+    pub(crate) fn get_attr_eval(
+        self,
+        name: &str,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Option<Value<'v>>> {
+        (self.vtable.starlark_value.get_attr_eval)(self.value, name, eval)
+    }
+
     #[inline]
     pub(crate) fn has_attr(self, name: &str, heap: &'v Heap) -> bool {
         (self.vtable.starlark_value.has_attr)(self.value, name, heap)
@@ -497,6 +513,11 @@ impl<'v> AValueDyn<'v> {
         (self.vtable.starlark_value.collect_repr_cycle)(self.value, collector)
     }
 
+    #[inline]
+    pub(crate) fn to_json_pretty(self, indent: usize) -> anyhow::Result<String> {
+        (self.vtable.starlark_value.to_json_pretty)(self.value, indent)
+    }
+
     #[inline]
     pub(crate) fn downcast_ref<T: StarlarkValue<'v>>(self) -> Option<&'v T> {
         if self.vtable.static_type_of_value.get() == T::static_type_id() {
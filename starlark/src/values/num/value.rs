@@ -31,6 +31,7 @@ use crate::values::types::int_or_big::StarlarkIntRef;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
 use crate::values::UnpackValue;
+use crate::values::Value;
 
 #[derive(Debug, thiserror::Error)]
 enum NumError {
@@ -71,6 +72,17 @@ impl<'v> NumRef<'v> {
         }
     }
 
+    /// Get underlying value as float, if it can be represented exactly.
+    pub(crate) fn as_float_exact(&self) -> Option<f64> {
+        match self {
+            Self::Int(i) => {
+                let f = i.to_f64();
+                (i.cmp_f64(f) == Ordering::Equal).then_some(f)
+            }
+            Self::Float(f) => Some(f.0),
+        }
+    }
+
     pub(crate) fn f64_to_i32_exact(f: f64) -> Option<i32> {
         let i = f as i32;
         if i as f64 == f { Some(i) } else { None }
@@ -84,6 +96,14 @@ impl<'v> NumRef<'v> {
         }
     }
 
+    /// Implementation of `==` for numbers: `false` if `other` is not a number.
+    pub(crate) fn equals_value(self, other: Value) -> bool {
+        match other.unpack_num_ref() {
+            None => false,
+            Some(other) => self == other,
+        }
+    }
+
     /// Get hash of the underlying number
     pub(crate) fn get_hash_64(self) -> u64 {
         fn float_hash(f: f64) -> u64 {
@@ -164,11 +184,7 @@ impl<'v> From<f64> for NumRef<'v> {
 /// This is total eq per starlark spec, not Rust's partial eq.
 impl<'v> PartialEq for NumRef<'v> {
     fn eq(&self, other: &Self) -> bool {
-        if let (NumRef::Int(a), NumRef::Int(b)) = (self, other) {
-            a == b
-        } else {
-            StarlarkFloat::compare_impl(self.as_float(), other.as_float()) == Ordering::Equal
-        }
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -182,10 +198,12 @@ impl<'v> PartialOrd for NumRef<'v> {
 
 impl<'v> Ord for NumRef<'v> {
     fn cmp(&self, other: &Self) -> Ordering {
-        if let (NumRef::Int(a), NumRef::Int(b)) = (self, other) {
-            a.cmp(b)
-        } else {
-            StarlarkFloat::compare_impl(self.as_float(), other.as_float())
+        match (self, other) {
+            (NumRef::Int(a), NumRef::Int(b)) => a.cmp(b),
+            // Do not convert ints to floats to compare exactly.
+            (NumRef::Int(a), NumRef::Float(b)) => a.cmp_f64(b.0),
+            (NumRef::Float(a), NumRef::Int(b)) => b.cmp_f64(a.0).reverse(),
+            (NumRef::Float(a), NumRef::Float(b)) => StarlarkFloat::compare_impl(a.0, b.0),
         }
     }
 }
@@ -228,6 +246,7 @@ mod tests {
     use num_bigint::BigInt;
 
     use super::*;
+    use crate::assert;
     use crate::values::types::inline_int::InlineInt;
     use crate::values::Value;
 
@@ -358,4 +377,30 @@ mod tests {
             NumRef::Float(StarlarkFloat(10.0))
         );
     }
+
+    #[test]
+    fn test_compare_big_int_float_exact() {
+        assert::all_true(
+            r#"
+(1 << 53) == 9007199254740992.0
+(1 << 53) + 1 != 9007199254740992.0
+(1 << 53) + 1 > 9007199254740992.0
+9007199254740992.0 < (1 << 53) + 1
+(1 << 53) - 1 < 9007199254740992.0
+-(1 << 53) - 1 < -9007199254740992.0
+(1 << 100) > 1e30
+(1 << 100) == 1267650600228229401496703205376.0
+1000000000000000000000000000001 < 1e30
+2147483648 < 2147483648.5
+2147483649 > 2147483648.5
+-2147483649 < -2147483648.5
+-2147483648 > -2147483648.5
+(1 << 100) < float("inf")
+(1 << 100) > float("-inf")
+(1 << 100) < float("nan")
+{1 << 100: "x"}[1267650600228229401496703205376.0] == "x"
+{(1 << 53) + 1: "x"}.get(9007199254740992.0) == None
+"#,
+        );
+    }
 }
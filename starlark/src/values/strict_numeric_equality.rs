@@ -0,0 +1,103 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Strict numeric equality mode, see
+//! [`Evaluator::enable_strict_numeric_equality`](crate::eval::Evaluator::enable_strict_numeric_equality).
+//!
+//! The mode is a property of the evaluator, checked by the `==` and `in` instructions,
+//! so regular equality, which is also used to compare keys of hashed containers,
+//! does not depend on it: `1` and `1.0` have the same hash, and if they were
+//! different keys in strict mode, a dict built in strict mode would have
+//! duplicate keys when used outside of it.
+
+use crate::values::dict::DictRef;
+use crate::values::list::ListRef;
+use crate::values::num::value::NumRef;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::Value;
+
+/// For values which are equal, do they have `int` and `float` at the same places.
+fn same_numeric_types<'v>(a: Value<'v>, b: Value<'v>) -> crate::Result<bool> {
+    if a.ptr_eq(b) {
+        return Ok(true);
+    }
+    fn all_same<'v>(
+        mut pairs: impl Iterator<Item = (Value<'v>, Value<'v>)>,
+    ) -> crate::Result<bool> {
+        pairs.try_fold(true, |same, (a, b)| {
+            Ok(same && same_numeric_types(a, b)?)
+        })
+    }
+    if let (Some(x), Some(y)) = (a.unpack_num_ref(), b.unpack_num_ref()) {
+        Ok(matches!(x, NumRef::Float(_)) == matches!(y, NumRef::Float(_)))
+    } else if let (Some(xs), Some(ys)) = (ListRef::from_value(a), ListRef::from_value(b)) {
+        all_same(xs.iter().zip(ys.iter()))
+    } else if let (Some(xs), Some(ys)) = (TupleRef::from_value(a), TupleRef::from_value(b)) {
+        all_same(xs.iter().zip(ys.iter()))
+    } else if let (Some(x), Some(y)) = (StructRef::from_value(a), StructRef::from_value(b)) {
+        // Equal structs have the same field names.
+        all_same(x.iter().filter_map(|(k, v)| Some((v, y.0.get(k.as_str())?))))
+    } else if let (Some(x), Some(y)) = (DictRef::from_value(a), DictRef::from_value(b)) {
+        // Equal dicts have equal keys, which are compared with regular equality.
+        for (k, v) in x.iter() {
+            if let Some(w) = y.get(k)? {
+                if !same_numeric_types(v, w)? {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    } else {
+        Ok(true)
+    }
+}
+
+/// `a == b` in strict numeric equality mode: an `int` is never equal to a `float`,
+/// including in lists, tuples, structs and dict values.
+pub(crate) fn equals_strict<'v>(a: Value<'v>, b: Value<'v>) -> crate::Result<bool> {
+    Ok(a.equals(b)? && same_numeric_types(a, b)?)
+}
+
+/// `needle in container` in strict numeric equality mode:
+/// elements of lists and tuples are compared with [`equals_strict`],
+/// other containers are checked like in regular mode.
+pub(crate) fn is_in_strict<'v>(container: Value<'v>, needle: Value<'v>) -> crate::Result<bool> {
+    let items = match (ListRef::from_value(container), TupleRef::from_value(container)) {
+        (Some(xs), _) => xs.content(),
+        (None, Some(xs)) => xs.content(),
+        (None, None) => return container.is_in(needle),
+    };
+    for item in items {
+        if equals_strict(*item, needle)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Result which is the same with and without strict numeric equality,
+/// `None` otherwise: constant folding cannot depend on the mode, because compiled
+/// code can be run by evaluators in either mode.
+pub(crate) fn same_in_both_modes(
+    regular: crate::Result<bool>,
+    strict: crate::Result<bool>,
+) -> Option<bool> {
+    match (regular, strict) {
+        (Ok(regular), Ok(strict)) if regular == strict => Some(regular),
+        _ => None,
+    }
+}
@@ -0,0 +1,148 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Total order over values of all types.
+
+use std::cmp::Ordering;
+
+use itertools::Itertools;
+
+use crate::values::dict::DictRef;
+use crate::values::list::ListRef;
+use crate::values::stack_guard;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::Value;
+
+/// Order values first by type name, then within a type.
+///
+/// Structs, lists, tuples and dicts are compared element-wise with this function,
+/// struct fields are compared in field name order. Other values of the same type
+/// are compared with [`Value::compare`], and if the type is not comparable,
+/// by their `repr`.
+pub(crate) fn total_cmp<'v>(x: Value<'v>, y: Value<'v>) -> Ordering {
+    if x.ptr_eq(y) {
+        return Ordering::Equal;
+    }
+    let type_cmp = x.get_type().cmp(y.get_type());
+    if type_cmp != Ordering::Equal {
+        return type_cmp;
+    }
+    // Deep or cyclic values: give up comparing element-wise.
+    let Ok(_guard) = stack_guard::stack_guard() else {
+        return x.to_repr().cmp(&y.to_repr());
+    };
+
+    if let (Some(xs), Some(ys)) = (StructRef::from_value(x), StructRef::from_value(y)) {
+        let xs = xs.iter().sorted_by_key(|(k, _)| k.as_str());
+        let ys = ys.iter().sorted_by_key(|(k, _)| k.as_str());
+        return cmp_by(xs, ys, |(xk, xv), (yk, yv)| {
+            xk.as_str().cmp(yk.as_str()).then_with(|| total_cmp(xv, yv))
+        });
+    }
+    if let (Some(xs), Some(ys)) = (ListRef::from_value(x), ListRef::from_value(y)) {
+        return total_cmp_slice(xs.content(), ys.content());
+    }
+    if let (Some(xs), Some(ys)) = (TupleRef::from_value(x), TupleRef::from_value(y)) {
+        return total_cmp_slice(xs.content(), ys.content());
+    }
+    if let (Some(xs), Some(ys)) = (DictRef::from_value(x), DictRef::from_value(y)) {
+        return cmp_by(xs.iter(), ys.iter(), |(xk, xv), (yk, yv)| {
+            total_cmp(xk, yk).then_with(|| total_cmp(xv, yv))
+        });
+    }
+    match x.compare(y) {
+        Ok(ord) => ord,
+        Err(_) => x.to_repr().cmp(&y.to_repr()),
+    }
+}
+
+fn total_cmp_slice<'v>(xs: &[Value<'v>], ys: &[Value<'v>]) -> Ordering {
+    cmp_by(xs.iter().copied(), ys.iter().copied(), total_cmp)
+}
+
+/// Lexicographic comparison of two iterators (`Iterator::cmp_by` is unstable).
+fn cmp_by<T>(
+    mut xs: impl Iterator<Item = T>,
+    mut ys: impl Iterator<Item = T>,
+    mut cmp: impl FnMut(T, T) -> Ordering,
+) -> Ordering {
+    loop {
+        match (xs.next(), ys.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match cmp(x, y) {
+                Ordering::Equal => {}
+                ord => return ord,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use itertools::Itertools;
+
+    use crate::assert;
+    use crate::values::list::ListRef;
+
+    fn sorted(program: &str) -> String {
+        let module = assert::pass_module(&format!("x = {}", program));
+        let x = module.get("x").unwrap();
+        let mut xs = ListRef::from_value(x.value()).unwrap().content().to_vec();
+        xs.sort_by(|a, b| a.total_cmp(*b));
+        xs.iter().map(|x| x.to_repr()).join(", ")
+    }
+
+    #[test]
+    fn test_mixed() {
+        assert_eq!(
+            "None, 1.5, 1, 3, \"a\", \"b\", struct(a=1), struct(a=\"x\")",
+            sorted("['b', struct(a='x'), 3, None, struct(a=1), 1.5, 'a', 1]")
+        );
+    }
+
+    #[test]
+    fn test_deterministic() {
+        // Same elements in different order sort the same.
+        let elements = "[1], (1,), {1: 2}, [1, 'a'], struct(b=1, a=2), struct(a=3), len";
+        assert_eq!(
+            sorted(&format!("[{}]", elements)),
+            sorted(&format!("list(reversed([{}]))", elements))
+        );
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        // Fields are compared in name order, values of different types are ordered by type.
+        assert_eq!(
+            "struct(a=1, b=[]), struct(b=\"x\", a=1), struct(b=0)",
+            sorted("[struct(b=0), struct(a=1, b=[]), struct(b='x', a=1)]")
+        );
+    }
+
+    #[test]
+    fn test_equal() {
+        let module = assert::pass_module("x = struct(a=[1, {}])\ny = struct(a=[1, {}])");
+        let x = module.get("x").unwrap();
+        let y = module.get("y").unwrap();
+        assert_eq!(Ordering::Equal, x.value().total_cmp(y.value()));
+    }
+}
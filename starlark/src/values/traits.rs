@@ -288,6 +288,16 @@ pub trait StarlarkValue<'v>:
         false
     }
 
+    /// Implemented by structs, whose fields shadow struct methods with the same name:
+    /// an attribute returned by [`get_attr`](StarlarkValue::get_attr) wins over
+    /// a member returned by [`get_methods`](StarlarkValue::get_methods).
+    /// Only whether the function is implemented matters, it is never called.
+    ///
+    /// This function must not be implemented outside of starlark crate.
+    #[doc(hidden)]
+    #[starlark_internal_vtable(skip)]
+    fn attrs_shadow_methods(_private: Private) {}
+
     /// Function is implemented for types values.
     #[doc(hidden)]
     fn type_matches_value(&self, _value: Value<'v>, _private: Private) -> bool {
@@ -352,6 +362,21 @@ pub trait StarlarkValue<'v>:
         write!(collector, "<{}...>", Self::TYPE).unwrap()
     }
 
+    /// Convert self to pretty-printed JSON, with nested values indented by `indent` spaces.
+    ///
+    /// The default implementation serializes self with the same serialization as
+    /// [`Value::to_json`], so scalars are printed as in compact JSON.
+    fn to_json_pretty(&self, indent: usize) -> anyhow::Result<String> {
+        let indent = " ".repeat(indent);
+        let mut json = Vec::new();
+        let mut serializer = serde_json::Serializer::with_formatter(
+            &mut json,
+            serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes()),
+        );
+        erased_serde::serialize(self, &mut serializer).map_err(|e| anyhow::anyhow!(e))?;
+        Ok(String::from_utf8(json)?)
+    }
+
     /// String used when printing call stack. `repr(self)` by default.
     fn name_for_call_stack(&self, me: Value<'v>) -> String {
         me.to_repr()
@@ -590,6 +615,22 @@ pub trait StarlarkValue<'v>:
         self.get_attr(attribute.key(), heap)
     }
 
+    /// Get an attribute which [`get_attr`](StarlarkValue::get_attr) cannot return,
+    /// because computing it requires calling Starlark code.
+    ///
+    /// Attribute access from Starlark code (`a.attribute` and `getattr(a, "attribute")`)
+    /// calls this when `get_attr` returns `None`. Unlike `get_attr`, this is not
+    /// called speculatively, so it may have side effects.
+    /// [`has_attr`](StarlarkValue::has_attr) and [`dir_attr`](StarlarkValue::dir_attr)
+    /// should include the attributes returned by this function.
+    fn get_attr_eval(
+        &self,
+        _attribute: &str,
+        _eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Option<Value<'v>>> {
+        Ok(None)
+    }
+
     /// Return true if an attribute of name `attribute` exists for the current
     /// value.
     ///
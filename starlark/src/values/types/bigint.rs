@@ -17,7 +17,9 @@
 
 //! Outside of `i32` range int.
 
-mod convert;
+pub(crate) mod convert;
+#[cfg(feature = "chrono")]
+pub(crate) mod timestamp;
 
 use std::cmp::Ordering;
 use std::hash::Hash;
@@ -31,7 +33,9 @@ use starlark_derive::starlark_value;
 
 use crate as starlark;
 use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHashValue;
 use crate::collections::StarlarkHasher;
+use crate::private::Private;
 use crate::typing::Ty;
 use crate::typing::TyBasic;
 use crate::typing::TypingBinOp;
@@ -172,11 +176,11 @@ impl<'v> StarlarkValue<'v> for StarlarkBigInt {
     }
 
     fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
-        Ok(Some(NumRef::Int(StarlarkIntRef::Big(self))) == other.unpack_num())
+        Ok(NumRef::Int(StarlarkIntRef::Big(self)).equals_value(other))
     }
 
     fn compare(&self, other: Value<'v>) -> crate::Result<Ordering> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             None => ValueError::unsupported_with(self, "compare", other),
             Some(other) => Ok(NumRef::Int(StarlarkIntRef::Big(self)).cmp(&other)),
         }
@@ -184,12 +188,12 @@ impl<'v> StarlarkValue<'v> for StarlarkBigInt {
 
     fn add(&self, rhs: Value<'v>, heap: &'v Heap) -> Option<crate::Result<Value<'v>>> {
         Some(Ok(heap.alloc(
-            NumRef::Int(StarlarkIntRef::Big(self)) + rhs.unpack_num()?,
+            NumRef::Int(StarlarkIntRef::Big(self)) + rhs.unpack_num_ref()?,
         )))
     }
 
     fn sub(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(other) => Ok(heap.alloc(NumRef::Int(StarlarkIntRef::Big(self)) - other)),
             None => ValueError::unsupported_with(self, "-", other),
         }
@@ -197,26 +201,26 @@ impl<'v> StarlarkValue<'v> for StarlarkBigInt {
 
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> Option<crate::Result<Value<'v>>> {
         Some(Ok(heap.alloc(
-            NumRef::Int(StarlarkIntRef::Big(self)) * other.unpack_num()?,
+            NumRef::Int(StarlarkIntRef::Big(self)) * other.unpack_num_ref()?,
         )))
     }
 
     fn div(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(other) => Ok(heap.alloc(NumRef::Int(StarlarkIntRef::Big(self)).div(other)?)),
             None => ValueError::unsupported_with(self, "/", other),
         }
     }
 
     fn floor_div(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(rhs) => Ok(heap.alloc(NumRef::Int(StarlarkIntRef::Big(self)).floor_div(rhs)?)),
             None => ValueError::unsupported_with(self, "//", other),
         }
     }
 
     fn percent(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(rhs) => Ok(heap.alloc(NumRef::Int(StarlarkIntRef::Big(self)).percent(rhs)?)),
             None => ValueError::unsupported_with(self, "%", other),
         }
@@ -275,6 +279,11 @@ impl<'v> StarlarkValue<'v> for StarlarkBigInt {
         Ok(())
     }
 
+    fn get_hash(&self, _private: Private) -> crate::Result<StarlarkHashValue> {
+        // Same as float hash, so equal big ints and floats are the same dict key.
+        Ok(NumRef::Int(StarlarkIntRef::Big(self)).get_hash())
+    }
+
     fn typechecker_ty(&self) -> Option<Ty> {
         Some(Ty::int())
     }
@@ -477,6 +486,22 @@ mod tests {
         assert::eq("10.0", "10.0 % 100000000000000000000");
     }
 
+    #[test]
+    fn test_minus_overflow() {
+        assert::eq("9223372036854775808", "-(-9223372036854775808)");
+        assert::eq("-9223372036854775808", "-9223372036854775808");
+        assert::eq("2147483648", "-(-2147483648)");
+        assert::eq("-2147483648", "-(2147483648)");
+        assert::pass(
+            r#"
+x = -9223372036854775807 - 1
+assert_eq(-x, 9223372036854775807 + 1)
+assert_eq(-x - 1, 9223372036854775807)
+assert_eq(str(-x), "9223372036854775808")
+"#,
+        );
+    }
+
     #[test]
     fn test_bit_and_big() {
         assert::eq(
@@ -697,6 +722,25 @@ mod tests {
         assert_eq!(hash1.finish(), hash2.finish());
     }
 
+    #[test]
+    fn test_hash_same_value_from_big_arithmetic() {
+        // Results of big int arithmetic which fit the inline representation are
+        // demoted, so they are the same dict keys as literals.
+        assert::pass(
+            r#"
+d = {5: "five", 2147483648: "big", 0.5: "half"}
+big = 1 << 100
+assert_eq("five", d[big - big + 5])
+assert_eq("five", d[big // (big // 5)])
+assert_eq("five", d[5.0])
+assert_eq("big", d[big // (big // 2147483648)])
+assert_eq("big", d[2147483648.0])
+assert_eq({5: None}, {(big * 5) // big: None})
+assert_eq(1, len(dict([(5, 1), (big - big + 5, 2), (5.0, 3)])))
+"#,
+        );
+    }
+
     #[test]
     fn test_int_type_matches_bigint() {
         assert::is_true("isinstance(1 << 100, int)");
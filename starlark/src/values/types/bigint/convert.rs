@@ -15,6 +15,11 @@
  * limitations under the License.
  */
 
+use std::num::NonZeroI32;
+use std::num::NonZeroI64;
+use std::num::NonZeroU32;
+use std::num::NonZeroU64;
+
 use num_bigint::BigInt;
 
 use crate::typing::Ty;
@@ -28,6 +33,63 @@ use crate::values::FrozenValue;
 use crate::values::Heap;
 use crate::values::UnpackValue;
 use crate::values::Value;
+use crate::values::ValueError;
+
+#[derive(Debug, thiserror::Error)]
+enum IntOutOfRangeError {
+    #[error("Value {0} is out of range for `{1}`")]
+    Param(String, &'static str),
+    #[error("Value {0} of parameter `{1}` is out of range for `{2}`")]
+    NamedParam(String, String, &'static str),
+}
+
+/// Error for a parameter which failed to unpack into the Rust integer type `rust_type`.
+///
+/// When the value is an `int`, it must be out of range, so say that instead of
+/// reporting the type mismatch of `int` and `int`.
+#[cold]
+pub(crate) fn int_param_error<'v, I: UnpackValue<'v>>(
+    value: Value<'v>,
+    rust_type: &'static str,
+    param_name: Option<&str>,
+) -> anyhow::Error {
+    match (StarlarkIntRef::unpack_value(value), param_name) {
+        (Some(i), None) => IntOutOfRangeError::Param(i.approx_display(), rust_type).into(),
+        (Some(i), Some(name)) => {
+            IntOutOfRangeError::NamedParam(i.approx_display(), name.to_owned(), rust_type).into()
+        }
+        (None, None) => ValueError::IncorrectParameterTypeWithExpected(
+            I::starlark_type_repr().to_string(),
+            value.get_type().to_owned(),
+        )
+        .into(),
+        (None, Some(name)) => ValueError::IncorrectParameterTypeNamedWithExpected(
+            name.to_owned(),
+            I::starlark_type_repr().to_string(),
+            value.get_type().to_owned(),
+        )
+        .into(),
+    }
+}
+
+/// `UnpackValue` parameter methods of Rust integer types, reporting out of range values.
+macro_rules! unpack_int_param {
+    ($t:ty) => {
+        #[inline]
+        fn unpack_param(value: Value<'v>) -> anyhow::Result<Self> {
+            Self::unpack_value(value)
+                .ok_or_else(|| int_param_error::<Self>(value, stringify!($t), None))
+        }
+
+        #[inline]
+        fn unpack_named_param(value: Value<'v>, param_name: &str) -> anyhow::Result<Self> {
+            Self::unpack_value(value)
+                .ok_or_else(|| int_param_error::<Self>(value, stringify!($t), Some(param_name)))
+        }
+    };
+}
+
+pub(crate) use unpack_int_param;
 
 impl StarlarkTypeRepr for u32 {
     type Canonical = <i32 as StarlarkTypeRepr>::Canonical;
@@ -139,6 +201,50 @@ impl AllocFrozenValue for isize {
     }
 }
 
+impl StarlarkTypeRepr for u128 {
+    type Canonical = <i32 as StarlarkTypeRepr>::Canonical;
+
+    fn starlark_type_repr() -> Ty {
+        i32::starlark_type_repr()
+    }
+}
+
+impl<'v> AllocValue<'v> for u128 {
+    #[inline]
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc(StarlarkInt::from(self))
+    }
+}
+
+impl AllocFrozenValue for u128 {
+    #[inline]
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc(StarlarkInt::from(self))
+    }
+}
+
+impl StarlarkTypeRepr for i128 {
+    type Canonical = <i32 as StarlarkTypeRepr>::Canonical;
+
+    fn starlark_type_repr() -> Ty {
+        i32::starlark_type_repr()
+    }
+}
+
+impl<'v> AllocValue<'v> for i128 {
+    #[inline]
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc(StarlarkInt::from(self))
+    }
+}
+
+impl AllocFrozenValue for i128 {
+    #[inline]
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc(StarlarkInt::from(self))
+    }
+}
+
 impl StarlarkTypeRepr for BigInt {
     type Canonical = <i32 as StarlarkTypeRepr>::Canonical;
 
@@ -164,30 +270,57 @@ impl<'v> UnpackValue<'v> for u32 {
     fn unpack_value(value: Value<'v>) -> Option<u32> {
         value.unpack_integer()
     }
+
+    unpack_int_param!(u32);
 }
 
 impl<'v> UnpackValue<'v> for u64 {
     fn unpack_value(value: Value<'v>) -> Option<u64> {
         value.unpack_integer()
     }
+
+    unpack_int_param!(u64);
 }
 
 impl<'v> UnpackValue<'v> for i64 {
+    #[inline]
     fn unpack_value(value: Value<'v>) -> Option<i64> {
-        value.unpack_integer()
+        StarlarkIntRef::unpack_value(value)?.as_i64_fast()
     }
+
+    unpack_int_param!(i64);
 }
 
 impl<'v> UnpackValue<'v> for usize {
     fn unpack_value(value: Value<'v>) -> Option<usize> {
         value.unpack_integer()
     }
+
+    unpack_int_param!(usize);
 }
 
 impl<'v> UnpackValue<'v> for isize {
     fn unpack_value(value: Value<'v>) -> Option<isize> {
         value.unpack_integer()
     }
+
+    unpack_int_param!(isize);
+}
+
+impl<'v> UnpackValue<'v> for u128 {
+    fn unpack_value(value: Value<'v>) -> Option<u128> {
+        value.unpack_integer()
+    }
+
+    unpack_int_param!(u128);
+}
+
+impl<'v> UnpackValue<'v> for i128 {
+    fn unpack_value(value: Value<'v>) -> Option<i128> {
+        value.unpack_integer()
+    }
+
+    unpack_int_param!(i128);
 }
 
 impl<'v> UnpackValue<'v> for BigInt {
@@ -199,14 +332,62 @@ impl<'v> UnpackValue<'v> for BigInt {
     }
 }
 
+/// Conversions of a `NonZero*` integer type, unpacking like its primitive type,
+/// and rejecting zero like out of range values.
+macro_rules! non_zero_int {
+    ($t:ident, $primitive:ty) => {
+        impl StarlarkTypeRepr for $t {
+            type Canonical = <i32 as StarlarkTypeRepr>::Canonical;
+
+            fn starlark_type_repr() -> Ty {
+                i32::starlark_type_repr()
+            }
+        }
+
+        impl<'v> AllocValue<'v> for $t {
+            #[inline]
+            fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+                self.get().alloc_value(heap)
+            }
+        }
+
+        impl AllocFrozenValue for $t {
+            #[inline]
+            fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+                self.get().alloc_frozen_value(heap)
+            }
+        }
+
+        impl<'v> UnpackValue<'v> for $t {
+            fn unpack_value(value: Value<'v>) -> Option<$t> {
+                $t::new(<$primitive>::unpack_value(value)?)
+            }
+
+            unpack_int_param!($t);
+        }
+    };
+}
+
+non_zero_int!(NonZeroU32, u32);
+non_zero_int!(NonZeroU64, u64);
+non_zero_int!(NonZeroI32, i32);
+non_zero_int!(NonZeroI64, i64);
+
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroI32;
+    use std::num::NonZeroI64;
+    use std::num::NonZeroU32;
+    use std::num::NonZeroU64;
+
     use starlark_derive::starlark_module;
 
     use crate as starlark;
     use crate::assert::Assert;
     use crate::environment::GlobalsBuilder;
     use crate::values::none::NoneType;
+    use crate::values::UnpackValue;
+    use crate::values::Value;
 
     #[test]
     fn test_unpack_int_error() {
@@ -223,14 +404,118 @@ mod tests {
 
         let mut a = Assert::new();
         a.globals_add(module);
-        // TODO(nga): error is correct, but error message is not helpful.
         a.fail(
             "takes_i32(1 << 100)",
-            "Type of parameter `_i` doesn't match, expected `int`, actual `int`",
+            "Value 1.27e30 (approx) of parameter `_i` is out of range for `i32`",
         );
         a.fail(
             "takes_i64(1 << 100)",
-            "Type of parameter `_i` doesn't match, expected `int`, actual `int`",
+            "Value 1.27e30 (approx) of parameter `_i` is out of range for `i64`",
+        );
+        a.fail(
+            "takes_i32(2147483648)",
+            "Value 2147483648 of parameter `_i` is out of range for `i32`",
+        );
+        a.fail(
+            "takes_i32('x')",
+            "Type of parameter `_i` doesn't match, expected `int`, actual `string`",
+        );
+    }
+
+    #[test]
+    fn test_non_zero() {
+        #[starlark_module]
+        fn module(globals: &mut GlobalsBuilder) {
+            fn echo_non_zero_u32(
+                #[starlark(require = pos)] x: NonZeroU32,
+            ) -> starlark::Result<NonZeroU32> {
+                Ok(x)
+            }
+
+            fn echo_non_zero_i64(
+                #[starlark(require = named)] x: NonZeroI64,
+            ) -> starlark::Result<NonZeroI64> {
+                Ok(x)
+            }
+
+            fn non_zero_u64_max() -> starlark::Result<NonZeroU64> {
+                Ok(NonZeroU64::MAX)
+            }
+
+            fn non_zero_i32_min() -> starlark::Result<NonZeroI32> {
+                Ok(NonZeroI32::MIN)
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(module);
+        a.eq("echo_non_zero_u32(4294967295)", "4294967295");
+        a.eq("echo_non_zero_i64(x = -(1 << 63))", "-(1 << 63)");
+        a.eq("non_zero_u64_max()", "(1 << 64) - 1");
+        a.eq("non_zero_i32_min()", "-2147483648");
+        a.fail(
+            "echo_non_zero_u32(0)",
+            "Value 0 of parameter `x` is out of range for `NonZeroU32`",
+        );
+        a.fail(
+            "echo_non_zero_u32(-1)",
+            "Value -1 of parameter `x` is out of range for `NonZeroU32`",
+        );
+        a.fail(
+            "echo_non_zero_i64(x = 0)",
+            "Value 0 of parameter `x` is out of range for `NonZeroI64`",
+        );
+        a.fail(
+            "echo_non_zero_u32('1')",
+            "Type of parameter `x` doesn't match, expected `int`, actual `string`",
+        );
+        assert_eq!(None, NonZeroU64::unpack_value(Value::testing_new_int(0)));
+        assert_eq!(
+            NonZeroU64::new(3),
+            NonZeroU64::unpack_value(Value::testing_new_int(3))
+        );
+    }
+
+    #[test]
+    fn test_u128_i128_round_trip() {
+        #[starlark_module]
+        fn module(globals: &mut GlobalsBuilder) {
+            fn u128_max() -> starlark::Result<u128> {
+                Ok(u128::MAX)
+            }
+
+            fn i128_min() -> starlark::Result<i128> {
+                Ok(i128::MIN)
+            }
+
+            fn echo_u128(#[starlark(require = pos)] x: u128) -> starlark::Result<u128> {
+                Ok(x)
+            }
+
+            fn echo_i128(#[starlark(require = pos)] x: i128) -> starlark::Result<i128> {
+                Ok(x)
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(module);
+        a.eq("u128_max()", "(1 << 128) - 1");
+        a.eq("i128_min()", "-(1 << 127)");
+        a.eq("echo_u128(u128_max())", "u128_max()");
+        a.eq("echo_i128(i128_min())", "i128_min()");
+        a.eq("echo_u128(17)", "17");
+        a.eq("echo_i128(-17)", "-17");
+        a.fail(
+            "echo_u128(-1)",
+            "Value -1 of parameter `x` is out of range for `u128`",
+        );
+        a.fail(
+            "echo_u128(1 << 128)",
+            "Value 3.40e38 (approx) of parameter `x` is out of range for `u128`",
+        );
+        a.fail(
+            "echo_i128(1 << 127)",
+            "Value 1.70e38 (approx) of parameter `x` is out of range for `i128`",
         );
     }
 }
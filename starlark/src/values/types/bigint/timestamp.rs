@@ -0,0 +1,145 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Interpret integers as Unix timestamps (requires the `chrono` feature).
+
+use chrono::DateTime;
+use chrono::Utc;
+use num_bigint::BigInt;
+use num_bigint::Sign;
+use num_traits::ToPrimitive;
+
+use crate::typing::Ty;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+/// Number of nanoseconds in a second.
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// Convert a non-negative number of `unit`-long ticks since the epoch
+/// (`unit` is the number of ticks per second) into a timestamp.
+fn unpack_timestamp(value: Value, ticks_per_sec: u32) -> Option<DateTime<Utc>> {
+    let ticks = BigInt::unpack_value(value)?;
+    if ticks.sign() == Sign::Minus {
+        return None;
+    }
+    let secs = (&ticks / ticks_per_sec).to_i64()?;
+    let nanos = (&ticks % ticks_per_sec).to_u32()? * (NANOS_PER_SEC / ticks_per_sec);
+    DateTime::from_timestamp(secs, nanos)
+}
+
+macro_rules! unix_timestamp {
+    ($name:ident, $ticks_per_sec:expr, $unit:literal) => {
+        #[doc = concat!(
+            "Unpack an `int` as a number of ", $unit, " since the Unix epoch.\n\n",
+            "Negative integers and timestamps not representable by [`DateTime`] are rejected."
+        )]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub DateTime<Utc>);
+
+        impl StarlarkTypeRepr for $name {
+            type Canonical = <i32 as StarlarkTypeRepr>::Canonical;
+
+            fn starlark_type_repr() -> Ty {
+                i32::starlark_type_repr()
+            }
+        }
+
+        impl<'v> UnpackValue<'v> for $name {
+            fn unpack_value(value: Value<'v>) -> Option<Self> {
+                unpack_timestamp(value, $ticks_per_sec).map($name)
+            }
+        }
+    };
+}
+
+unix_timestamp!(UnixSeconds, 1, "seconds");
+unix_timestamp!(UnixMillis, 1_000, "milliseconds");
+unix_timestamp!(UnixNanos, NANOS_PER_SEC, "nanoseconds");
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use num_bigint::BigInt;
+
+    use crate::values::int::UnixMillis;
+    use crate::values::int::UnixNanos;
+    use crate::values::int::UnixSeconds;
+    use crate::values::Heap;
+    use crate::values::UnpackValue;
+
+    #[test]
+    fn test_epoch() {
+        let heap = Heap::new();
+        let zero = heap.alloc(0);
+        assert_eq!(
+            Some(UnixSeconds(DateTime::UNIX_EPOCH)),
+            UnixSeconds::unpack_value(zero)
+        );
+        assert_eq!(
+            Some(UnixMillis(DateTime::UNIX_EPOCH)),
+            UnixMillis::unpack_value(zero)
+        );
+        assert_eq!(
+            Some(UnixNanos(DateTime::UNIX_EPOCH)),
+            UnixNanos::unpack_value(zero)
+        );
+    }
+
+    #[test]
+    fn test_known_values() {
+        let heap = Heap::new();
+        let expected = "2023-11-14T22:13:20.123Z".parse().unwrap();
+        assert_eq!(
+            Some(UnixSeconds("2023-11-14T22:13:20Z".parse().unwrap())),
+            UnixSeconds::unpack_value(heap.alloc(1_700_000_000))
+        );
+        assert_eq!(
+            Some(UnixMillis(expected)),
+            UnixMillis::unpack_value(heap.alloc(1_700_000_000_123i64))
+        );
+        assert_eq!(
+            Some(UnixNanos(expected)),
+            UnixNanos::unpack_value(heap.alloc(1_700_000_000_123_000_000i64))
+        );
+    }
+
+    #[test]
+    fn test_big_nanos() {
+        let heap = Heap::new();
+        // Does not fit into `i64`.
+        let nanos = heap.alloc(BigInt::from(10).pow(19) + 1);
+        assert_eq!(
+            Some(UnixNanos("2286-11-20T17:46:40.000000001Z".parse().unwrap())),
+            UnixNanos::unpack_value(nanos)
+        );
+    }
+
+    #[test]
+    fn test_reject() {
+        let heap = Heap::new();
+        assert_eq!(None, UnixSeconds::unpack_value(heap.alloc(-1)));
+        assert_eq!(None, UnixNanos::unpack_value(heap.alloc(-1)));
+        assert_eq!(None, UnixSeconds::unpack_value(heap.alloc("1")));
+        // Out of `DateTime` range.
+        assert_eq!(
+            None,
+            UnixSeconds::unpack_value(heap.alloc(BigInt::from(10).pow(30)))
+        );
+    }
+}
@@ -15,21 +15,116 @@
  * limitations under the License.
  */
 
+use std::hash::Hash;
+
+use starlark_map::small_map::SmallMap;
+
+use crate::typing::Ty;
 use crate::values::dict::DictRef;
+use crate::values::structs::StructRef;
 use crate::values::type_repr::DictType;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::UnpackValue;
 use crate::values::Value;
 
-/// Unpack `dict`.
+#[derive(Debug, thiserror::Error)]
+enum UnpackDictEntriesError {
+    #[error("Duplicate key at dict entry {0}")]
+    DuplicateKey(usize),
+    #[error("Dict key `{0}` is not of type `{1}`, got value of type `{2}`")]
+    KeyType(String, Ty, &'static str),
+    #[error("Dict value for key `{0}` is not of type `{1}`, got value of type `{2}`")]
+    ValueType(String, Ty, &'static str),
+}
+
+/// Unpack `dict`, or `struct` as a dict from field names to field values.
 ///
 /// There's `impl` [`UnpackValue`] for [`SmallMap`](starlark_map::small_map::SmallMap)
 /// but this can be used when hashing of unpacked keys is not needed.
+///
+/// Struct field names are string values, so structs are only accepted
+/// when `K` unpacks from a string, like `&str` or `String`.
+/// The type is `dict[K, V]` regardless: `struct` is not part of it,
+/// because it is not accepted for every `K`.
 pub struct UnpackDictEntries<K, V> {
     /// Entries of the dictionary.
     pub entries: Vec<(K, V)>,
 }
 
+impl<K: Hash + Eq, V> UnpackDictEntries<K, V> {
+    /// Collect the entries into a map, in the dict iteration order.
+    ///
+    /// Returns an error if two entries unpack to equal keys,
+    /// instead of keeping only one of them.
+    pub fn into_small_map(self) -> anyhow::Result<SmallMap<K, V>> {
+        let mut map = SmallMap::with_capacity(self.entries.len());
+        for (i, (k, v)) in self.entries.into_iter().enumerate() {
+            if map.insert(k, v).is_some() {
+                return Err(UnpackDictEntriesError::DuplicateKey(i).into());
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl<'v, K: UnpackValue<'v>, V: UnpackValue<'v>> UnpackDictEntries<K, V> {
+    /// Unpack like [`unpack_value`](UnpackValue::unpack_value), but on failure return
+    /// an error naming the first key whose entry could not be unpacked and the expected type.
+    pub fn try_unpack(value: Value<'v>) -> anyhow::Result<Self> {
+        if let Some(s) = StructRef::from_value(value) {
+            let len = s.iter().len();
+            return Self::try_unpack_entries(len, struct_entries(&s));
+        }
+        let dict = DictRef::unpack_value_err(value)?;
+        Self::try_unpack_entries(dict.len(), dict.iter())
+    }
+
+    fn try_unpack_entries(
+        len: usize,
+        iter: impl Iterator<Item = (Value<'v>, Value<'v>)>,
+    ) -> anyhow::Result<Self> {
+        let mut entries = Vec::with_capacity(len);
+        for (k, v) in iter {
+            let Some(key) = K::unpack_value(k) else {
+                return Err(UnpackDictEntriesError::KeyType(
+                    k.to_repr(),
+                    K::starlark_type_repr(),
+                    k.get_type(),
+                )
+                .into());
+            };
+            let Some(value) = V::unpack_value(v) else {
+                return Err(UnpackDictEntriesError::ValueType(
+                    k.to_repr(),
+                    V::starlark_type_repr(),
+                    v.get_type(),
+                )
+                .into());
+            };
+            entries.push((key, value));
+        }
+        Ok(UnpackDictEntries { entries })
+    }
+
+    fn unpack_entries(
+        len: usize,
+        iter: impl Iterator<Item = (Value<'v>, Value<'v>)>,
+    ) -> Option<Self> {
+        let mut entries = Vec::with_capacity(len);
+        for (k, v) in iter {
+            entries.push((K::unpack_value(k)?, V::unpack_value(v)?));
+        }
+        Some(UnpackDictEntries { entries })
+    }
+}
+
+/// Struct fields as dict entries, keys being field names.
+fn struct_entries<'v, 'a>(
+    s: &'a StructRef<'v>,
+) -> impl Iterator<Item = (Value<'v>, Value<'v>)> + 'a {
+    s.iter().map(|(k, v)| (k.to_value(), v))
+}
+
 impl<K: StarlarkTypeRepr, V: StarlarkTypeRepr> StarlarkTypeRepr for UnpackDictEntries<K, V> {
     type Canonical = <DictType<K, V> as StarlarkTypeRepr>::Canonical;
 
@@ -40,11 +135,113 @@ impl<K: StarlarkTypeRepr, V: StarlarkTypeRepr> StarlarkTypeRepr for UnpackDictEn
 
 impl<'v, K: UnpackValue<'v>, V: UnpackValue<'v>> UnpackValue<'v> for UnpackDictEntries<K, V> {
     fn unpack_value(value: Value<'v>) -> Option<Self> {
-        let dict = DictRef::unpack_value(value)?;
-        let mut entries = Vec::with_capacity(dict.len());
-        for (k, v) in dict.iter() {
-            entries.push((K::unpack_value(k)?, V::unpack_value(v)?));
+        if let Some(dict) = DictRef::unpack_value(value) {
+            Self::unpack_entries(dict.len(), dict.iter())
+        } else {
+            let s = StructRef::from_value(value)?;
+            let len = s.iter().len();
+            Self::unpack_entries(len, struct_entries(&s))
         }
-        Some(UnpackDictEntries { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::dict::AllocDict;
+    use crate::values::dict::UnpackDictEntries;
+    use crate::values::structs::AllocStruct;
+    use crate::values::type_repr::StarlarkTypeRepr;
+    use crate::values::Heap;
+    use crate::values::UnpackValue;
+    use crate::values::Value;
+
+    #[test]
+    fn test_into_small_map() {
+        let heap = Heap::new();
+        let d = heap.alloc(AllocDict([("b", 2), ("a", 1), ("c", 3)]));
+        let map = UnpackDictEntries::<&str, i32>::unpack_value(d)
+            .unwrap()
+            .into_small_map()
+            .unwrap();
+        assert_eq!(
+            vec![("b", 2), ("a", 1), ("c", 3)],
+            map.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_try_unpack() {
+        let heap = Heap::new();
+        let d = heap.alloc(AllocDict([("a", heap.alloc(1)), ("b", heap.alloc("x"))]));
+        assert_eq!(
+            "Dict value for key `\"b\"` is not of type `int`, got value of type `string`",
+            UnpackDictEntries::<&str, i32>::try_unpack(d)
+                .err()
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "Dict key `\"a\"` is not of type `int`, got value of type `string`",
+            UnpackDictEntries::<i32, i32>::try_unpack(d)
+                .err()
+                .unwrap()
+                .to_string()
+        );
+        let entries = UnpackDictEntries::<&str, Value>::try_unpack(d).unwrap();
+        assert_eq!(2, entries.entries.len());
+        assert!(UnpackDictEntries::<&str, i32>::try_unpack(heap.alloc(1)).is_err());
+    }
+
+    #[test]
+    fn test_unpack_struct() {
+        let heap = Heap::new();
+        let s = heap.alloc(AllocStruct([("b", heap.alloc(2)), ("a", heap.alloc(1))]));
+        assert_eq!(
+            vec![("b", 2), ("a", 1)],
+            UnpackDictEntries::<&str, i32>::unpack_value(s)
+                .unwrap()
+                .entries
+        );
+        assert_eq!(
+            2,
+            UnpackDictEntries::<String, i32>::try_unpack(s)
+                .unwrap()
+                .entries
+                .len()
+        );
+        assert!(UnpackDictEntries::<i32, i32>::unpack_value(s).is_none());
+        assert_eq!(
+            "Dict key `\"b\"` is not of type `int`, got value of type `string`",
+            UnpackDictEntries::<i32, i32>::try_unpack(s)
+                .err()
+                .unwrap()
+                .to_string()
+        );
+        assert!(UnpackDictEntries::<&str, &str>::unpack_value(s).is_none());
+        assert_eq!(
+            "dict[str, int]",
+            UnpackDictEntries::<&str, i32>::starlark_type_repr().to_string()
+        );
+        assert!(
+            UnpackDictEntries::<&str, i32>::unpack_value(heap.alloc(AllocStruct::EMPTY))
+                .unwrap()
+                .entries
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_into_small_map_duplicate_key() {
+        let entries = UnpackDictEntries {
+            entries: vec![
+                ("x".to_owned(), 1),
+                ("y".to_owned(), 2),
+                ("x".to_owned(), 3),
+            ],
+        };
+        assert_eq!(
+            "Duplicate key at dict entry 2",
+            entries.into_small_map().unwrap_err().to_string()
+        );
     }
 }
@@ -28,6 +28,7 @@ use std::mem;
 use std::ops::Deref;
 
 use allocative::Allocative;
+use anyhow::Context;
 use display_container::fmt_keyed_container;
 use serde::Serialize;
 use starlark_derive::starlark_value;
@@ -320,7 +321,18 @@ impl FrozenDictData {
 impl<'v> Freeze for DictGen<RefCell<Dict<'v>>> {
     type Frozen = DictGen<FrozenDictData>;
     fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
-        let content = self.0.into_inner().content.freeze(freezer)?;
+        let dict = self.0.into_inner();
+        let mut content = SmallMap::with_capacity(dict.content.len());
+        for (key, value) in dict.content.into_iter_hashed() {
+            let hash = key.hash();
+            let key = key.into_key().freeze(freezer)?;
+            // TODO(nga): verify hash unchanged after freeze.
+            let key = Hashed::new_unchecked(hash, key);
+            let value = value
+                .freeze(freezer)
+                .with_context(|| format!("failed to freeze value of key {}", key.key()))?;
+            content.insert_hashed_unique_unchecked(key, value);
+        }
         Ok(DictGen(FrozenDictData { content }))
     }
 }
@@ -254,7 +254,7 @@ impl<'v> UnpackValue<'v> for StarlarkFloat {
 /// Allows either a float or an int. If the int is not in the range of a float, it will lose precision.
 impl<'v> UnpackValue<'v> for f64 {
     fn unpack_value(value: Value<'v>) -> Option<Self> {
-        value.unpack_num().map(|x| x.as_float())
+        value.unpack_num_ref().map(|x| x.as_float())
     }
 }
 
@@ -267,7 +267,7 @@ impl Display for StarlarkFloat {
 #[starlark_value(type = StarlarkFloat::TYPE)]
 impl<'v> StarlarkValue<'v> for StarlarkFloat {
     fn equals(&self, other: Value) -> crate::Result<bool> {
-        Ok(Some(NumRef::Float(StarlarkFloat(self.0))) == other.unpack_num())
+        Ok(NumRef::Float(StarlarkFloat(self.0)).equals_value(other))
     }
 
     fn collect_repr(&self, s: &mut String) {
@@ -296,36 +296,40 @@ impl<'v> StarlarkValue<'v> for StarlarkFloat {
     }
 
     fn add(&self, other: Value, heap: &'v Heap) -> Option<crate::Result<Value<'v>>> {
-        Some(Ok(heap.alloc(NumRef::Float(*self) + other.unpack_num()?)))
+        Some(Ok(
+            heap.alloc(NumRef::Float(*self) + other.unpack_num_ref()?)
+        ))
     }
 
     fn sub(&self, other: Value, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             None => ValueError::unsupported_with(self, "-", other),
             Some(other) => Ok(heap.alloc(NumRef::Float(*self) - other)),
         }
     }
 
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> Option<crate::Result<Value<'v>>> {
-        Some(Ok(heap.alloc(NumRef::Float(*self) * other.unpack_num()?)))
+        Some(Ok(
+            heap.alloc(NumRef::Float(*self) * other.unpack_num_ref()?)
+        ))
     }
 
     fn div(&self, other: Value, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             None => ValueError::unsupported_with(self, "/", other),
             Some(other) => Ok(heap.alloc(NumRef::Float(*self).div(other)?)),
         }
     }
 
     fn percent(&self, other: Value, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(other) => Ok(heap.alloc(NumRef::Float(*self).percent(other)?)),
             None => ValueError::unsupported_with(self, "%", other),
         }
     }
 
     fn floor_div(&self, other: Value, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             None => ValueError::unsupported_with(self, "//", other),
             Some(other) => Ok(heap.alloc(NumRef::Float(*self).floor_div(other)?)),
         }
@@ -336,7 +340,7 @@ impl<'v> StarlarkValue<'v> for StarlarkFloat {
     }
 
     fn compare(&self, other: Value) -> crate::Result<Ordering> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             None => ValueError::unsupported_with(self, "compare", other),
             Some(other) => Ok(NumRef::Float(*self).cmp(&other)),
         }
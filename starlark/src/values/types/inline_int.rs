@@ -170,11 +170,15 @@ impl InlineInt {
             .and_then(|i| InlineInt::try_from(i).ok())
     }
 
+    /// Left shift, `None` if any bits (including the sign bit) are shifted out.
     #[inline]
     pub(crate) fn checked_shl(self, rhs: u32) -> Option<InlineInt> {
-        self.0
-            .checked_shl(rhs)
-            .and_then(|i| InlineInt::try_from(i).ok())
+        // `i32::checked_shl` only checks the shift amount, not the overflow.
+        let r = self.0.checked_shl(rhs)?;
+        if r >> rhs != self.0 {
+            return None;
+        }
+        InlineInt::try_from(r).ok()
     }
 
     pub(crate) fn to_bigint(self) -> BigInt {
@@ -263,6 +267,24 @@ impl TryFrom<isize> for InlineInt {
     }
 }
 
+impl TryFrom<u128> for InlineInt {
+    type Error = InlineIntOverflow;
+
+    #[inline]
+    fn try_from(value: u128) -> Result<Self, Self::Error> {
+        InlineInt::try_from_impl(value)
+    }
+}
+
+impl TryFrom<i128> for InlineInt {
+    type Error = InlineIntOverflow;
+
+    #[inline]
+    fn try_from(value: i128) -> Result<Self, Self::Error> {
+        InlineInt::try_from_impl(value)
+    }
+}
+
 impl<'v> TryFrom<&'v BigInt> for InlineInt {
     type Error = InlineIntOverflow;
 
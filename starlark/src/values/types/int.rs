@@ -56,8 +56,18 @@ use crate::values::num::typecheck::typecheck_num_bin_op;
 use crate::values::num::typecheck::NumTy;
 use crate::values::num::value::NumRef;
 use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::types::bigint::convert::int_param_error;
+use crate::values::types::bigint::convert::unpack_int_param;
+#[cfg(feature = "chrono")]
+pub use crate::values::types::bigint::timestamp::UnixMillis;
+#[cfg(feature = "chrono")]
+pub use crate::values::types::bigint::timestamp::UnixNanos;
+#[cfg(feature = "chrono")]
+pub use crate::values::types::bigint::timestamp::UnixSeconds;
 use crate::values::types::bigint::StarlarkBigInt;
 use crate::values::types::inline_int::InlineInt;
+pub use crate::values::types::int_or_big::IntByteOrder;
+pub use crate::values::types::int_or_big::IntWidth;
 use crate::values::types::int_or_big::StarlarkInt;
 use crate::values::types::int_or_big::StarlarkIntRef;
 use crate::values::AllocFrozenValue;
@@ -72,6 +82,77 @@ use crate::values::Value;
 /// The result of calling `type()` on integers.
 pub const INT_TYPE: &str = "int";
 
+/// Encode an `int` value as exactly `length` bytes,
+/// like Python's `int.to_bytes(length, byteorder, signed = signed)`.
+///
+/// Fails if the value is not an `int`, if it does not fit into `length` bytes,
+/// or if it is negative and `signed` is `false`.
+pub fn int_to_bytes(
+    value: Value,
+    length: usize,
+    byteorder: IntByteOrder,
+    signed: bool,
+) -> anyhow::Result<Vec<u8>> {
+    value
+        .unpack_or_err::<StarlarkIntRef>()?
+        .to_bytes(length, byteorder, signed)
+}
+
+/// Decode an `int` value from bytes,
+/// like Python's `int.from_bytes(bytes, byteorder, signed = signed)`.
+pub fn int_from_bytes<'v>(
+    heap: &'v Heap,
+    bytes: &[u8],
+    byteorder: IntByteOrder,
+    signed: bool,
+) -> Value<'v> {
+    heap.alloc(StarlarkInt::from_bytes(bytes, byteorder, signed))
+}
+
+/// Parse an `int` value from ASCII digits in given base (2 to 36),
+/// with an optional leading `+` or `-`, without converting the bytes to a string first.
+///
+/// Fails on invalid digits, including underscores and base prefixes.
+pub fn int_from_ascii_bytes<'v>(
+    heap: &'v Heap,
+    bytes: &[u8],
+    radix: u32,
+) -> anyhow::Result<Value<'v>> {
+    Ok(heap.alloc(StarlarkInt::from_ascii_bytes(bytes, radix)?))
+}
+
+/// Reduce the fraction `num / den` of two `int` values to lowest terms,
+/// with a positive denominator.
+///
+/// Fails if either value is not an `int`, or if the denominator is zero.
+pub fn reduce_fraction<'v>(
+    heap: &'v Heap,
+    num: Value<'v>,
+    den: Value<'v>,
+) -> anyhow::Result<(Value<'v>, Value<'v>)> {
+    let (num, den) = StarlarkInt::reduce_fraction(
+        num.unpack_or_err::<StarlarkIntRef>()?,
+        den.unpack_or_err::<StarlarkIntRef>()?,
+    )?;
+    Ok((heap.alloc(num), heap.alloc(den)))
+}
+
+/// Render an `int` value compactly for logs: exactly if it is small,
+/// otherwise rounded like `1.27e38 (approx)`.
+///
+/// Fails if the value is not an `int`.
+pub fn int_approx_display(value: Value) -> anyhow::Result<String> {
+    Ok(value.unpack_or_err::<StarlarkIntRef>()?.approx_display())
+}
+
+/// Check whether an `int` value fits the Rust integer type without converting it,
+/// for example to pick a wider type before unpacking.
+///
+/// Fails if the value is not an `int`.
+pub fn int_fits(value: Value, width: IntWidth) -> anyhow::Result<bool> {
+    Ok(value.unpack_or_err::<StarlarkIntRef>()?.fits(width))
+}
+
 impl<'v> AllocValue<'v> for i32 {
     #[inline]
     fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
@@ -93,14 +174,16 @@ impl StarlarkTypeRepr for i32 {
     }
 }
 
-impl UnpackValue<'_> for i32 {
-    fn unpack_value(value: Value) -> Option<Self> {
+impl<'v> UnpackValue<'v> for i32 {
+    fn unpack_value(value: Value<'v>) -> Option<Self> {
         if InlineInt::smaller_than_i32() {
             StarlarkIntRef::unpack_value(value)?.to_i32()
         } else {
             Some(InlineInt::unpack_value(value)?.to_i32())
         }
     }
+
+    unpack_int_param!(i32);
 }
 
 // WARNING: This type isn't a real type, a pointer to this is secretly an i32.
@@ -187,7 +270,7 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
     }
 
     fn equals(&self, other: Value) -> crate::Result<bool> {
-        Ok(Some(NumRef::Int(StarlarkIntRef::Small(self.get()))) == other.unpack_num())
+        Ok(NumRef::Int(StarlarkIntRef::Small(self.get())).equals_value(other))
     }
 
     fn to_bool(&self) -> bool {
@@ -210,22 +293,22 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
     }
     fn add(&self, other: Value<'v>, heap: &'v Heap) -> Option<crate::Result<Value<'v>>> {
         Some(Ok(heap.alloc(
-            NumRef::Int(StarlarkIntRef::Small(self.get())) + other.unpack_num()?,
+            NumRef::Int(StarlarkIntRef::Small(self.get())) + other.unpack_num_ref()?,
         )))
     }
     fn sub(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(other) => Ok(heap.alloc(NumRef::Int(StarlarkIntRef::Small(self.get())) - other)),
             None => ValueError::unsupported_with(self, "-", other),
         }
     }
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> Option<crate::Result<Value<'v>>> {
         Some(Ok(heap.alloc(
-            NumRef::Int(StarlarkIntRef::Small(self.get())) * other.unpack_num()?,
+            NumRef::Int(StarlarkIntRef::Small(self.get())) * other.unpack_num_ref()?,
         )))
     }
     fn div(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(other) => {
                 Ok(heap.alloc(NumRef::Int(StarlarkIntRef::Small(self.get())).div(other)?))
             }
@@ -233,7 +316,7 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
         }
     }
     fn percent(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(other) => {
                 Ok(heap.alloc(NumRef::Int(StarlarkIntRef::Small(self.get())).percent(other)?))
             }
@@ -241,7 +324,7 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
         }
     }
     fn floor_div(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             Some(other) => {
                 Ok(heap.alloc(NumRef::Int(StarlarkIntRef::Small(self.get())).floor_div(other)?))
             }
@@ -250,7 +333,7 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
     }
 
     fn compare(&self, other: Value) -> crate::Result<Ordering> {
-        match other.unpack_num() {
+        match other.unpack_num_ref() {
             None => ValueError::unsupported_with(self, "compare", other),
             Some(other) => Ok(NumRef::Int(StarlarkIntRef::Small(self.get())).cmp(&other)),
         }
@@ -377,4 +460,46 @@ mod tests {
         // `get_type` calls `as_avalue_dyn` internally.
         assert_eq!("int", Value::new_int(InlineInt::MINUS_ONE).get_type());
     }
+
+    #[test]
+    fn test_int_to_from_bytes() {
+        let heap = Heap::new();
+        let bytes = int_to_bytes(heap.alloc(-2), 2, IntByteOrder::Big, true).unwrap();
+        assert_eq!(vec![0xff, 0xfe], bytes);
+        assert_eq!(
+            -2,
+            int_from_bytes(&heap, &bytes, IntByteOrder::Big, true)
+                .unpack_i32()
+                .unwrap()
+        );
+        assert!(int_to_bytes(heap.alloc("1"), 2, IntByteOrder::Big, true).is_err());
+    }
+
+    #[test]
+    fn test_int_from_ascii_bytes() {
+        let heap = Heap::new();
+        let v = int_from_ascii_bytes(&heap, b"-1000000000000000000000", 10).unwrap();
+        assert_eq!("-1000000000000000000000", v.to_str());
+        assert!(int_from_ascii_bytes(&heap, b"12x", 10).is_err());
+    }
+
+    #[test]
+    fn test_reduce_fraction() {
+        let heap = Heap::new();
+        let (num, den) = reduce_fraction(&heap, heap.alloc(6), heap.alloc(-4)).unwrap();
+        assert_eq!((Some(-3), Some(2)), (num.unpack_i32(), den.unpack_i32()));
+        assert!(reduce_fraction(&heap, heap.alloc(1), heap.alloc(0)).is_err());
+        assert!(reduce_fraction(&heap, heap.alloc(1.0), heap.alloc(2)).is_err());
+    }
+
+    #[test]
+    fn test_int_approx_display() {
+        let heap = Heap::new();
+        assert_eq!("42", int_approx_display(heap.alloc(42)).unwrap());
+        assert_eq!(
+            "1.84e19 (approx)",
+            int_approx_display(heap.alloc(u64::MAX)).unwrap()
+        );
+        assert!(int_approx_display(heap.alloc("1")).is_err());
+    }
 }
@@ -16,6 +16,8 @@
  */
 
 use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Write;
 use std::ops::Add;
 use std::ops::BitAnd;
 use std::ops::BitOr;
@@ -29,8 +31,12 @@ use std::str::FromStr;
 use anyhow::Context;
 use dupe::Dupe;
 use num_bigint::BigInt;
+use num_bigint::BigUint;
 use num_bigint::Sign;
+use num_integer::Integer;
 use num_traits::FromPrimitive;
+use num_traits::Num;
+use num_traits::One;
 use num_traits::Signed;
 use num_traits::ToPrimitive;
 use num_traits::Zero;
@@ -40,6 +46,7 @@ use crate as starlark;
 use crate::typing::Ty;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::types::bigint::StarlarkBigInt;
+use crate::values::types::float::StarlarkFloat;
 use crate::values::types::inline_int::InlineInt;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
@@ -61,6 +68,32 @@ enum StarlarkIntError {
     LeftShiftNegative,
     #[error("Negative right shift")]
     RightShiftNegative,
+    #[error("Cannot parse empty string as an integer")]
+    ParseEmpty,
+    #[error("Cannot parse `{0}` as an integer in base {1}: invalid digit `{2}`")]
+    ParseInvalidDigit(String, u32, char),
+    #[error("Cannot parse bytes as an integer in base {1}: invalid byte `{2:#04x}` at offset {0}")]
+    ParseInvalidByte(usize, u32, u8),
+    #[error("Invalid base {0}, must be between 2 and 36")]
+    ParseInvalidRadix(u32),
+    #[error("Integer `{0}` does not fit into {1} bytes")]
+    ToBytesOverflow(StarlarkInt, usize),
+    #[error("Cannot convert negative integer `{0}` to unsigned bytes")]
+    ToBytesNegativeUnsigned(StarlarkInt),
+    #[error("Cannot convert float `{0}` to integer ratio")]
+    IntegerRatioNonFinite(f64),
+    #[error("Zero denominator in fraction: {0}/0")]
+    FractionZeroDenominator(StarlarkInt),
+    #[error("`{0}` must be a non-negative integer, got `{1}`")]
+    CombinatoricsNegative(&'static str, StarlarkInt),
+    #[error("`k` is too large: {0}, at most 100000 is supported")]
+    CombinatoricsTooLarge(StarlarkInt),
+    #[error("Empty range for random integer: `lo` is {0}, `hi` is {1}")]
+    RandomEmptyRange(StarlarkInt, StarlarkInt),
+    #[error("Modulus must be positive, got `{0}`")]
+    ModInverseNonPositiveModulus(StarlarkInt),
+    #[error("`{0}` is not invertible modulo `{1}`")]
+    ModInverseNotInvertible(StarlarkInt, StarlarkInt),
 }
 
 #[derive(
@@ -87,6 +120,37 @@ impl StarlarkTypeRepr for StarlarkInt {
     }
 }
 
+/// How to display the sign when formatting an integer.
+#[derive(Eq, PartialEq, Copy, Clone, Dupe, Debug)]
+pub(crate) enum IntSignDisplay {
+    /// Only negative numbers have a sign: `-5`, `5`, `0`.
+    Negative,
+    /// Non-negative numbers (including zero) are prefixed with `+`: `-5`, `+5`, `+0`.
+    Always,
+}
+
+/// Fixed-width Rust integer type an `int` may be checked to fit.
+#[derive(Eq, PartialEq, Copy, Clone, Dupe, Debug)]
+pub enum IntWidth {
+    /// `i32`.
+    I32,
+    /// `u32`.
+    U32,
+    /// `i64`.
+    I64,
+    /// `u64`.
+    U64,
+}
+
+/// Byte order for converting integers to and from bytes.
+#[derive(Eq, PartialEq, Copy, Clone, Dupe, Debug)]
+pub enum IntByteOrder {
+    /// Most significant byte first.
+    Big,
+    /// Least significant byte first.
+    Little,
+}
+
 #[derive(Eq, PartialEq, Copy, Clone, Dupe, Debug)]
 pub(crate) enum StarlarkIntRef<'v> {
     Small(InlineInt),
@@ -102,9 +166,80 @@ impl FromStr for StarlarkInt {
     }
 }
 
+/// Integers with at most this many digits are printed exactly by `approx_display`.
+pub(crate) const APPROX_DISPLAY_EXACT_DIGITS: usize = 15;
+/// Number of significant digits printed by `approx_display` for larger integers.
+pub(crate) const APPROX_DISPLAY_PRECISION: usize = 3;
+
 impl StarlarkInt {
+    /// Wrap a result of an operation on big ints which cannot fit into [`InlineInt`],
+    /// skipping the check for demotion to small int done by `From<BigInt>`.
+    ///
+    /// Big ints are outside of `InlineInt` range, so for example a product of two
+    /// big ints, or a big int shifted left, is big too.
+    fn from_big_unchecked(value: BigInt) -> StarlarkInt {
+        StarlarkInt::Big(StarlarkBigInt::unchecked_new(value))
+    }
+
+    /// Parse digits (without sign or base prefix) in given base.
+    ///
+    /// Digit strings of any length are accepted, values which do not fit
+    /// into a small int are parsed as big ints. Malformed input (empty string
+    /// or a character which is not a digit in given base) is an error.
     pub(crate) fn from_str_radix(s: &str, base: u32) -> crate::Result<StarlarkInt> {
-        Ok(StarlarkInt::from(TokenInt::from_str_radix(s, base)?))
+        if let Ok(i) = i32::from_str_radix(s, base) {
+            return Ok(StarlarkInt::from(i));
+        }
+        if s.is_empty() {
+            return Err(crate::Error::new_value(StarlarkIntError::ParseEmpty));
+        }
+        if let Some(c) = s.chars().find(|c| *c != '_' && !c.is_digit(base)) {
+            return Err(crate::Error::new_value(
+                StarlarkIntError::ParseInvalidDigit(s.to_owned(), base, c),
+            ));
+        }
+        match BigInt::from_str_radix(s, base) {
+            Ok(i) => Ok(StarlarkInt::from(i)),
+            // All characters are digits or underscores, but underscores are misplaced.
+            Err(_) => Ok(StarlarkInt::from(TokenInt::from_str_radix(s, base)?)),
+        }
+    }
+
+    /// Parse ASCII digits in given base, with an optional leading `+` or `-`,
+    /// directly from bytes, without validating them as UTF-8 first.
+    ///
+    /// Unlike [`from_str_radix`](StarlarkInt::from_str_radix), underscores
+    /// and base prefixes are not accepted.
+    pub(crate) fn from_ascii_bytes(bytes: &[u8], radix: u32) -> anyhow::Result<StarlarkInt> {
+        if !(2..=36).contains(&radix) {
+            return Err(StarlarkIntError::ParseInvalidRadix(radix).into());
+        }
+        let (negative, start) = match bytes.first() {
+            Some(b'-') => (true, 1),
+            Some(b'+') => (false, 1),
+            _ => (false, 0),
+        };
+        if bytes.len() == start {
+            return Err(StarlarkIntError::ParseEmpty.into());
+        }
+        let mut digits = Vec::with_capacity(bytes.len() - start);
+        for (i, b) in bytes.iter().enumerate().skip(start) {
+            match (*b as char).to_digit(radix) {
+                Some(d) => digits.push(d as u8),
+                None => return Err(StarlarkIntError::ParseInvalidByte(i, radix, *b).into()),
+            }
+        }
+        // Fast path for values which fit into `i64`.
+        let small = digits.iter().try_fold(0i64, |acc, d| {
+            acc.checked_mul(radix as i64)?.checked_add(*d as i64)
+        });
+        if let Some(i) = small {
+            return Ok(StarlarkInt::from(if negative { -i } else { i }));
+        }
+        // Cannot fail: digits are valid and non-empty.
+        let i = BigUint::from_radix_be(&digits, radix).unwrap();
+        let sign = if negative { Sign::Minus } else { Sign::Plus };
+        Ok(StarlarkInt::from(BigInt::from_biguint(sign, i)))
     }
 
     pub(crate) fn from_f64_exact(f: f64) -> anyhow::Result<StarlarkInt> {
@@ -121,6 +256,82 @@ impl StarlarkInt {
         }
     }
 
+    /// Exact `(numerator, denominator)` of a finite float, like Python's `float.as_integer_ratio`.
+    ///
+    /// The fraction is in lowest terms, and the denominator is positive.
+    pub(crate) fn integer_ratio_f64(f: f64) -> anyhow::Result<(StarlarkInt, StarlarkInt)> {
+        if !f.is_finite() {
+            return Err(StarlarkIntError::IntegerRatioNonFinite(f).into());
+        }
+        let bits = f.to_bits();
+        let biased_exponent = ((bits >> 52) & 0x7ff) as i32;
+        let fraction = bits & ((1 << 52) - 1);
+        let (mantissa, exponent) = if biased_exponent == 0 {
+            // Subnormal.
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), biased_exponent - 1075)
+        };
+        if mantissa == 0 {
+            return Ok((StarlarkInt::from(0), StarlarkInt::from(1)));
+        }
+        // The denominator is a power of two, so lowest terms means odd numerator
+        // unless the denominator is one.
+        let trailing_zeros = mantissa.trailing_zeros() as i32;
+        let (mantissa, exponent) = if exponent < 0 {
+            let shift = trailing_zeros.min(-exponent);
+            (mantissa >> shift, exponent + shift)
+        } else {
+            (mantissa, exponent)
+        };
+        let mut numerator = BigInt::from(mantissa);
+        let mut denominator = BigInt::from(1);
+        if exponent >= 0 {
+            numerator <<= exponent as u32;
+        } else {
+            denominator <<= (-exponent) as u32;
+        }
+        if f.is_sign_negative() {
+            numerator = -numerator;
+        }
+        Ok((StarlarkInt::from(numerator), StarlarkInt::from(denominator)))
+    }
+
+    /// Divide the numerator and the denominator by their greatest common divisor,
+    /// and normalize the sign so the denominator is positive.
+    pub(crate) fn reduce_fraction(
+        num: StarlarkIntRef,
+        den: StarlarkIntRef,
+    ) -> anyhow::Result<(StarlarkInt, StarlarkInt)> {
+        if den == 0 {
+            return Err(StarlarkIntError::FractionZeroDenominator(num.to_owned()).into());
+        }
+        let num = num.to_big();
+        let den = den.to_big();
+        // Non-negative, and non-zero because the denominator is non-zero.
+        let gcd = num.gcd(&den);
+        let (mut num, mut den) = (num / &gcd, den / &gcd);
+        if den.is_negative() {
+            num = -num;
+            den = -den;
+        }
+        Ok((StarlarkInt::from(num), StarlarkInt::from(den)))
+    }
+
+    /// Decode an integer from bytes, like Python's `int.from_bytes`.
+    ///
+    /// If `signed`, the bytes are interpreted as two's complement.
+    /// Empty bytes decode to zero.
+    pub(crate) fn from_bytes(bytes: &[u8], byteorder: IntByteOrder, signed: bool) -> StarlarkInt {
+        let i = match (byteorder, signed) {
+            (IntByteOrder::Big, true) => BigInt::from_signed_bytes_be(bytes),
+            (IntByteOrder::Little, true) => BigInt::from_signed_bytes_le(bytes),
+            (IntByteOrder::Big, false) => BigInt::from_bytes_be(Sign::Plus, bytes),
+            (IntByteOrder::Little, false) => BigInt::from_bytes_le(Sign::Plus, bytes),
+        };
+        StarlarkInt::from(i)
+    }
+
     pub(crate) fn as_ref(&self) -> StarlarkIntRef {
         match self {
             StarlarkInt::Small(i) => StarlarkIntRef::Small(*i),
@@ -150,7 +361,7 @@ impl<'v> StarlarkIntRef<'v> {
         }
     }
 
-    fn to_big(self) -> BigInt {
+    pub(crate) fn to_big(self) -> BigInt {
         match self {
             StarlarkIntRef::Small(i) => i.to_bigint(),
             StarlarkIntRef::Big(i) => i.get().clone(),
@@ -171,6 +382,33 @@ impl<'v> StarlarkIntRef<'v> {
         }
     }
 
+    /// Get the value as `i64` if it fits, checking the inline representation first.
+    ///
+    /// Inline integers always fit, so for them this is a single branch.
+    /// Big integers are converted by looking at their digits.
+    ///
+    /// For loops over many values which are usually small, accumulate in `i64`
+    /// and switch to the big path only on the first value which does not fit,
+    /// or on overflow:
+    ///
+    /// ```ignore
+    /// let mut sum: i64 = 0;
+    /// for (i, x) in xs.iter().enumerate() {
+    ///     match x.as_i64_fast().and_then(|x| sum.checked_add(x)) {
+    ///         Some(s) => sum = s,
+    ///         // Slow path: continue with big integers from here.
+    ///         None => return sum_big(StarlarkInt::from(sum), &xs[i..]),
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub(crate) fn as_i64_fast(self) -> Option<i64> {
+        match self {
+            StarlarkIntRef::Small(i) => Some(i.to_i32() as i64),
+            StarlarkIntRef::Big(i) => i.get().to_i64(),
+        }
+    }
+
     pub(crate) fn to_u64(self) -> Option<u64> {
         match self {
             StarlarkIntRef::Small(i) => i.to_u64(),
@@ -178,6 +416,52 @@ impl<'v> StarlarkIntRef<'v> {
         }
     }
 
+    /// Whether the value fits `i32`, without converting it.
+    #[inline]
+    pub(crate) fn fits_i32(self) -> bool {
+        match self {
+            StarlarkIntRef::Small(_) => true,
+            StarlarkIntRef::Big(i) => i.get().to_i32().is_some(),
+        }
+    }
+
+    /// Whether the value fits `u32`, without converting it.
+    #[inline]
+    pub(crate) fn fits_u32(self) -> bool {
+        match self {
+            StarlarkIntRef::Small(i) => i.to_i32() >= 0,
+            StarlarkIntRef::Big(i) => i.get().to_u32().is_some(),
+        }
+    }
+
+    /// Whether the value fits `i64`, without converting it.
+    #[inline]
+    pub(crate) fn fits_i64(self) -> bool {
+        match self {
+            StarlarkIntRef::Small(_) => true,
+            StarlarkIntRef::Big(i) => i.get().to_i64().is_some(),
+        }
+    }
+
+    /// Whether the value fits `u64`, without converting it.
+    #[inline]
+    pub(crate) fn fits_u64(self) -> bool {
+        match self {
+            StarlarkIntRef::Small(i) => i.to_i32() >= 0,
+            StarlarkIntRef::Big(i) => i.get().to_u64().is_some(),
+        }
+    }
+
+    /// Whether the value fits the Rust integer type, without converting it.
+    pub(crate) fn fits(self, width: IntWidth) -> bool {
+        match width {
+            IntWidth::I32 => self.fits_i32(),
+            IntWidth::U32 => self.fits_u32(),
+            IntWidth::I64 => self.fits_i64(),
+            IntWidth::U64 => self.fits_u64(),
+        }
+    }
+
     fn floor_div_small_small(a: InlineInt, b: InlineInt) -> anyhow::Result<StarlarkInt> {
         if b == 0 {
             return Err(StarlarkIntError::FloorDivisionByZero(
@@ -254,6 +538,30 @@ impl<'v> StarlarkIntRef<'v> {
         }
     }
 
+    /// `divmod(a, b)`, that is `(a // b, a % b)`: the remainder has the sign of `b`.
+    pub(crate) fn divmod(
+        self,
+        other: StarlarkIntRef,
+    ) -> anyhow::Result<(StarlarkInt, StarlarkInt)> {
+        match (self, other) {
+            (StarlarkIntRef::Small(_), StarlarkIntRef::Small(_)) => {
+                Ok((self.floor_div(other)?, self.percent(other)?))
+            }
+            _ => {
+                let (a, b) = (self.to_big(), other.to_big());
+                if b.is_zero() {
+                    return Err(StarlarkIntError::FloorDivisionByZero(
+                        self.to_owned(),
+                        other.to_owned(),
+                    )
+                    .into());
+                }
+                let (q, r) = a.div_mod_floor(&b);
+                Ok((StarlarkInt::from(q), StarlarkInt::from(r)))
+            }
+        }
+    }
+
     fn percent_small(a: InlineInt, b: InlineInt) -> anyhow::Result<InlineInt> {
         if b == 0 {
             return Err(StarlarkIntError::ModuloByZero(
@@ -342,7 +650,11 @@ impl<'v> StarlarkIntRef<'v> {
             StarlarkIntRef::Small(b) => {
                 // No overflow, checked above.
                 let b = b.to_u64().unwrap();
-                Ok(StarlarkInt::from(self.to_big() << b))
+                match self {
+                    // Shift is positive, checked above.
+                    StarlarkIntRef::Big(a) => Ok(StarlarkInt::from_big_unchecked(a.get() << b)),
+                    StarlarkIntRef::Small(a) => Ok(StarlarkInt::from(a.to_bigint() << b)),
+                }
             }
         }
     }
@@ -384,12 +696,412 @@ impl<'v> StarlarkIntRef<'v> {
         }
     }
 
+    /// Write the integer in radix 2, 8, 10 or 16, without base prefix.
+    ///
+    /// The sign always precedes the digits (`-ff`, `+ff`), so the output
+    /// can be parsed back with `int(s, radix)` regardless of sign display.
+    pub(crate) fn write_radix(
+        self,
+        out: &mut String,
+        radix: u32,
+        upper: bool,
+        sign: IntSignDisplay,
+    ) {
+        fn write_abs<T>(out: &mut String, abs: T, radix: u32, upper: bool)
+        where
+            T: fmt::Display + fmt::Binary + fmt::Octal + fmt::LowerHex + fmt::UpperHex,
+        {
+            match (radix, upper) {
+                (2, _) => write!(out, "{:b}", abs),
+                (8, _) => write!(out, "{:o}", abs),
+                (10, _) => write!(out, "{}", abs),
+                (16, false) => write!(out, "{:x}", abs),
+                (16, true) => write!(out, "{:X}", abs),
+                _ => unreachable!("unsupported radix: {}", radix),
+            }
+            .unwrap()
+        }
+
+        if self.is_negative() {
+            out.push('-');
+        } else if sign == IntSignDisplay::Always {
+            out.push('+');
+        }
+        match self {
+            StarlarkIntRef::Small(i) => {
+                write_abs(out, (i.to_i32() as i64).unsigned_abs(), radix, upper)
+            }
+            StarlarkIntRef::Big(i) => write_abs(out, i.get().magnitude(), radix, upper),
+        }
+    }
+
+    /// Encode the integer as exactly `length` bytes, like Python's `int.to_bytes`.
+    ///
+    /// If `signed`, two's complement is used, otherwise negative integers are an error.
+    /// Fails if the integer does not fit into `length` bytes.
+    pub(crate) fn to_bytes(
+        self,
+        length: usize,
+        byteorder: IntByteOrder,
+        signed: bool,
+    ) -> anyhow::Result<Vec<u8>> {
+        let i = self.to_big();
+        if !signed && i.is_negative() {
+            return Err(StarlarkIntError::ToBytesNegativeUnsigned(self.to_owned()).into());
+        }
+        // Minimal little endian representation. Zero needs no bytes.
+        let mut bytes = if i.is_zero() {
+            Vec::new()
+        } else if signed {
+            i.to_signed_bytes_le()
+        } else {
+            i.to_bytes_le().1
+        };
+        if bytes.len() > length {
+            return Err(StarlarkIntError::ToBytesOverflow(self.to_owned(), length).into());
+        }
+        let fill = if i.is_negative() { 0xff } else { 0 };
+        bytes.resize(length, fill);
+        if byteorder == IntByteOrder::Big {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    /// Compare with a float exactly, without converting the integer to `f64`.
+    ///
+    /// Like float comparison, NaN is greater than any integer.
+    pub(crate) fn cmp_f64(self, f: f64) -> Ordering {
+        match self {
+            // `i32` is exactly representable as `f64`.
+            StarlarkIntRef::Small(i) => StarlarkFloat::compare_impl(i.to_i32() as f64, f),
+            StarlarkIntRef::Big(i) => {
+                if f.is_nan() || f == f64::INFINITY {
+                    return Ordering::Less;
+                }
+                if f == f64::NEG_INFINITY {
+                    return Ordering::Greater;
+                }
+                let trunc = f.trunc();
+                // Integral finite floats are converted to `BigInt` exactly.
+                let trunc_int = BigInt::from_f64(trunc).unwrap();
+                match i.get().cmp(&trunc_int) {
+                    // `i` is equal to the integer part of `f`,
+                    // so `f` is greater if it has a positive fractional part.
+                    Ordering::Equal => trunc.partial_cmp(&f).unwrap(),
+                    ord => ord,
+                }
+            }
+        }
+    }
+
     pub(crate) fn abs(self) -> StarlarkInt {
         match self {
             StarlarkIntRef::Small(i) => i.abs(),
             StarlarkIntRef::Big(i) => StarlarkInt::from(i.get().abs()),
         }
     }
+
+    /// Human-friendly rendering of the magnitude, for logs.
+    ///
+    /// Integers with at most [`APPROX_DISPLAY_EXACT_DIGITS`] digits are printed exactly,
+    /// larger ones are rounded to [`APPROX_DISPLAY_PRECISION`] significant digits,
+    /// like `1.27e38 (approx)`.
+    pub(crate) fn approx_display(self) -> String {
+        let i = self.to_big();
+        let digits = i.magnitude().to_string();
+        if digits.len() <= APPROX_DISPLAY_EXACT_DIGITS {
+            return i.to_string();
+        }
+        let mut exponent = digits.len() - 1;
+        // Round half up on the first dropped digit.
+        let mut mantissa: u64 = digits[..APPROX_DISPLAY_PRECISION].parse().unwrap();
+        if digits.as_bytes()[APPROX_DISPLAY_PRECISION] >= b'5' {
+            mantissa += 1;
+            if mantissa == 10u64.pow(APPROX_DISPLAY_PRECISION as u32) {
+                mantissa /= 10;
+                exponent += 1;
+            }
+        }
+        let mantissa = mantissa.to_string();
+        format!(
+            "{}{}.{}e{} (approx)",
+            if i.is_negative() { "-" } else { "" },
+            &mantissa[..1],
+            &mantissa[1..],
+            exponent
+        )
+    }
+
+    /// Is this integer a square of an integer.
+    pub(crate) fn is_perfect_square(self) -> bool {
+        let i = self.to_big();
+        if i.is_negative() {
+            return false;
+        }
+        let root = i.sqrt();
+        &root * &root == i
+    }
+
+    /// If this integer is `base ** exponent` for some `exponent >= 2`,
+    /// return the base and the largest such exponent.
+    ///
+    /// `0` and `1` are `0 ** 2` and `1 ** 2`, `-1` is `(-1) ** 3`.
+    /// Negative integers can only be odd powers.
+    pub(crate) fn perfect_power(self) -> Option<(StarlarkInt, u32)> {
+        let i = self.to_big();
+        let negative = i.is_negative();
+        let abs = i.abs();
+        if abs <= BigInt::from(1) {
+            let exponent = if negative { 3 } else { 2 };
+            return Some((StarlarkInt::from(i), exponent));
+        }
+        // If `abs == b ** e`, then `abs` is a `p`-th power for every prime `p` dividing `e`,
+        // so only try prime exponents, taking roots while they exist.
+        // Once `abs` is not a `p`-th power, its roots are not either,
+        // so each prime is tried once plus once per found root.
+        // The base is at least 2, so the exponent is at most the bit length.
+        let max_exponent = abs.bits() as usize;
+        let mut composite = vec![false; max_exponent + 1];
+        let mut base = abs;
+        let mut exponent = 1;
+        let mut p = 2;
+        while p <= max_exponent {
+            if !composite[p] {
+                if p as u64 > base.bits() {
+                    break;
+                }
+                // Negative integers can only be odd powers.
+                if !(negative && p == 2) {
+                    let root = base.nth_root(p as u32);
+                    if root.pow(p as u32) == base {
+                        base = root;
+                        exponent *= p as u32;
+                        continue;
+                    }
+                }
+                for m in (p * p..=max_exponent).step_by(p) {
+                    composite[m] = true;
+                }
+            }
+            p += 1;
+        }
+        if exponent == 1 {
+            return None;
+        }
+        let base = if negative { -base } else { base };
+        Some((StarlarkInt::from(base), exponent))
+    }
+
+    /// If this integer is `2 ** k`, return `k`.
+    pub(crate) fn exact_log2(self) -> Option<u64> {
+        match self {
+            StarlarkIntRef::Small(i) => {
+                let i = i.to_i32();
+                if i > 0 && i & (i - 1) == 0 {
+                    Some(i.trailing_zeros() as u64)
+                } else {
+                    None
+                }
+            }
+            StarlarkIntRef::Big(i) => {
+                let i = i.get();
+                if !i.is_positive() {
+                    return None;
+                }
+                // A power of two has a single bit set.
+                let k = i.trailing_zeros()?;
+                if i.bits() == k + 1 {
+                    Some(k)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// If this integer is `10 ** k`, return `k`.
+    pub(crate) fn exact_log10(self) -> Option<u64> {
+        match self {
+            StarlarkIntRef::Small(i) => {
+                let mut i = i.to_i32();
+                if i <= 0 {
+                    return None;
+                }
+                let mut k = 0;
+                while i % 10 == 0 {
+                    i /= 10;
+                    k += 1;
+                }
+                if i == 1 {
+                    Some(k)
+                } else {
+                    None
+                }
+            }
+            StarlarkIntRef::Big(i) => {
+                let i = i.get();
+                if !i.is_positive() {
+                    return None;
+                }
+                // `10 ** k` is `2 ** k * 5 ** k`, so `k` can only be the number
+                // of trailing zero bits.
+                let k = i.trailing_zeros()?;
+                if BigInt::from(10).pow(u32::try_from(k).ok()?) == *i {
+                    Some(k)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+// Combinatorics, like Python `math.comb` and `math.perm`.
+impl<'v> StarlarkIntRef<'v> {
+    /// Check arguments, and return `n` and `k` if `k <= n`.
+    fn combinatorics_args(n: Self, k: Self) -> anyhow::Result<Option<(BigInt, BigInt)>> {
+        if n < 0 {
+            return Err(StarlarkIntError::CombinatoricsNegative("n", n.to_owned()).into());
+        }
+        if k < 0 {
+            return Err(StarlarkIntError::CombinatoricsNegative("k", k.to_owned()).into());
+        }
+        if k > n {
+            return Ok(None);
+        }
+        Ok(Some((n.to_big(), k.to_big())))
+    }
+
+    /// Number of loop iterations, failing if it exceeds the limit.
+    fn combinatorics_steps(steps: &BigInt, k: Self) -> anyhow::Result<u64> {
+        match steps.to_u64() {
+            // Limit the work: each step multiplies a growing BigInt.
+            // 100_000 is the same limit as for left shift.
+            Some(steps) if steps <= 100_000 => Ok(steps),
+            _ => Err(StarlarkIntError::CombinatoricsTooLarge(k.to_owned()).into()),
+        }
+    }
+
+    /// Number of ways to choose `k` items from `n` items without repetition and without order.
+    ///
+    /// Zero if `k > n`, fails if `n` or `k` is negative,
+    /// or if both `k` and `n - k` are greater than `100_000`.
+    pub(crate) fn comb(n: Self, k: Self) -> anyhow::Result<StarlarkInt> {
+        let k_arg = k;
+        let Some((n, k)) = Self::combinatorics_args(n, k)? else {
+            return Ok(StarlarkInt::from(0));
+        };
+        // `comb(n, k) == comb(n, n - k)`, use the shorter loop.
+        let k = Self::combinatorics_steps(&k.clone().min(&n - &k), k_arg)?;
+        let base = &n - BigInt::from(k);
+        let mut r = BigInt::from(1);
+        for i in 1..=k {
+            // Product of `i` consecutive integers is divisible by `i!`, so division is exact.
+            r = r * (&base + BigInt::from(i)) / BigInt::from(i);
+        }
+        Ok(StarlarkInt::from(r))
+    }
+
+    /// Number of ways to choose `k` items from `n` items without repetition and with order.
+    ///
+    /// Zero if `k > n`, fails if `n` or `k` is negative, or if `k` is greater than `100_000`.
+    pub(crate) fn perm(n: Self, k: Self) -> anyhow::Result<StarlarkInt> {
+        let k_arg = k;
+        let Some((n, k)) = Self::combinatorics_args(n, k)? else {
+            return Ok(StarlarkInt::from(0));
+        };
+        let k = Self::combinatorics_steps(&k, k_arg)?;
+        let base = &n - BigInt::from(k);
+        let mut r = BigInt::from(1);
+        for i in 1..=k {
+            r *= &base + BigInt::from(i);
+        }
+        Ok(StarlarkInt::from(r))
+    }
+
+    /// Modular multiplicative inverse: `x` in `[0, m)` such that `a * x % m == 1`.
+    ///
+    /// Fails if `m` is not positive or `a` and `m` are not coprime.
+    pub(crate) fn mod_inverse(a: Self, m: Self) -> anyhow::Result<StarlarkInt> {
+        let modulus = m.to_big();
+        if !modulus.is_positive() {
+            return Err(StarlarkIntError::ModInverseNonPositiveModulus(m.to_owned()).into());
+        }
+        let e = a.to_big().mod_floor(&modulus).extended_gcd(&modulus);
+        if !e.gcd.is_one() {
+            return Err(
+                StarlarkIntError::ModInverseNotInvertible(a.to_owned(), m.to_owned()).into(),
+            );
+        }
+        Ok(StarlarkInt::from(e.x.mod_floor(&modulus)))
+    }
+}
+
+/// [SplitMix64](https://prng.di.unimi.it/splitmix64.c) generator.
+///
+/// The algorithm is part of the contract of `rand`: a seed must give the same
+/// numbers on every platform and in every version.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn mix(mut z: u64) -> u64 {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        Self::mix(self.0)
+    }
+}
+
+// Deterministic pseudo-random integers.
+impl<'v> StarlarkIntRef<'v> {
+    /// Pseudo-random integer in `[lo, hi)`, determined by `seed` only.
+    ///
+    /// The seed is written in two's complement as the fewest little-endian 64-bit words
+    /// which fit it, and the initial SplitMix64 state is
+    /// `state = mix(state ^ word)` over the words starting from zero.
+    /// Then, for `bits` the bit length of `hi - lo - 1`, `ceil(bits / 64)` outputs
+    /// are joined as little-endian words, and the low `bits` bits are taken
+    /// as an offset from `lo`. Offsets not less than `hi - lo` are rejected,
+    /// and the next outputs are used instead.
+    pub(crate) fn seeded_random(seed: Self, lo: Self, hi: Self) -> anyhow::Result<StarlarkInt> {
+        let lo_big = lo.to_big();
+        let Some(span) = (hi.to_big() - &lo_big)
+            .to_biguint()
+            .filter(|s| !s.is_zero())
+        else {
+            return Err(StarlarkIntError::RandomEmptyRange(lo.to_owned(), hi.to_owned()).into());
+        };
+
+        let seed = seed.to_big();
+        let mut bytes = seed.to_signed_bytes_le();
+        let sign_byte = if seed.is_negative() { 0xff } else { 0 };
+        bytes.resize(bytes.len().div_ceil(8) * 8, sign_byte);
+        let mut state = 0;
+        for word in bytes.chunks_exact(8) {
+            state = SplitMix64::mix(state ^ u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        let mut rng = SplitMix64(state);
+
+        let bits = (&span - 1u32).bits();
+        let mask = (BigUint::one() << bits) - 1u32;
+        loop {
+            let digits = (0..bits.div_ceil(64))
+                .flat_map(|_| {
+                    let x = rng.next();
+                    [x as u32, (x >> 32) as u32]
+                })
+                .collect();
+            let offset = BigUint::new(digits) & &mask;
+            if offset < span {
+                return Ok(StarlarkInt::from(lo_big + BigInt::from(offset)));
+            }
+        }
+    }
 }
 
 impl<'v> StarlarkTypeRepr for StarlarkIntRef<'v> {
@@ -470,6 +1182,20 @@ impl From<usize> for StarlarkInt {
     }
 }
 
+impl From<u128> for StarlarkInt {
+    #[inline]
+    fn from(value: u128) -> Self {
+        StarlarkInt::from_impl(value)
+    }
+}
+
+impl From<i128> for StarlarkInt {
+    #[inline]
+    fn from(value: i128) -> Self {
+        StarlarkInt::from_impl(value)
+    }
+}
+
 impl<'v> BitAnd for StarlarkIntRef<'v> {
     type Output = StarlarkInt;
 
@@ -573,6 +1299,10 @@ impl<'v> Mul<i32> for StarlarkIntRef<'v> {
                 }
                 StarlarkInt::from(a.to_bigint() * rhs)
             }
+            // Multiplying by anything but `-1`, `0` or `1` keeps a big int big.
+            StarlarkIntRef::Big(b) if rhs.unsigned_abs() >= 2 => {
+                StarlarkInt::from_big_unchecked(b.get() * rhs)
+            }
             StarlarkIntRef::Big(b) => StarlarkInt::from(b.get() * rhs),
         }
     }
@@ -594,7 +1324,7 @@ impl<'v> Mul for StarlarkIntRef<'v> {
             (StarlarkIntRef::Small(a), b) => a.to_i32() * b,
             (a, StarlarkIntRef::Small(b)) => a * b.to_i32(),
             (StarlarkIntRef::Big(a), StarlarkIntRef::Big(b)) => {
-                StarlarkInt::from(a.get() * b.get())
+                StarlarkInt::from_big_unchecked(a.get() * b.get())
             }
         }
     }
@@ -651,6 +1381,10 @@ impl<'v> PartialOrd<StarlarkIntRef<'v>> for i32 {
 mod tests {
     use std::str::FromStr;
 
+    use crate::assert;
+    use crate::values::types::inline_int::InlineInt;
+    use crate::values::types::int_or_big::IntByteOrder;
+    use crate::values::types::int_or_big::IntSignDisplay;
     use crate::values::types::int_or_big::StarlarkInt;
 
     fn int(s: &str) -> StarlarkInt {
@@ -778,4 +1512,533 @@ mod tests {
         assert_eq!("1", percent("-5", "3"));
         assert_eq!("-2", percent("-5", "-3"));
     }
+
+    /// Compute `&`, `|` and `^`, checking the result does not depend on representation.
+    fn bit_ops(a: &str, b: &str) -> [String; 3] {
+        let (a, b) = (int(a), int(b));
+        let (a, b) = (a.as_ref(), b.as_ref());
+        let (big_a, big_b) = (a.to_big(), b.to_big());
+        let ops = [a & b, a | b, a ^ b];
+        let big_ops = [&big_a & &big_b, &big_a | &big_b, &big_a ^ &big_b].map(StarlarkInt::from);
+        assert_eq!(big_ops, ops);
+        ops.map(|x| x.to_string())
+    }
+
+    #[test]
+    fn test_floor_div_percent_big_boundary() {
+        assert::all_true(
+            r#"
+(-7 << 100) // (2 << 100) == -4
+(-7 << 100) % (2 << 100) == 1 << 100
+(7 << 100) // (-2 << 100) == -4
+(7 << 100) % (-2 << 100) == -1 << 100
+((7 << 100) + 5) // (2 << 100) == 3
+((7 << 100) + 5) % (2 << 100) == 1267650600228229401496703205381
+(-(7 << 100) - 5) // (2 << 100) == -4
+(-(7 << 100) - 5) % (2 << 100) == 1267650600228229401496703205371
+((7 << 100) + 5) // (-2 << 100) == -4
+((7 << 100) + 5) % (-2 << 100) == -1267650600228229401496703205371
+(-(7 << 100) - 5) // (-2 << 100) == 3
+(-(7 << 100) - 5) % (-2 << 100) == -1267650600228229401496703205381
+(1 << 100) // -3 == -422550200076076467165567735126
+(1 << 100) % -3 == -2
+(-1 << 100) // 3 == -422550200076076467165567735126
+(-1 << 100) % 3 == 2
+-7 // (1 << 100) == -1
+-7 % (1 << 100) == 1267650600228229401496703205369
+7 // (-1 << 100) == -1
+7 % (-1 << 100) == -1267650600228229401496703205369
+-7 // -(1 << 100) == 0
+-7 % -(1 << 100) == -7
+-2147483648 // -1 == 2147483648
+-2147483648 % -1 == 0
+"#,
+        );
+    }
+
+    #[test]
+    fn test_fits() {
+        let fits = |s: &str| {
+            let i = int(s);
+            let i = i.as_ref();
+            (i.fits_i32(), i.fits_u32(), i.fits_i64(), i.fits_u64())
+        };
+        assert_eq!((true, true, true, true), fits("0"));
+        assert_eq!((true, false, true, false), fits("-1"));
+        assert_eq!((true, true, true, true), fits("2147483647"));
+        assert_eq!((false, true, true, true), fits("2147483648"));
+        assert_eq!((true, false, true, false), fits("-2147483648"));
+        assert_eq!((false, false, true, false), fits("-2147483649"));
+        assert_eq!((false, true, true, true), fits("4294967295"));
+        assert_eq!((false, false, true, true), fits("4294967296"));
+        assert_eq!((false, false, true, true), fits("9223372036854775807"));
+        assert_eq!((false, false, false, true), fits("9223372036854775808"));
+        assert_eq!((false, false, true, false), fits("-9223372036854775808"));
+        assert_eq!((false, false, false, false), fits("-9223372036854775809"));
+        assert_eq!((false, false, false, true), fits("18446744073709551615"));
+        assert_eq!((false, false, false, false), fits("18446744073709551616"));
+    }
+
+    #[test]
+    fn test_big_results_stay_big() {
+        assert::all_true(
+            r#"
+2147483648 * -1 == -2147483648
+-2147483649 * -1 == 2147483649
+2147483648 * 1 == 2147483648
+2147483648 * 0 == 0
+2147483648 * 2 == 4294967296
+2147483648 * -2 == -4294967296
+2147483648 * 2147483648 == 4611686018427387904
+-2147483649 * 2147483648 == -4611686018427387904 - 2147483648
+2147483648 << 1 == 4294967296
+-2147483649 << 1 == -4294967298
+"#,
+        );
+        assert::pass(
+            r#"
+def power(x, n):
+    r = 1
+    for _ in range(n):
+        r = r * x
+    return r
+
+def power_by_squaring(x, n):
+    r = 1
+    for i in range(9):
+        if (n >> i) & 1:
+            r = r * x
+        x = x * x
+    return r
+
+assert_eq(power(3, 300), power_by_squaring(3, 300))
+assert_eq(power(-7, 301), power_by_squaring(-7, 301))
+assert_eq(power(3, 300) // power(3, 299), 3)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_bit_ops_negative() {
+        // Expected values are computed with Python.
+        assert_eq!(["3", "-5", "-8"], bit_ops("-5", "3"));
+        assert_eq!(["5", "-3", "-8"], bit_ops("5", "-3"));
+        assert_eq!(["-7", "-1", "6"], bit_ops("-5", "-3"));
+        assert_eq!(
+            ["-2147483648", "-1", "2147483647"],
+            bit_ops("-2147483648", "-1")
+        );
+        assert_eq!(
+            ["2147483647", "-2147483649", "-4294967296"],
+            bit_ops("-2147483649", "2147483647")
+        );
+    }
+
+    #[test]
+    fn test_bit_ops_negative_big() {
+        // Expected values are computed with Python.
+        assert_eq!(
+            [
+                "1267650600228229401496703205376",
+                "-1",
+                "-1267650600228229401496703205377"
+            ],
+            bit_ops("-1", "1267650600228229401496703205376")
+        );
+        assert_eq!(
+            ["0", "-1", "-1"],
+            bit_ops(
+                "-1267650600228229401496703205376",
+                "1267650600228229401496703205375"
+            )
+        );
+        assert_eq!(
+            [
+                "0",
+                "-1267650600228229401496703205375",
+                "-1267650600228229401496703205375"
+            ],
+            bit_ops("-1267650600228229401496703205376", "1")
+        );
+        assert_eq!(
+            [
+                "-1267650600228229401496703205376",
+                "-633825300114114700748351602688",
+                "633825300114114700748351602688"
+            ],
+            bit_ops(
+                "-1267650600228229401496703205376",
+                "-633825300114114700748351602688"
+            )
+        );
+        assert_eq!(
+            [
+                "-1267650601408821022214114521145",
+                "-1",
+                "1267650601408821022214114521144"
+            ],
+            bit_ops(
+                "-1267650600228229401496703217721",
+                "-1180591620717411303425"
+            )
+        );
+    }
+
+    #[test]
+    fn test_shift_small_big_boundary() {
+        assert::all_true(
+            r#"
+1 << 30 == 1073741824
+1 << 31 == 2147483648
+1 << 32 == 4294967296
+3 << 30 == 3221225472
+-1 << 31 == -2147483648
+-1 << 32 == -4294967296
+-3 << 30 == -3221225472
+2147483647 << 1 == 4294967294
+(1 << 31) >> 31 == 1
+(1 << 32) >> 1 == 2147483648
+(-1 << 32) >> 1 == -2147483648
+"#,
+        );
+    }
+
+    #[test]
+    fn test_shift_large_amount() {
+        assert::all_true(
+            r#"
+1 << 1000 == 10715086071862673209484250490600018105614048117055336074437503883703510511249361224931983788156958581275946729175531468251871452856923140435984577574698574803934567774824230985421074605062371141877954182153046474983581941267398767559165543946077062914571196477686542167660429831652624386837205668069376
+(1 << 1000) >> 1000 == 1
+-1 << 1000 == -(1 << 1000)
+5 >> 31 == 0
+5 >> 32 == 0
+5 >> 1000 == 0
+-5 >> 31 == -1
+-5 >> 32 == -1
+-5 >> 1000 == -1
+(1 << 100) >> 1000 == 0
+(-1 << 100) >> 1000 == -1
+5 >> (1 << 100) == 0
+-5 >> (1 << 100) == -1
+0 << (1 << 100) == 0
+"#,
+        );
+        assert::fail("1 << (1 << 100)", "Integer overflow computing left shift");
+    }
+
+    #[test]
+    fn test_bit_ops_negative_starlark() {
+        assert::all_true(
+            r#"
+(-5) & 3 == 3
+(-1) | (1 << 100) == -1
+(-1) & (1 << 100) == 1 << 100
+(-(1 << 100)) & ((1 << 100) - 1) == 0
+(-(1 << 100)) ^ (-(1 << 99)) == 1 << 99
+((1 << 100) & -1) == 1 << 100
+"#,
+        );
+    }
+
+    fn radix(a: &str, radix: u32, sign: IntSignDisplay) -> String {
+        let mut s = String::new();
+        int(a).as_ref().write_radix(&mut s, radix, false, sign);
+        s
+    }
+
+    #[test]
+    fn test_reduce_fraction() {
+        fn reduce(num: &str, den: &str) -> String {
+            let (num, den) =
+                StarlarkInt::reduce_fraction(int(num).as_ref(), int(den).as_ref()).unwrap();
+            format!("{}/{}", num, den)
+        }
+
+        assert_eq!("1/2", reduce("2", "4"));
+        assert_eq!("-1/2", reduce("2", "-4"));
+        assert_eq!("-1/2", reduce("-2", "4"));
+        assert_eq!("1/2", reduce("-2", "-4"));
+        assert_eq!("0/1", reduce("0", "-7"));
+        assert_eq!("7/1", reduce("-7", "-1"));
+        // Big numerator and denominator with a big common factor.
+        assert_eq!(
+            "-3/7",
+            reduce(
+                "369999999999999999999999999999999999999963",
+                "-863333333333333333333333333333333333333247"
+            )
+        );
+        assert_eq!(
+            "100000000000000000000/3",
+            reduce("200000000000000000000", "6")
+        );
+        // Big values which reduce to small.
+        assert_eq!(
+            "1/1",
+            reduce("-100000000000000000000", "-100000000000000000000")
+        );
+        assert_eq!(
+            "Zero denominator in fraction: 5/0",
+            StarlarkInt::reduce_fraction(int("5").as_ref(), int("0").as_ref())
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_as_i64_fast() {
+        assert_eq!(Some(-17), int("-17").as_ref().as_i64_fast());
+        assert_eq!(
+            Some(i64::MAX),
+            StarlarkInt::from(i64::MAX).as_ref().as_i64_fast()
+        );
+        assert_eq!(
+            Some(i64::MIN),
+            StarlarkInt::from(i64::MIN).as_ref().as_i64_fast()
+        );
+        assert_eq!(None, int("9223372036854775808").as_ref().as_i64_fast());
+        assert_eq!(None, int("-9223372036854775809").as_ref().as_i64_fast());
+    }
+
+    #[test]
+    fn test_neg_promotes() {
+        assert_eq!(
+            "9223372036854775808",
+            (-StarlarkInt::from(i64::MIN)).to_string()
+        );
+        assert_eq!(StarlarkInt::from(i64::MIN), -(-StarlarkInt::from(i64::MIN)));
+        let min = StarlarkInt::Small(InlineInt::MIN);
+        assert_eq!(
+            StarlarkInt::from(-(InlineInt::MIN.to_i32() as i64)),
+            -min.clone()
+        );
+        assert_eq!(min, -(-min.clone()));
+    }
+
+    #[test]
+    fn test_approx_display() {
+        assert_eq!("0", int("0").as_ref().approx_display());
+        assert_eq!("-17", int("-17").as_ref().approx_display());
+        assert_eq!(
+            "999999999999999",
+            int("999999999999999").as_ref().approx_display()
+        );
+        assert_eq!(
+            "1.00e15 (approx)",
+            int("1000000000000000").as_ref().approx_display()
+        );
+        assert_eq!(
+            "1.70e38 (approx)",
+            int("170141183460469231731687303715884105727")
+                .as_ref()
+                .approx_display()
+        );
+        assert_eq!(
+            "-1.27e38 (approx)",
+            int("-127000000000000000000000000000000000000")
+                .as_ref()
+                .approx_display()
+        );
+        assert_eq!(
+            "1.00e20 (approx)",
+            int("99999999999999999999").as_ref().approx_display()
+        );
+        assert_eq!(
+            "1.23e20 (approx)",
+            int("123499999999999999999").as_ref().approx_display()
+        );
+    }
+
+    #[test]
+    fn test_from_ascii_bytes() {
+        assert_eq!(int("0"), StarlarkInt::from_ascii_bytes(b"0", 10).unwrap());
+        assert_eq!(
+            int("-255"),
+            StarlarkInt::from_ascii_bytes(b"-ff", 16).unwrap()
+        );
+        assert_eq!(int("5"), StarlarkInt::from_ascii_bytes(b"+101", 2).unwrap());
+        assert_eq!(
+            int("-9223372036854775807"),
+            StarlarkInt::from_ascii_bytes(b"-9223372036854775807", 10).unwrap()
+        );
+        assert_eq!(
+            int("123456789012345678901234567890123456789"),
+            StarlarkInt::from_ascii_bytes(b"123456789012345678901234567890123456789", 10).unwrap()
+        );
+        assert_eq!(
+            int("-1267650600228229401496703205376"),
+            StarlarkInt::from_ascii_bytes(b"-10000000000000000000000000", 16).unwrap()
+        );
+        assert_eq!(
+            int("-9223372036854775808"),
+            StarlarkInt::from_ascii_bytes(b"-9223372036854775808", 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_ascii_bytes_error() {
+        assert_eq!(
+            "Cannot parse bytes as an integer in base 10: invalid byte `0x61` at offset 3",
+            StarlarkInt::from_ascii_bytes(b"-12a", 10)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Cannot parse bytes as an integer in base 8: invalid byte `0xff` at offset 0",
+            StarlarkInt::from_ascii_bytes(b"\xff", 8)
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(StarlarkInt::from_ascii_bytes(b"1_000", 10).is_err());
+        assert!(StarlarkInt::from_ascii_bytes(b"-", 10).is_err());
+        assert!(StarlarkInt::from_ascii_bytes(b"", 10).is_err());
+        assert!(StarlarkInt::from_ascii_bytes(b"1", 37).is_err());
+        assert!(StarlarkInt::from_ascii_bytes(b"1", 1).is_err());
+    }
+
+    #[test]
+    fn test_is_perfect_square() {
+        let is_square = |s: &str| int(s).as_ref().is_perfect_square();
+        assert!(is_square("0"));
+        assert!(is_square("1"));
+        assert!(!is_square("2"));
+        assert!(!is_square("-1"));
+        assert!(!is_square("-4"));
+        // (2**100 + 1) ** 2
+        let square = "1606938044258990275541962092343697903722659452585786241712129";
+        assert!(is_square(square));
+        let below = int(square).as_ref() - int("1").as_ref();
+        let above = int(square).as_ref() + int("1").as_ref();
+        assert!(!below.as_ref().is_perfect_square());
+        assert!(!above.as_ref().is_perfect_square());
+        // (2**100) ** 2 and its neighbours, where the root rounds down.
+        let square = "1606938044258990275541962092341162602522202993782792835301376";
+        assert!(is_square(square));
+        let below = int(square).as_ref() - int("1").as_ref();
+        assert!(!below.as_ref().is_perfect_square());
+        assert!(!is_square(&format!("-{}", square)));
+    }
+
+    #[test]
+    fn test_write_radix() {
+        assert_eq!("5", radix("5", 10, IntSignDisplay::Negative));
+        assert_eq!("-5", radix("-5", 10, IntSignDisplay::Negative));
+        assert_eq!("0", radix("0", 10, IntSignDisplay::Negative));
+        assert_eq!(
+            "-80000000",
+            radix("-2147483648", 16, IntSignDisplay::Negative)
+        );
+        assert_eq!(
+            "-1000000000000000000000000000000000000000000000000000000000000000000",
+            radix("-73786976294838206464", 2, IntSignDisplay::Negative)
+        );
+    }
+
+    #[test]
+    fn test_write_radix_explicit_sign() {
+        assert_eq!("+5", radix("5", 10, IntSignDisplay::Always));
+        assert_eq!("-5", radix("-5", 10, IntSignDisplay::Always));
+        assert_eq!("+0", radix("0", 10, IntSignDisplay::Always));
+        assert_eq!("+ff", radix("255", 16, IntSignDisplay::Always));
+        assert_eq!("-377", radix("-255", 8, IntSignDisplay::Always));
+        assert_eq!(
+            "+12345678901234567890",
+            radix("12345678901234567890", 10, IntSignDisplay::Always)
+        );
+        assert_eq!(
+            "-ab54a98ceb1f0ad2",
+            radix("-12345678901234567890", 16, IntSignDisplay::Always)
+        );
+    }
+
+    fn to_bytes(i: &str, length: usize, byteorder: IntByteOrder, signed: bool) -> String {
+        match int(i).as_ref().to_bytes(length, byteorder, signed) {
+            Ok(bytes) => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        assert_eq!("", to_bytes("0", 0, IntByteOrder::Big, false));
+        assert_eq!("0000", to_bytes("0", 2, IntByteOrder::Big, true));
+        assert_eq!("0001", to_bytes("1", 2, IntByteOrder::Big, false));
+        assert_eq!("0100", to_bytes("1", 2, IntByteOrder::Little, false));
+        assert_eq!("ff", to_bytes("255", 1, IntByteOrder::Big, false));
+        assert_eq!("7f", to_bytes("127", 1, IntByteOrder::Big, true));
+        assert_eq!("80", to_bytes("-128", 1, IntByteOrder::Big, true));
+        assert_eq!("ffff", to_bytes("-1", 2, IntByteOrder::Big, true));
+        assert_eq!("feff", to_bytes("-2", 2, IntByteOrder::Little, true));
+        assert_eq!(
+            "010000000000000000",
+            to_bytes("18446744073709551616", 9, IntByteOrder::Big, false)
+        );
+    }
+
+    #[test]
+    fn test_to_bytes_overflow() {
+        assert_eq!(
+            "Integer `256` does not fit into 1 bytes",
+            to_bytes("256", 1, IntByteOrder::Big, false)
+        );
+        assert_eq!(
+            "Integer `128` does not fit into 1 bytes",
+            to_bytes("128", 1, IntByteOrder::Big, true)
+        );
+        assert_eq!(
+            "Integer `-129` does not fit into 1 bytes",
+            to_bytes("-129", 1, IntByteOrder::Big, true)
+        );
+        assert_eq!(
+            "Integer `1` does not fit into 0 bytes",
+            to_bytes("1", 0, IntByteOrder::Big, false)
+        );
+        assert_eq!(
+            "Integer `18446744073709551616` does not fit into 8 bytes",
+            to_bytes("18446744073709551616", 8, IntByteOrder::Little, false)
+        );
+        assert_eq!(
+            "Cannot convert negative integer `-1` to unsigned bytes",
+            to_bytes("-1", 8, IntByteOrder::Little, false)
+        );
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        for i in [
+            "0",
+            "1",
+            "-1",
+            "127",
+            "-128",
+            "255",
+            "-256",
+            "2147483647",
+            "-2147483648",
+            "18446744073709551615",
+            "-18446744073709551616",
+            "340282366920938463463374607431768211455",
+        ] {
+            for byteorder in [IntByteOrder::Big, IntByteOrder::Little] {
+                let bytes = int(i).as_ref().to_bytes(17, byteorder, true).unwrap();
+                assert_eq!(int(i), StarlarkInt::from_bytes(&bytes, byteorder, true));
+                if !i.starts_with('-') {
+                    let bytes = int(i).as_ref().to_bytes(16, byteorder, false).unwrap();
+                    assert_eq!(int(i), StarlarkInt::from_bytes(&bytes, byteorder, false));
+                }
+            }
+        }
+        assert_eq!(
+            int("0"),
+            StarlarkInt::from_bytes(&[], IntByteOrder::Big, true)
+        );
+        assert_eq!(
+            int("-1"),
+            StarlarkInt::from_bytes(&[0xff], IntByteOrder::Big, true)
+        );
+        assert_eq!(
+            int("255"),
+            StarlarkInt::from_bytes(&[0xff], IntByteOrder::Big, false)
+        );
+    }
 }
@@ -225,7 +225,7 @@ impl<'v> StarlarkValue<'v> for Range {
     unsafe fn iter_stop(&self) {}
 
     fn is_in(&self, other: Value) -> crate::Result<bool> {
-        let other = match other.unpack_num().and_then(|n| n.as_int()) {
+        let other = match other.unpack_num_ref().and_then(|n| n.as_int()) {
             Some(other) => other,
             None => {
                 // Consider `"a" in range(3)`
@@ -0,0 +1,43 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The set type, a mutable collection of unique hashable values, which iterates in insertion order.
+//!
+//! The `set()` function creates a set, optionally from an iterable.
+//! Sets support `in`, `len()`, iteration, and the `add` and `remove` methods.
+//! The operators `|`, `&`, `-` and `^` compute union, intersection, difference and
+//! symmetric difference; elements of the result appear in the order of the left operand,
+//! followed by the order of the right operand.
+//!
+//! ```
+//! # starlark::assert::is_true(r#"
+//! s = set([3, 1])
+//! s.add(2)
+//! list(s | set([4, 1])) == [3, 1, 2, 4]
+//! # "#);
+//! ```
+//!
+//! Sets are not part of the Starlark specification and are only available
+//! with [`LibraryExtension::SetType`](crate::environment::LibraryExtension::SetType).
+//! Converted to JSON, a set is an array.
+
+mod refs;
+pub(crate) mod value;
+
+pub use crate::values::set::refs::SetMut;
+pub use crate::values::set::refs::SetRef;
+pub use crate::values::set::value::Set;
@@ -0,0 +1,511 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::mem;
+use std::ops::Deref;
+
+use allocative::Allocative;
+use display_container::fmt_container;
+use serde::Serialize;
+use starlark_derive::starlark_value;
+use starlark_derive::StarlarkDocs;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::coerce;
+use crate::coerce::Coerce;
+use crate::collections::Hashed;
+use crate::collections::SmallSet;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::TyStarlarkValue;
+use crate::typing::TypingBinOp;
+use crate::values::dict::refcell::unleak_borrow;
+use crate::values::error::ValueError;
+use crate::values::set::SetRef;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::AllocValue;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(
+    Clone,
+    Default,
+    Trace,
+    Debug,
+    ProvidesStaticType,
+    StarlarkDocs,
+    Allocative
+)]
+#[starlark_docs(builtin = "extension")]
+pub(crate) struct SetGen<T>(pub(crate) T);
+
+impl<'v, T: SetLike<'v>> Display for SetGen<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_container(f, "set([", "])", self.0.content().iter())
+    }
+}
+
+impl<'v> Display for Set<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_container(f, "set([", "])", self.iter())
+    }
+}
+
+/// Define the set type.
+#[derive(Clone, Default, Trace, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub struct Set<'v> {
+    /// The data stored by the set. The elements must all be hashable values.
+    content: SmallSet<Value<'v>>,
+}
+
+impl<'v> StarlarkTypeRepr for Set<'v> {
+    type Canonical = Self;
+
+    fn starlark_type_repr() -> Ty {
+        Ty::starlark_value::<FrozenSet>()
+    }
+}
+
+#[derive(Clone, Default, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub(crate) struct FrozenSetData {
+    /// The data stored by the set. The elements must all be hashable values.
+    pub(crate) content: SmallSet<FrozenValue>,
+}
+
+/// Alias is used in `StarlarkDocs` derive.
+pub(crate) type FrozenSet = SetGen<FrozenSetData>;
+
+pub(crate) type MutableSet<'v> = SetGen<RefCell<Set<'v>>>;
+
+unsafe impl<'v> Coerce<Set<'v>> for FrozenSetData {}
+
+impl<'v> AllocValue<'v> for Set<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex(SetGen(RefCell::new(self)))
+    }
+}
+
+impl<'v> Set<'v> {
+    /// The result of calling `type()` on sets.
+    pub const TYPE: &'static str = "set";
+
+    /// Create a set from the given elements.
+    pub fn new(content: SmallSet<Value<'v>>) -> Self {
+        Self { content }
+    }
+
+    /// Number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Is the set empty?
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Iterate through the elements in insertion order.
+    pub fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = Value<'v>> + 'a {
+        self.content.iter().copied()
+    }
+
+    /// Iterate through the elements in insertion order, retaining their hashes.
+    pub fn iter_hashed<'a>(&'a self) -> impl Iterator<Item = Hashed<Value<'v>>> + 'a
+    where
+        'v: 'a,
+    {
+        self.content.iter_hashed().map(|x| x.copied())
+    }
+
+    /// Does the set contain the given element? Will be [`Err`] if the element is not hashable.
+    pub fn contains(&self, value: Value<'v>) -> crate::Result<bool> {
+        Ok(self.contains_hashed(value.get_hashed()?))
+    }
+
+    /// Like [`Set::contains`], but where you already have the hash.
+    pub fn contains_hashed(&self, value: Hashed<Value<'v>>) -> bool {
+        self.content.contains_hashed(value.as_ref())
+    }
+
+    /// Add an element to the set, keeping its position if it is already present.
+    /// Returns `true` if the element was not present.
+    pub fn insert_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.insert_hashed(value)
+    }
+
+    /// Remove an element from the set. Returns `true` if the element was present.
+    pub fn remove_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.remove_hashed(value.as_ref())
+    }
+
+    /// Remove all elements from the set.
+    pub fn clear(&mut self) {
+        self.content.clear();
+    }
+
+    /// Elements of either set: elements of `self`, then new elements of `other`.
+    pub fn union(&self, other: &Set<'v>) -> Set<'v> {
+        let mut content = self.content.clone();
+        for x in other.iter_hashed() {
+            content.insert_hashed(x);
+        }
+        Set { content }
+    }
+
+    /// Elements of `self` which are also in `other`.
+    pub fn intersection(&self, other: &Set<'v>) -> Set<'v> {
+        self.filter(|x| other.contains_hashed(x))
+    }
+
+    /// Elements of `self` which are not in `other`.
+    pub fn difference(&self, other: &Set<'v>) -> Set<'v> {
+        self.filter(|x| !other.contains_hashed(x))
+    }
+
+    /// Elements in exactly one of the sets:
+    /// elements of `self` not in `other`, then elements of `other` not in `self`.
+    pub fn symmetric_difference(&self, other: &Set<'v>) -> Set<'v> {
+        let mut res = self.difference(other);
+        for x in other.iter_hashed() {
+            if !self.contains_hashed(x) {
+                res.content.insert_hashed_unique_unchecked(x);
+            }
+        }
+        res
+    }
+
+    fn filter(&self, mut f: impl FnMut(Hashed<Value<'v>>) -> bool) -> Set<'v> {
+        let mut content = SmallSet::new();
+        for x in self.iter_hashed() {
+            if f(x) {
+                content.insert_hashed_unique_unchecked(x);
+            }
+        }
+        Set { content }
+    }
+}
+
+impl<'v> Freeze for SetGen<RefCell<Set<'v>>> {
+    type Frozen = SetGen<FrozenSetData>;
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let content = self.0.into_inner().content.freeze(freezer)?;
+        Ok(SetGen(FrozenSetData { content }))
+    }
+}
+
+pub(crate) trait SetLike<'v>: Debug + Allocative {
+    type ContentRef<'a>: Deref<Target = Set<'v>>
+    where
+        Self: 'a,
+        'v: 'a;
+    fn content<'a>(&'a self) -> Self::ContentRef<'a>;
+    // These functions are unsafe for the same reason
+    // `StarlarkValue` iterator functions are unsafe.
+    unsafe fn iter_start(&self);
+    unsafe fn content_unchecked(&self) -> &Set<'v>;
+    unsafe fn iter_stop(&self);
+}
+
+impl<'v> SetLike<'v> for RefCell<Set<'v>> {
+    type ContentRef<'a>
+        = Ref<'a, Set<'v>>
+    where
+        Self: 'a,
+        'v: 'a;
+
+    fn content<'a>(&'a self) -> Ref<'a, Set<'v>> {
+        self.borrow()
+    }
+
+    #[inline]
+    unsafe fn iter_start(&self) {
+        mem::forget(self.borrow());
+    }
+
+    #[inline]
+    unsafe fn iter_stop(&self) {
+        unleak_borrow(self);
+    }
+
+    #[inline]
+    unsafe fn content_unchecked(&self) -> &Set<'v> {
+        // SAFETY: this function contract is, caller must ensure that the value is borrowed.
+        self.try_borrow_unguarded().ok().unwrap_unchecked()
+    }
+}
+
+impl<'v> SetLike<'v> for FrozenSetData {
+    type ContentRef<'a>
+        = &'a Set<'v>
+    where
+        Self: 'a,
+        'v: 'a;
+
+    fn content<'a>(&'a self) -> &'a Set<'v> {
+        coerce(self)
+    }
+
+    unsafe fn iter_start(&self) {}
+
+    unsafe fn iter_stop(&self) {}
+
+    unsafe fn content_unchecked(&self) -> &Set<'v> {
+        coerce(self)
+    }
+}
+
+impl<'v> MutableSet<'v> {
+    pub(crate) fn borrow_mut(&self) -> anyhow::Result<RefMut<'_, Set<'v>>> {
+        self.0
+            .try_borrow_mut()
+            .map_err(|_| ValueError::MutationDuringIteration.into())
+    }
+}
+
+pub(crate) fn set_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::set::set_methods)
+}
+
+impl<'v, T: SetLike<'v>> SetGen<T> {
+    fn bin_op(
+        &self,
+        op: &str,
+        rhs: Value<'v>,
+        heap: &'v Heap,
+        f: impl FnOnce(&Set<'v>, &Set<'v>) -> Set<'v>,
+    ) -> crate::Result<Value<'v>>
+    where
+        Self: StarlarkValue<'v>,
+    {
+        match SetRef::from_value(rhs) {
+            Some(rhs) => Ok(heap.alloc(f(&self.0.content(), &rhs))),
+            None => ValueError::unsupported_with(self, op, rhs),
+        }
+    }
+}
+
+#[starlark_value(type = Set::TYPE)]
+impl<'v, T: SetLike<'v> + 'v> StarlarkValue<'v> for SetGen<T>
+where
+    Self: ProvidesStaticType<'v>,
+{
+    type Canonical = FrozenSet;
+
+    fn get_methods() -> Option<&'static Methods> {
+        set_methods()
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        r.push_str("set([");
+        for (i, x) in self.0.content().iter().enumerate() {
+            if i != 0 {
+                r.push_str(", ");
+            }
+            x.collect_repr(r);
+        }
+        r.push_str("])");
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.content().is_empty()
+    }
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        match SetRef::from_value(other) {
+            None => Ok(false),
+            Some(other) => {
+                let this = self.0.content();
+                // Sets are equal regardless of insertion order.
+                Ok(this.len() == other.len()
+                    && this.iter_hashed().all(|x| other.contains_hashed(x)))
+            }
+        }
+    }
+
+    fn length(&self) -> crate::Result<i32> {
+        Ok(self.0.content().len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> crate::Result<bool> {
+        self.0.content().contains(other)
+    }
+
+    unsafe fn iterate(&self, me: Value<'v>, _heap: &'v Heap) -> crate::Result<Value<'v>> {
+        self.0.iter_start();
+        Ok(me)
+    }
+
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        debug_assert!(index <= self.0.content().len());
+        let rem = self.0.content().len() - index;
+        (rem, Some(rem))
+    }
+
+    unsafe fn iter_next(&self, index: usize, _heap: &'v Heap) -> Option<Value<'v>> {
+        self.0.content_unchecked().content.get_index(index).copied()
+    }
+
+    unsafe fn iter_stop(&self) {
+        self.0.iter_stop();
+    }
+
+    fn bit_or(&self, rhs: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        self.bin_op("|", rhs, heap, Set::union)
+    }
+
+    fn bit_and(&self, rhs: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        self.bin_op("&", rhs, heap, Set::intersection)
+    }
+
+    fn sub(&self, rhs: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        self.bin_op("-", rhs, heap, Set::difference)
+    }
+
+    fn bit_xor(&self, rhs: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        self.bin_op("^", rhs, heap, Set::symmetric_difference)
+    }
+
+    fn bin_op_ty(op: TypingBinOp, rhs: &TyBasic) -> Option<Ty> {
+        match op {
+            TypingBinOp::In => Some(Ty::bool()),
+            TypingBinOp::BitOr | TypingBinOp::BitAnd | TypingBinOp::Sub | TypingBinOp::BitXor => {
+                match rhs {
+                    TyBasic::Any => Some(Ty::starlark_value::<FrozenSet>()),
+                    TyBasic::StarlarkValue(rhs) if *rhs == TyStarlarkValue::new::<FrozenSet>() => {
+                        Some(Ty::starlark_value::<FrozenSet>())
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'v, T: SetLike<'v>> Serialize for SetGen<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.content().iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::Assert;
+    use crate::environment::Globals;
+
+    #[test]
+    fn test_set() {
+        assert::all_true(
+            r#"
+len(set([1, 2, 1])) == 2
+2 in set([1, 2])
+3 not in set([1, 2])
+not set()
+bool(set([0]))
+set([1, 2]) == set([2, 1])
+set([1, 2]) != set([1, 2, 3])
+set([1]) != [1]
+type(set()) == "set"
+isinstance(set(), set)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_operators_preserve_order() {
+        assert::all_true(
+            r#"
+list(set([3, 1, 2]) | set([4, 1, 5])) == [3, 1, 2, 4, 5]
+list(set([3, 1, 2]) & set([2, 3, 4])) == [3, 2]
+list(set([3, 1, 2]) - set([1])) == [3, 2]
+list(set([3, 1, 2]) ^ set([4, 1])) == [3, 2, 4]
+"#,
+        );
+        assert::fail("set([1]) | [2]", "not supported");
+    }
+
+    #[test]
+    fn test_add_remove() {
+        assert::is_true(
+            r#"
+x = set()
+x.add(2)
+x.add(1)
+x.add(2)
+x.remove(2)
+x.add(2)
+list(x) == [1, 2]
+"#,
+        );
+        assert::fail("set([1]).remove(2)", "Key `2` was not found");
+        assert::fail("set([[1]])", "not hashable");
+        assert::fail("set().add({})", "not hashable");
+    }
+
+    #[test]
+    fn test_mutation() {
+        assert::fail(
+            r#"
+x = set([1, 2])
+for v in x:
+    x.add(v + 10)
+"#,
+            "mutate an iterable for an iterator",
+        );
+        let mut a = Assert::new();
+        a.module("m", "x = set([1])");
+        a.fail("load('m', 'x')\nx.add(2)", "Immutable");
+        a.is_true("load('m', 'x')\nx | set([2]) == set([1, 2])");
+    }
+
+    #[test]
+    fn test_repr_and_json() {
+        assert::eq("repr(set())", "'set([])'");
+        assert::eq("repr(set([1, 'a']))", "'set([1, \"a\"])'");
+        assert::eq("str(set([1, 2]))", "'set([1, 2])'");
+        assert::eq("json.encode(set([2, 1]))", "'[2,1]'");
+    }
+
+    #[test]
+    fn test_not_in_standard_globals() {
+        let mut a = Assert::new();
+        a.globals(Globals::standard());
+        a.fail("set()", "Variable `set` not found");
+    }
+}
@@ -18,17 +18,15 @@
 //! String interpolation-related code.
 //! Based on <https://docs.python.org/3/library/stdtypes.html#printf-style-string-formatting>
 
-use std::fmt::Write;
-
 use dupe::Dupe;
-use num_traits::Signed;
 use thiserror::Error;
 
 use crate::values::float;
 use crate::values::float::StarlarkFloat;
 use crate::values::num::value::NumRef;
 use crate::values::string::dot_format::format_one;
-use crate::values::types::int_or_big::StarlarkIntRef;
+use crate::values::types::int_or_big::IntSignDisplay;
+use crate::values::types::int_or_big::StarlarkInt;
 use crate::values::types::tuple::value::Tuple;
 use crate::values::Heap;
 use crate::values::StringValue;
@@ -37,12 +35,6 @@ use crate::values::Value;
 use crate::values::ValueError;
 use crate::values::ValueLike;
 
-// `i32::abs(i32::MIN)` panics as `i32::MIN` has no corresponding
-// positive value that fits inside `i32`. For this edge case,
-// let's just hardcode the results.
-const I32_MIN_OCTAL: &str = "-20000000000";
-const I32_MIN_HEX: &str = "-80000000";
-
 /// Operator `%` format or evaluation errors
 #[derive(Clone, Dupe, Debug, Error)]
 enum StringInterpolationError {
@@ -54,6 +46,8 @@ enum StringInterpolationError {
     IncompleteFormat,
     #[error("Unsupported format character: {0:?}")]
     UnsupportedFormatCharacter(char),
+    #[error("Unsupported format character with `+` flag: {0:?}")]
+    UnsupportedFormatCharacterWithSign(char),
     #[error("Expecting format character (internal error)")]
     ExpectingFormatCharacter,
 }
@@ -63,14 +57,14 @@ enum PercentSFormat {
     Str,
     /// `%r`.
     Repr,
-    /// `%d`.
-    Dec,
-    /// `%o`.
-    Oct,
-    /// `%x`.
-    Hex,
-    /// `%X`.
-    HexUpper,
+    /// `%d` or `%+d`.
+    Dec(IntSignDisplay),
+    /// `%o` or `%+o`.
+    Oct(IntSignDisplay),
+    /// `%x` or `%+x`.
+    Hex(IntSignDisplay),
+    /// `%X` or `%+X`.
+    HexUpper(IntSignDisplay),
     /// `%e`.
     Exp,
     /// `%E`.
@@ -103,6 +97,34 @@ impl<'a> Iterator for PercentFormatParser<'a> {
             let (literal, rem) = self.rem.split_at(index_of_percent);
             match rem.as_bytes().get(1) {
                 None => return Some(Err(StringInterpolationError::IncompleteFormat.into())),
+                Some(b'+') => {
+                    let format = match rem.as_bytes().get(2) {
+                        None => {
+                            return Some(Err(StringInterpolationError::IncompleteFormat.into()));
+                        }
+                        Some(b'd') => PercentSFormat::Dec(IntSignDisplay::Always),
+                        Some(b'o') => PercentSFormat::Oct(IntSignDisplay::Always),
+                        Some(b'x') => PercentSFormat::Hex(IntSignDisplay::Always),
+                        Some(b'X') => PercentSFormat::HexUpper(IntSignDisplay::Always),
+                        Some(_) => {
+                            let Some(c) = rem.chars().nth(2) else {
+                                return Some(Err(
+                                    StringInterpolationError::ExpectingFormatCharacter.into(),
+                                ));
+                            };
+                            return Some(Err(
+                                StringInterpolationError::UnsupportedFormatCharacterWithSign(c)
+                                    .into(),
+                            ));
+                        }
+                    };
+                    // Format character is ASCII, so we can safely skip 3 bytes.
+                    self.rem = &rem[3..];
+                    Some(Ok(Item {
+                        literal,
+                        format: Some(format),
+                    }))
+                }
                 Some(f) => {
                     let res = match f {
                         b'%' => {
@@ -123,19 +145,19 @@ impl<'a> Iterator for PercentFormatParser<'a> {
                         },
                         b'd' => Item {
                             literal,
-                            format: Some(PercentSFormat::Dec),
+                            format: Some(PercentSFormat::Dec(IntSignDisplay::Negative)),
                         },
                         b'o' => Item {
                             literal,
-                            format: Some(PercentSFormat::Oct),
+                            format: Some(PercentSFormat::Oct(IntSignDisplay::Negative)),
                         },
                         b'x' => Item {
                             literal,
-                            format: Some(PercentSFormat::Hex),
+                            format: Some(PercentSFormat::Hex(IntSignDisplay::Negative)),
                         },
                         b'X' => Item {
                             literal,
-                            format: Some(PercentSFormat::HexUpper),
+                            format: Some(PercentSFormat::HexUpper(IntSignDisplay::Negative)),
                         },
                         b'e' => Item {
                             literal,
@@ -225,100 +247,43 @@ pub(crate) fn percent(format: &str, value: Value) -> crate::Result<String> {
                 }
             }
             Some(PercentSFormat::Repr) => next_value()?.collect_repr(&mut res),
-            Some(PercentSFormat::Dec) => {
+            Some(PercentSFormat::Dec(sign)) => {
                 let value = next_value()?;
-                match value.unpack_num() {
-                    Some(NumRef::Int(StarlarkIntRef::Small(v))) => {
-                        write!(res, "{}", v.to_i32()).unwrap()
-                    }
-                    Some(NumRef::Int(StarlarkIntRef::Big(v))) => {
-                        write!(res, "{}", v.get()).unwrap()
-                    }
+                match value.unpack_num_ref() {
+                    Some(NumRef::Int(v)) => v.write_radix(&mut res, 10, false, sign),
                     Some(NumRef::Float(v)) => {
                         match NumRef::Float(StarlarkFloat(v.0.trunc())).as_int() {
-                            Some(v) => write!(res, "{}", v).unwrap(),
+                            Some(v) => StarlarkInt::from(v)
+                                .as_ref()
+                                .write_radix(&mut res, 10, false, sign),
                             None => ValueError::unsupported_type(value, "format(%d)")?,
                         }
                     }
                     None => ValueError::unsupported_type(value, "format(%d)")?,
                 }
             }
-            Some(PercentSFormat::Oct) => {
+            Some(PercentSFormat::Oct(sign)) => {
                 let value = next_value()?;
-                match value.unpack_num() {
-                    Some(NumRef::Int(StarlarkIntRef::Small(v))) => {
-                        let v = v.to_i32();
-                        match v.checked_abs() {
-                            Some(vp) => {
-                                write!(res, "{}{:o}", if v < 0 { "-" } else { "" }, vp).unwrap()
-                            }
-                            None => write!(res, "{}", I32_MIN_OCTAL).unwrap(),
-                        }
-                    }
-                    Some(NumRef::Int(StarlarkIntRef::Big(v))) => {
-                        let v = v.get();
-                        write!(
-                            res,
-                            "{}{:o}",
-                            if v.is_negative() { "-" } else { "" },
-                            v.abs()
-                        )
-                        .unwrap()
-                    }
+                match value.unpack_num_ref() {
+                    Some(NumRef::Int(v)) => v.write_radix(&mut res, 8, false, sign),
                     Some(NumRef::Float(_)) | None => {
                         ValueError::unsupported_type(value, "format(%o)")?
                     }
                 }
             }
-            Some(PercentSFormat::Hex) => {
+            Some(PercentSFormat::Hex(sign)) => {
                 let value = next_value()?;
-                match value.unpack_num() {
-                    Some(NumRef::Int(StarlarkIntRef::Small(v))) => {
-                        let v = v.to_i32();
-                        match v.checked_abs() {
-                            Some(vp) => {
-                                write!(res, "{}{:x}", if v < 0 { "-" } else { "" }, vp).unwrap()
-                            }
-                            None => write!(res, "{}", I32_MIN_HEX).unwrap(),
-                        }
-                    }
-                    Some(NumRef::Int(StarlarkIntRef::Big(v))) => {
-                        let v = v.get();
-                        write!(
-                            res,
-                            "{}{:x}",
-                            if v.is_negative() { "-" } else { "" },
-                            v.abs()
-                        )
-                        .unwrap()
-                    }
+                match value.unpack_num_ref() {
+                    Some(NumRef::Int(v)) => v.write_radix(&mut res, 16, false, sign),
                     Some(NumRef::Float(_)) | None => {
                         ValueError::unsupported_type(value, "format(%x)")?
                     }
                 }
             }
-            Some(PercentSFormat::HexUpper) => {
+            Some(PercentSFormat::HexUpper(sign)) => {
                 let value = next_value()?;
-                match value.unpack_num() {
-                    Some(NumRef::Int(StarlarkIntRef::Small(v))) => {
-                        let v = v.to_i32();
-                        match v.checked_abs() {
-                            Some(vp) => {
-                                write!(res, "{}{:X}", if v < 0 { "-" } else { "" }, vp).unwrap()
-                            }
-                            None => write!(res, "{}", I32_MIN_HEX).unwrap(),
-                        }
-                    }
-                    Some(NumRef::Int(StarlarkIntRef::Big(v))) => {
-                        let v = v.get();
-                        write!(
-                            res,
-                            "{}{:X}",
-                            if v.is_negative() { "-" } else { "" },
-                            v.abs()
-                        )
-                        .unwrap()
-                    }
+                match value.unpack_num_ref() {
+                    Some(NumRef::Int(v)) => v.write_radix(&mut res, 16, true, sign),
                     Some(NumRef::Float(_)) | None => {
                         ValueError::unsupported_type(value, "format(%X)")?
                     }
@@ -544,4 +509,38 @@ mod tests {
         assert::eq("'%x' % (-2147483647,)", "'-7fffffff'");
         assert::eq("'%x' % (-2147483648,)", "'-80000000'");
     }
+
+    #[test]
+    fn test_explicit_sign() {
+        assert::eq("'%+d' % 5", "'+5'");
+        assert::eq("'%+d' % -5", "'-5'");
+        assert::eq("'%+d' % 0", "'+0'");
+        assert::eq("'%+d' % 5.5", "'+5'");
+        assert::eq("'%+d' % (1 << 100)", "'+1267650600228229401496703205376'");
+        assert::eq("'%+o' % 8", "'+10'");
+        assert::eq("'%+x' % -255", "'-ff'");
+        assert::eq("'%+X' % 255", "'+FF'");
+        assert::eq("'%+x' % 0", "'+0'");
+        assert::eq("'%+d%%' % 5", "'+5%'");
+
+        assert::fail(
+            "'%+s' % 5",
+            "Unsupported format character with `+` flag: 's'",
+        );
+        assert::fail("'%+' % 5", "Incomplete format");
+    }
+
+    #[test]
+    fn test_explicit_sign_round_trip() {
+        assert::all_true(
+            r#"
+int('%+d' % 5) == 5
+int('%+d' % -5) == -5
+int('%+d' % 0) == 0
+int('%+x' % 255, 16) == 255
+int('%+o' % -8, 8) == -8
+int('%+d' % (1 << 100)) == 1 << 100
+"#,
+        );
+    }
 }
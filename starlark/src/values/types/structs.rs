@@ -33,12 +33,70 @@
 //! ip_address.port == 80
 //! # "#);
 //! ```
+//!
+//! Two structs can be merged with `+`. The result has the fields of both structs,
+//! with values of the right operand taking precedence.
+//!
+//! ```
+//! # starlark::assert::is_true(r#"
+//! struct(host='localhost', port=80) + struct(port=8080) == struct(host='localhost', port=8080)
+//! # "#);
+//! ```
+//!
+//! Like iterating a dictionary yields its keys, iterating a struct yields its field names,
+//! in the order the fields were defined.
+//!
+//! ```
+//! # starlark::assert::is_true(r#"
+//! [k for k in struct(host='localhost', port=80)] == ['host', 'port']
+//! # "#);
+//! ```
 
+pub(crate) mod access;
 pub(crate) mod alloc;
+pub(crate) mod builder;
+pub(crate) mod chain;
+pub(crate) mod columnar;
+pub(crate) mod key_value;
+pub(crate) mod lazy;
+pub(crate) mod of;
+pub(crate) mod patch;
+pub(crate) mod pool;
 pub(crate) mod refs;
+pub(crate) mod schema;
+pub(crate) mod sort;
+pub(crate) mod tagged;
+pub(crate) mod tagged_value;
 pub(crate) mod unordered_hasher;
 pub(crate) mod value;
+pub(crate) mod walk;
+
+pub use starlark_derive::StructAccess;
+pub use starlark_derive::UnpackStruct;
 
+pub use crate::values::types::structs::access::StructAccessField;
 pub use crate::values::types::structs::alloc::AllocStruct;
+pub use crate::values::types::structs::builder::StructBuilder;
+pub use crate::values::types::structs::chain::StructChain;
+pub use crate::values::types::structs::columnar::columnar;
+pub use crate::values::types::structs::key_value::KeyValue;
+pub use crate::values::types::structs::key_value::KeyValueKind;
+pub use crate::values::types::structs::lazy::LazyStruct;
+pub use crate::values::types::structs::lazy::LazyStructThunk;
+pub use crate::values::types::structs::of::StructOf;
+pub use crate::values::types::structs::patch::StructPatch;
+pub use crate::values::types::structs::patch::StructPatchOp;
+pub use crate::values::types::structs::pool::StructPool;
 pub use crate::values::types::structs::refs::FrozenStructRef;
+pub use crate::values::types::structs::refs::StructAttrKind;
+pub use crate::values::types::structs::refs::StructJsonLinesFormat;
 pub use crate::values::types::structs::refs::StructRef;
+pub use crate::values::types::structs::schema::Schema;
+pub use crate::values::types::structs::schema::SchemaError;
+pub use crate::values::types::structs::schema::SchemaType;
+pub use crate::values::types::structs::schema::UnknownFields;
+pub use crate::values::types::structs::sort::sort_by_struct_field;
+pub use crate::values::types::structs::tagged::TaggedField;
+pub use crate::values::types::structs::tagged::TaggedFieldName;
+pub use crate::values::types::structs::tagged::ValueField;
+pub use crate::values::types::structs::tagged_value::TaggedValue;
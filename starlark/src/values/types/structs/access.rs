@@ -0,0 +1,133 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Runtime support for `#[derive(StructAccess)]` and `#[derive(UnpackStruct)]`.
+
+use std::cell::OnceCell;
+use std::fmt;
+
+use crate::values::structs::StructRef;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum StructAccessError {
+    #[error("Expected `struct`, got value of type `{0}`")]
+    NotStruct(&'static str),
+    #[error("Struct has no field `{0}`")]
+    MissingField(String),
+}
+
+/// Field of an accessor generated by
+/// [`#[derive(StructAccess)]`](crate::values::structs::StructAccess),
+/// unpacked on first access and then cached.
+///
+/// The accessor must have one field marked `#[starlark(value)]` holding the struct,
+/// and `StructAccessField<T>` fields, one per struct field to access. The struct field
+/// name defaults to the Rust field name, and can be set with `#[starlark(field = "...")]`.
+/// The derive generates a constructor `new(value)`, failing if the value is not a struct,
+/// and a getter per field returning `anyhow::Result<&T>`.
+///
+/// Unlike unpacking the whole struct eagerly, fields which are never accessed
+/// are never unpacked, and missing or mistyped fields only fail when accessed.
+///
+/// ```
+/// use starlark::values::structs::StructAccess;
+/// use starlark::values::structs::StructAccessField;
+/// use starlark::values::Value;
+///
+/// #[derive(StructAccess)]
+/// struct ServerConfig<'v> {
+///     #[starlark(value)]
+///     value: Value<'v>,
+///     host: StructAccessField<&'v str>,
+///     #[starlark(field = "port")]
+///     port_number: StructAccessField<u32>,
+/// }
+///
+/// # fn run(config: Value) -> anyhow::Result<()> {
+/// let config = ServerConfig::new(config)?;
+/// println!("{}:{}", config.host()?, config.port_number()?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct StructAccessField<T> {
+    cell: OnceCell<T>,
+}
+
+impl<T> Default for StructAccessField<T> {
+    fn default() -> Self {
+        StructAccessField {
+            cell: OnceCell::new(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for StructAccessField<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StructAccessField")
+            .field(&self.cell.get())
+            .finish()
+    }
+}
+
+impl<T> StructAccessField<T> {
+    /// Was the field unpacked already.
+    pub fn is_cached(&self) -> bool {
+        self.cell.get().is_some()
+    }
+
+    /// Get the field `name` of the struct, unpacking it on the first call.
+    ///
+    /// Fails if the field is missing or has the wrong type; failures are not cached.
+    pub fn get<'v>(&self, value: Value<'v>, name: &str) -> anyhow::Result<&T>
+    where
+        T: UnpackValue<'v>,
+    {
+        if let Some(x) = self.cell.get() {
+            return Ok(x);
+        }
+        let x = struct_ref(value)?
+            .get_field_typed(name)?
+            .ok_or_else(|| StructAccessError::MissingField(name.to_owned()))?;
+        Ok(self.cell.get_or_init(|| x))
+    }
+}
+
+/// Check the value is a struct, used by generated constructors.
+pub fn struct_ref(value: Value) -> anyhow::Result<StructRef> {
+    StructRef::from_value(value)
+        .ok_or_else(|| StructAccessError::NotStruct(value.get_type()).into())
+}
+
+/// Unpack the field `name` of the struct, used by `#[derive(UnpackStruct)]`.
+pub fn unpack_struct_field<'v, T: UnpackValue<'v>>(
+    value: &StructRef<'v>,
+    name: &str,
+) -> anyhow::Result<T> {
+    value
+        .get_field_typed(name)?
+        .ok_or_else(|| StructAccessError::MissingField(name.to_owned()).into())
+}
+
+/// Like [`unpack_struct_field`], but without constructing an error.
+pub fn unpack_struct_field_opt<'v, T: UnpackValue<'v>>(
+    value: &StructRef<'v>,
+    name: &str,
+) -> Option<T> {
+    T::unpack_value(value.0.get(name)?)
+}
@@ -0,0 +1,283 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_map::small_map::SmallMap;
+use starlark_map::Hashed;
+
+use crate::values::dict::DictRef;
+use crate::values::structs::value::Struct;
+use crate::values::AllocValue;
+use crate::values::Heap;
+use crate::values::StringValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum StructBuilderError {
+    #[error("Struct is missing expected fields: {}", quote_names(.0))]
+    MissingFields(Vec<String>),
+    #[error("Struct has unexpected fields: {}", quote_names(.0))]
+    UnexpectedFields(Vec<String>),
+    #[error("Struct field names must be strings, got dict key of type `{0}`")]
+    NonStringKey(&'static str),
+}
+
+fn quote_names(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Build a struct field by field.
+///
+/// Fields are kept in insertion order. Adding a field with a name
+/// which is already present replaces its value.
+///
+/// # Example
+///
+/// ```
+/// use starlark::values::structs::StructBuilder;
+/// # use starlark::values::Heap;
+///
+/// # fn build(heap: &Heap) {
+/// let mut builder = StructBuilder::new(heap);
+/// builder.add("host", "localhost");
+/// builder.add("port", 80);
+/// let s = builder.build();
+/// # }
+/// ```
+pub struct StructBuilder<'v> {
+    heap: &'v Heap,
+    fields: SmallMap<StringValue<'v>, Value<'v>>,
+}
+
+impl<'v> StructBuilder<'v> {
+    /// Create a new builder allocating on the given heap.
+    pub fn new(heap: &'v Heap) -> Self {
+        Self::with_capacity(heap, 0)
+    }
+
+    /// Create a new builder with space for `capacity` fields.
+    pub fn with_capacity(heap: &'v Heap, capacity: usize) -> Self {
+        StructBuilder {
+            heap,
+            fields: SmallMap::with_capacity(capacity),
+        }
+    }
+
+    /// Add a field to the struct.
+    pub fn add(&mut self, key: &str, val: impl AllocValue<'v>) {
+        let key = self.heap.alloc_str(key);
+        let val = val.alloc_value(self.heap);
+        self.fields.insert(key, val);
+    }
+
+    /// Add fields from an iterator of names and values, like `struct(**fields)`.
+    ///
+    /// A field with a name which is already present replaces its value
+    /// but keeps its position, like [`SmallMap::insert`].
+    pub fn extend(&mut self, iter: impl IntoIterator<Item = (StringValue<'v>, Value<'v>)>) {
+        self.fields.extend(iter);
+    }
+
+    /// Add fields from a dict with string keys, like `struct(**dict)`.
+    ///
+    /// Existing fields are replaced as by [`extend`](StructBuilder::extend).
+    /// Fails if any key is not a string, in which case no fields are added.
+    pub fn extend_from_dict(&mut self, dict: DictRef<'v>) -> anyhow::Result<()> {
+        let fields = dict
+            .iter()
+            .map(|(k, v)| match StringValue::new(k) {
+                Some(k) => Ok((k, v)),
+                None => Err(StructBuilderError::NonStringKey(k.get_type())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.extend(fields);
+        Ok(())
+    }
+
+    /// Add fields from a parsed JSON object, like `struct(**json.decode(...))`.
+    ///
+    /// Field values are allocated as by `json.decode`, so nested objects become dicts.
+    /// Keys need not be identifiers: such fields are still accessible with `getattr`.
+    /// Existing fields are replaced as by [`extend`](StructBuilder::extend).
+    pub fn extend_from_json_map(&mut self, map: &serde_json::Map<String, serde_json::Value>) {
+        for (k, v) in map {
+            self.add(k, v);
+        }
+    }
+
+    /// Allocate the struct on the heap.
+    pub fn build(self) -> Value<'v> {
+        self.heap.alloc(Struct::new(self.fields))
+    }
+
+    /// Allocate the struct on the heap, but first check the struct has exactly
+    /// the `expected` fields, in any order.
+    ///
+    /// Fails if any expected field was not added, or if any added field is not expected.
+    pub fn build_exact(self, expected: &[&str]) -> anyhow::Result<Value<'v>> {
+        let missing: Vec<String> = expected
+            .iter()
+            .filter(|name| self.fields.get_hashed(Hashed::new(**name)).is_none())
+            .map(|name| (*name).to_owned())
+            .collect();
+        if !missing.is_empty() {
+            return Err(StructBuilderError::MissingFields(missing).into());
+        }
+        let unexpected: Vec<String> = self
+            .fields
+            .keys()
+            .filter(|name| !expected.contains(&name.as_str()))
+            .map(|name| name.as_str().to_owned())
+            .collect();
+        if !unexpected.is_empty() {
+            return Err(StructBuilderError::UnexpectedFields(unexpected).into());
+        }
+        Ok(self.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+    use crate::struct_of;
+    use crate::values::dict::DictRef;
+    use crate::values::structs::StructBuilder;
+    use crate::values::structs::StructRef;
+    use crate::values::Heap;
+
+    fn builder<'v>(heap: &'v Heap) -> StructBuilder<'v> {
+        let mut builder = StructBuilder::new(heap);
+        builder.add("a", 1);
+        builder.add("b", "x");
+        builder
+    }
+
+    #[test]
+    fn test_build() {
+        let heap = Heap::new();
+        let mut builder = builder(&heap);
+        builder.add("a", 2);
+        let s = builder.build();
+        assert_eq!("struct(a=2, b=\"x\")", s.to_repr());
+        assert!(StructRef::from_value(s).is_some());
+    }
+
+    #[test]
+    fn test_extend() {
+        let heap = Heap::new();
+        let mut builder = builder(&heap);
+        builder.extend([
+            (heap.alloc_str("c"), heap.alloc(3)),
+            (heap.alloc_str("a"), heap.alloc(4)),
+        ]);
+        assert_eq!("struct(a=4, b=\"x\", c=3)", builder.build().to_repr());
+    }
+
+    #[test]
+    fn test_extend_from_dict() {
+        let a = Assert::new();
+        let d = a.pass("{'c': 3, 'a': [4]}");
+        let heap = Heap::new();
+        let mut builder = builder(&heap);
+        builder
+            .extend_from_dict(DictRef::from_value(d.value()).unwrap())
+            .unwrap();
+        assert_eq!("struct(a=[4], b=\"x\", c=3)", builder.build().to_repr());
+    }
+
+    #[test]
+    fn test_extend_from_dict_non_string_key() {
+        let a = Assert::new();
+        let d = a.pass("{'c': 3, 1: 2}");
+        let heap = Heap::new();
+        let mut builder = builder(&heap);
+        assert_eq!(
+            "Struct field names must be strings, got dict key of type `int`",
+            builder
+                .extend_from_dict(DictRef::from_value(d.value()).unwrap())
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!("struct(a=1, b=\"x\")", builder.build().to_repr());
+    }
+
+    #[test]
+    fn test_extend_from_json_map() {
+        let json = serde_json::json!({
+            "c": {"d": [1, 2.5, null], "e": {"f": true}},
+            "a": "y",
+            "my-key": 3,
+        });
+        let heap = Heap::new();
+        let mut builder = builder(&heap);
+        builder.extend_from_json_map(json.as_object().unwrap());
+        let s = builder.build();
+        assert_eq!(
+            r#"struct(a="y", b="x", c={"d": [1, 2.5, None], "e": {"f": True}}, my-key=3)"#,
+            s.to_repr()
+        );
+        assert_eq!(
+            Some(3),
+            s.get_attr("my-key", &heap)
+                .unwrap()
+                .and_then(|v| v.unpack_i32())
+        );
+        assert!(s.equals_json(&serde_json::json!({
+            "a": "y",
+            "b": "x",
+            "c": {"d": [1, 2.5, null], "e": {"f": true}},
+            "my-key": 3,
+        })));
+    }
+
+    #[test]
+    fn test_build_exact() {
+        let heap = Heap::new();
+        let s = builder(&heap).build_exact(&["b", "a"]).unwrap();
+        assert_eq!("struct(a=1, b=\"x\")", s.to_repr());
+    }
+
+    #[test]
+    fn test_build_exact_missing() {
+        let heap = Heap::new();
+        let err = builder(&heap).build_exact(&["a", "b", "c"]).unwrap_err();
+        assert_eq!("Struct is missing expected fields: `c`", err.to_string());
+    }
+
+    #[test]
+    fn test_build_exact_unexpected() {
+        let heap = Heap::new();
+        let err = builder(&heap).build_exact(&["a"]).unwrap_err();
+        assert_eq!("Struct has unexpected fields: `b`", err.to_string());
+    }
+
+    #[test]
+    fn test_struct_of_macro() {
+        let heap = Heap::new();
+        let prefix = "opt";
+        let s = struct_of! {
+            &heap;
+            "a" => 1,
+            format!("{}_{}", prefix, 2) => "x",
+        };
+        assert_eq!("struct(a=1, opt_2=\"x\")", s.to_repr());
+        assert_eq!("struct()", struct_of! { &heap }.to_repr());
+    }
+}
@@ -0,0 +1,138 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_map::small_set::SmallSet;
+use starlark_map::Hashed;
+
+use crate::values::structs::StructRef;
+use crate::values::Value;
+
+/// A read-only view of several structs, resolving fields by checking
+/// each struct in priority order.
+///
+/// Useful for layered configurations (for example defaults, environment and overrides)
+/// without merging the structs eagerly.
+///
+/// # Example
+///
+/// ```
+/// use starlark::values::structs::AllocStruct;
+/// use starlark::values::structs::StructChain;
+/// use starlark::values::structs::StructRef;
+/// use starlark::values::Heap;
+///
+/// let heap = Heap::new();
+/// let overrides = heap.alloc(AllocStruct([("port", 8080)]));
+/// let defaults = heap.alloc(AllocStruct([("host", 1), ("port", 80)]));
+/// let chain = StructChain::new(
+///     [overrides, defaults]
+///         .into_iter()
+///         .map(|s| StructRef::from_value(s).unwrap()),
+/// );
+/// assert_eq!(Some(heap.alloc(8080)), chain.get_attr("port"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StructChain<'v> {
+    layers: Vec<StructRef<'v>>,
+}
+
+impl<'v> StructChain<'v> {
+    /// Create a chain from structs, highest priority first.
+    pub fn new(layers: impl IntoIterator<Item = StructRef<'v>>) -> Self {
+        StructChain {
+            layers: layers.into_iter().collect(),
+        }
+    }
+
+    /// The structs in priority order.
+    pub fn layers(&self) -> &[StructRef<'v>] {
+        &self.layers
+    }
+
+    /// Get the field from the first struct which has it.
+    pub fn get_attr(&self, name: &str) -> Option<Value<'v>> {
+        let name = Hashed::new(name);
+        self.layers
+            .iter()
+            .find_map(|layer| layer.0.get_hashed(name))
+    }
+
+    /// Does any struct have the field.
+    pub fn has_attr(&self, name: &str) -> bool {
+        self.get_attr(name).is_some()
+    }
+
+    /// Union of field names of all the structs.
+    ///
+    /// Names are listed in the order they are first seen, checking structs in priority order.
+    pub fn dir_attr(&self) -> Vec<String> {
+        let mut names = SmallSet::new();
+        for layer in &self.layers {
+            for (name, _) in layer.iter() {
+                names.insert(name.as_str());
+            }
+        }
+        names.into_iter().map(|name| name.to_owned()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::structs::AllocStruct;
+    use crate::values::structs::StructChain;
+    use crate::values::structs::StructRef;
+    use crate::values::Heap;
+    use crate::values::Value;
+
+    fn chain<'v>(layers: &[Value<'v>]) -> StructChain<'v> {
+        StructChain::new(
+            layers
+                .iter()
+                .map(|layer| StructRef::from_value(*layer).unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_get_attr_priority() {
+        let heap = Heap::new();
+        let overrides = heap.alloc(AllocStruct([("port", 8080)]));
+        let env = heap.alloc(AllocStruct([
+            ("host", heap.alloc("prod")),
+            ("port", heap.alloc(443)),
+        ]));
+        let defaults = heap.alloc(AllocStruct([("host", "localhost"), ("debug", "no")]));
+        let c = chain(&[overrides, env, defaults]);
+        assert_eq!(Some(heap.alloc(8080)), c.get_attr("port"));
+        assert_eq!(Some(heap.alloc("prod")), c.get_attr("host"));
+        assert_eq!(Some(heap.alloc("no")), c.get_attr("debug"));
+        assert_eq!(None, c.get_attr("missing"));
+        assert!(c.has_attr("debug"));
+        assert!(!c.has_attr("missing"));
+        assert_eq!(None, chain(&[]).get_attr("port"));
+    }
+
+    #[test]
+    fn test_dir_attr_union() {
+        let heap = Heap::new();
+        let overrides = heap.alloc(AllocStruct([("port", 8080)]));
+        let defaults = heap.alloc(AllocStruct([("host", 1), ("port", 80), ("debug", 0)]));
+        assert_eq!(
+            vec!["port", "host", "debug"],
+            chain(&[overrides, defaults]).dir_attr()
+        );
+    }
+}
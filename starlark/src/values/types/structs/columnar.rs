@@ -0,0 +1,121 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_map::small_map::SmallMap;
+use starlark_map::Hashed;
+
+use crate::values::structs::value::Struct;
+use crate::values::Heap;
+use crate::values::StringValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum ColumnarError {
+    #[error("Expected a struct at index {0}, got a value of type `{1}`")]
+    NotStruct(usize, &'static str),
+    #[error("Struct at index {0} is missing field `{1}`")]
+    MissingField(usize, String),
+    #[error("Struct at index {0} has unexpected field `{1}`")]
+    UnexpectedField(usize, String),
+}
+
+/// Transpose a list of structs into a struct of lists, one list per field.
+///
+/// For example `[struct(a = 1, b = 2), struct(a = 3, b = 4)]` becomes
+/// `struct(a = [1, 3], b = [2, 4])`. Fields are ordered as in the first struct.
+///
+/// By default all structs must have the same fields, in any order.
+/// With `fill_missing`, the result has the union of all fields in first-seen order,
+/// and `None` is used where a struct does not have a field.
+pub fn columnar<'v>(
+    heap: &'v Heap,
+    structs: &[Value<'v>],
+    fill_missing: bool,
+) -> anyhow::Result<Value<'v>> {
+    let mut columns: SmallMap<StringValue<'v>, Vec<Value<'v>>> = SmallMap::new();
+    for (i, value) in structs.iter().enumerate() {
+        let s = Struct::from_value(*value)
+            .ok_or_else(|| ColumnarError::NotStruct(i, value.get_type()))?;
+        for (k, v) in s.iter_hashed() {
+            match columns.get_mut_hashed(Hashed::new_unchecked(k.hash(), k.key().as_str())) {
+                Some(column) => column.push(v),
+                None if i == 0 || fill_missing => {
+                    let mut column = vec![Value::new_none(); i];
+                    column.push(v);
+                    columns.insert_hashed_unique_unchecked(k, column);
+                }
+                None => {
+                    return Err(
+                        ColumnarError::UnexpectedField(i, k.key().as_str().to_owned()).into(),
+                    );
+                }
+            }
+        }
+        for (k, column) in columns.iter_mut() {
+            if column.len() == i {
+                if !fill_missing {
+                    return Err(ColumnarError::MissingField(i, k.as_str().to_owned()).into());
+                }
+                column.push(Value::new_none());
+            }
+        }
+    }
+    let fields = columns
+        .into_iter()
+        .map(|(k, column)| (k, heap.alloc(column)))
+        .collect();
+    Ok(heap.alloc(Struct::new(fields)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_columnar() {
+        assert::eq(
+            "struct(a = [1, 3, 5], b = ['x', 'y', 'z'])",
+            "columnar([struct(a = 1, b = 'x'), struct(b = 'y', a = 3), struct(a = 5, b = 'z')])",
+        );
+        assert::eq("struct()", "columnar([])");
+        assert::eq("struct(a = [1])", "columnar((struct(a = 1),))");
+    }
+
+    #[test]
+    fn test_columnar_fill_missing() {
+        assert::eq(
+            "struct(a = [1, None, 5], b = [None, 'y', None], c = [None, 4, None])",
+            "columnar([struct(a = 1), struct(b = 'y', c = 4), struct(a = 5)], fill_missing = True)",
+        );
+    }
+
+    #[test]
+    fn test_columnar_shape_mismatch() {
+        assert::fail(
+            "columnar([struct(a = 1, b = 2), struct(a = 3)])",
+            "Struct at index 1 is missing field `b`",
+        );
+        assert::fail(
+            "columnar([struct(a = 1), struct(a = 3, c = 4)])",
+            "Struct at index 1 has unexpected field `c`",
+        );
+        assert::fail(
+            "columnar([struct(a = 1), {'a': 3}])",
+            "Expected a struct at index 1, got a value of type `dict`",
+        );
+    }
+}
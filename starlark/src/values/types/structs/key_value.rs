@@ -0,0 +1,221 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversion of structs to `google.protobuf.Struct`-like entries.
+
+use crate::values::dict::DictRef;
+use crate::values::structs::walk::field_path;
+use crate::values::structs::walk::walk;
+use crate::values::structs::walk::walk_fields;
+use crate::values::structs::walk::StructWalk;
+use crate::values::structs::StructRef;
+use crate::values::types::int_or_big::StarlarkIntRef;
+
+#[derive(Debug, thiserror::Error)]
+enum KeyValueError {
+    #[error("Cannot convert value of type `{1}` at `{0}` to a protobuf value")]
+    UnsupportedValue(String, &'static str),
+    #[error("Cannot convert dict with key of type `{1}` at `{0}` to a protobuf struct")]
+    NonStringKey(String, &'static str),
+}
+
+/// Field of a struct, produced by [`StructRef::to_key_values`].
+///
+/// Mirrors a `google.protobuf.Struct` fields entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyValue {
+    /// Field name.
+    pub key: String,
+    /// Field value.
+    pub value: KeyValueKind,
+}
+
+/// Value tagged with its kind, mirroring `google.protobuf.Value`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyValueKind {
+    /// `None`.
+    Null,
+    /// `int` or `float`.
+    ///
+    /// Like `google.protobuf.Value`, numbers are `f64`, so integers above `2**53`
+    /// in magnitude are rounded to the nearest `f64`, and very large integers become infinite.
+    Number(f64),
+    /// `str`.
+    String(String),
+    /// `bool`.
+    Bool(bool),
+    /// Struct, or dict with string keys.
+    Struct(Vec<KeyValue>),
+    /// `list` or `tuple`.
+    List(Vec<KeyValueKind>),
+}
+
+impl<'v> StructRef<'v> {
+    /// Convert the struct into key/value entries in field order,
+    /// following the `google.protobuf.Struct` mapping.
+    ///
+    /// Nested structs and dicts with string keys become [`KeyValueKind::Struct`],
+    /// lists and tuples become [`KeyValueKind::List`].
+    /// Fails on dicts with non-string keys, on values of any other type,
+    /// and on cyclic lists or dicts.
+    pub fn to_key_values(&self) -> anyhow::Result<Vec<KeyValue>> {
+        Ok(key_values(walk_fields("", *self)?))
+    }
+}
+
+fn key_values(fields: Vec<(String, KeyValueKind)>) -> Vec<KeyValue> {
+    fields
+        .into_iter()
+        .map(|(key, value)| KeyValue { key, value })
+        .collect()
+}
+
+impl StructWalk for KeyValueKind {
+    fn none() -> Self {
+        KeyValueKind::Null
+    }
+
+    fn bool(b: bool) -> Self {
+        KeyValueKind::Bool(b)
+    }
+
+    fn int(i: StarlarkIntRef) -> Self {
+        KeyValueKind::Number(i.to_f64())
+    }
+
+    fn float(f: f64) -> Self {
+        KeyValueKind::Number(f)
+    }
+
+    fn string(s: &str) -> Self {
+        KeyValueKind::String(s.to_owned())
+    }
+
+    fn list(items: Vec<Self>) -> Self {
+        KeyValueKind::List(items)
+    }
+
+    fn tuple(items: Vec<Self>) -> Self {
+        KeyValueKind::List(items)
+    }
+
+    fn struct_fields(fields: Vec<(String, Self)>) -> Self {
+        KeyValueKind::Struct(key_values(fields))
+    }
+
+    fn dict(path: &str, dict: &DictRef) -> anyhow::Result<Self> {
+        dict.iter()
+            .map(|(k, v)| match k.unpack_str() {
+                Some(k) => Ok(KeyValue {
+                    key: k.to_owned(),
+                    value: walk(field_path(path, k), v)?,
+                }),
+                None => Err(KeyValueError::NonStringKey(path.to_owned(), k.get_type()).into()),
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(KeyValueKind::Struct)
+    }
+
+    fn unsupported(path: String, ty: &'static str) -> anyhow::Error {
+        KeyValueError::UnsupportedValue(path, ty).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::values::structs::KeyValue;
+    use crate::values::structs::KeyValueKind;
+    use crate::values::structs::StructRef;
+
+    fn to_key_values(program: &str) -> anyhow::Result<Vec<KeyValue>> {
+        let module = assert::pass_module(&format!("x = {}", program));
+        let x = module.get("x").unwrap();
+        StructRef::from_value(x.value()).unwrap().to_key_values()
+    }
+
+    fn kv(key: &str, value: KeyValueKind) -> KeyValue {
+        KeyValue {
+            key: key.to_owned(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_to_key_values() {
+        let actual = to_key_values(
+            "struct(name = 'a', port = 80, ratio = 0.5, big = 1 << 70, on = True, \
+             nothing = None, hosts = ['h', (1,)], tls = struct(enabled = False), \
+             labels = {'k': 'v'})",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                kv("name", KeyValueKind::String("a".to_owned())),
+                kv("port", KeyValueKind::Number(80.0)),
+                kv("ratio", KeyValueKind::Number(0.5)),
+                kv("big", KeyValueKind::Number(2f64.powi(70))),
+                kv("on", KeyValueKind::Bool(true)),
+                kv("nothing", KeyValueKind::Null),
+                kv(
+                    "hosts",
+                    KeyValueKind::List(vec![
+                        KeyValueKind::String("h".to_owned()),
+                        KeyValueKind::List(vec![KeyValueKind::Number(1.0)]),
+                    ])
+                ),
+                kv(
+                    "tls",
+                    KeyValueKind::Struct(vec![kv("enabled", KeyValueKind::Bool(false))])
+                ),
+                kv(
+                    "labels",
+                    KeyValueKind::Struct(vec![kv("k", KeyValueKind::String("v".to_owned()))])
+                ),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_to_key_values_unsupported() {
+        assert_eq!(
+            "Cannot convert value of type `function` at `a.b[0]` to a protobuf value",
+            to_key_values("struct(a = struct(b = [len]))")
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Cannot convert dict with key of type `int` at `a` to a protobuf struct",
+            to_key_values("struct(a = {1: 2})").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_to_key_values_cyclic() {
+        let module = assert::pass_module("l = [1]\nl.append(l)\nx = struct(l = l)");
+        let x = module.get("x").unwrap();
+        assert_eq!(
+            "Too many recursion levels",
+            StructRef::from_value(x.value())
+                .unwrap()
+                .to_key_values()
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
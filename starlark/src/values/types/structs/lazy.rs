@@ -0,0 +1,334 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Struct with fields computed on first access.
+
+use std::cell::OnceCell;
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use display_container::fmt_keyed_container;
+use either::Either;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+use starlark_derive::Trace;
+use starlark_map::small_map::SmallMap;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::eval::Evaluator;
+use crate::values::AllocValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+
+/// Computes the value of a lazy field of [`LazyStruct`].
+///
+/// The thunk is `'static`, so it cannot capture unfrozen values,
+/// and it allocates the result on the heap of the struct.
+pub type LazyStructThunk = Box<dyn for<'v> Fn(&'v Heap) -> Value<'v>>;
+
+#[derive(Trace, Allocative)]
+enum LazyField<'v> {
+    Value(Value<'v>),
+    Thunk(
+        // Thunks are `'static` and cannot reference values.
+        #[allocative(skip)]
+        #[trace(unsafe_ignore)]
+        LazyStructThunk,
+        OnceCell<Value<'v>>,
+    ),
+    /// Starlark callable with no parameters and its result.
+    Callable(Value<'v>, OnceCell<Value<'v>>),
+}
+
+impl<'v> LazyField<'v> {
+    /// The value of the field if it is known or computed already.
+    fn get(&self) -> Option<Value<'v>> {
+        match self {
+            LazyField::Value(v) => Some(*v),
+            LazyField::Thunk(_, cell) | LazyField::Callable(_, cell) => cell.get().copied(),
+        }
+    }
+}
+
+/// Struct-like value where some fields are computed on first access and then cached.
+///
+/// Fields are read with the usual attribute syntax, `s.field`. The thunk of a
+/// lazy field is invoked by the first read of that field, and subsequent reads
+/// return the same value. A heap is only accessed by one thread at a time,
+/// so each thunk is invoked at most once.
+///
+/// Unlike `struct`, a lazy struct is not comparable or hashable, and it cannot be
+/// frozen: evaluation fails if it is assigned to a global variable of a module
+/// which is then frozen.
+///
+/// Lazy structs can only be created from Rust. A lazy field is computed either
+/// by a Rust closure ([`lazy_field`](LazyStruct::lazy_field)) or by a Starlark
+/// callable ([`lazy_field_callable`](LazyStruct::lazy_field_callable)).
+/// A callable is invoked by the attribute access from Starlark code, which has
+/// an evaluator, so until the first such access, reads of the field from Rust
+/// with [`Value::get_attr`] return `None`. If the callable fails, the error is
+/// returned and the callable is invoked again by the next access.
+///
+/// # Example
+///
+/// ```
+/// use starlark::values::structs::LazyStruct;
+/// use starlark::values::Heap;
+///
+/// let heap = Heap::new();
+/// let s = heap.alloc(
+///     LazyStruct::new()
+///         .field("name", heap.alloc("app"))
+///         .lazy_field("answer", Box::new(|heap| heap.alloc(6 * 7))),
+/// );
+/// assert_eq!(
+///     Some(42),
+///     s.get_attr("answer", &heap).unwrap().unwrap().unpack_i32()
+/// );
+/// ```
+#[derive(ProvidesStaticType, Trace, Allocative, NoSerialize, Default)]
+pub struct LazyStruct<'v> {
+    fields: SmallMap<String, LazyField<'v>>,
+}
+
+impl<'v> LazyStruct<'v> {
+    /// Lazy struct with no fields.
+    pub fn new() -> LazyStruct<'v> {
+        LazyStruct::default()
+    }
+
+    /// Add a field with known value, replacing a field with the same name.
+    pub fn field(mut self, name: &str, value: Value<'v>) -> LazyStruct<'v> {
+        self.fields.insert(name.to_owned(), LazyField::Value(value));
+        self
+    }
+
+    /// Add a field computed by `thunk` on first access,
+    /// replacing a field with the same name.
+    pub fn lazy_field(mut self, name: &str, thunk: LazyStructThunk) -> LazyStruct<'v> {
+        self.fields
+            .insert(name.to_owned(), LazyField::Thunk(thunk, OnceCell::new()));
+        self
+    }
+
+    /// Add a field computed by calling `callable` with no arguments on first access
+    /// from Starlark code, replacing a field with the same name.
+    pub fn lazy_field_callable(mut self, name: &str, callable: Value<'v>) -> LazyStruct<'v> {
+        self.fields.insert(
+            name.to_owned(),
+            LazyField::Callable(callable, OnceCell::new()),
+        );
+        self
+    }
+
+    /// Was the field computed already. Fields with known value are always evaluated.
+    /// Returns `None` if there is no such field.
+    pub fn is_evaluated(&self, name: &str) -> Option<bool> {
+        Some(self.fields.get(name)?.get().is_some())
+    }
+}
+
+impl<'v> fmt::Debug for LazyStruct<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LazyStruct({})", self)
+    }
+}
+
+impl<'v> Display for LazyStruct<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Do not force thunks: display must not allocate.
+        fmt_keyed_container(
+            f,
+            "lazy_struct(",
+            ")",
+            "=",
+            self.fields.iter().map(|(k, v)| {
+                (
+                    k.as_str(),
+                    v.get().map_or(Either::Right("<lazy>"), Either::Left),
+                )
+            }),
+        )
+    }
+}
+
+#[starlark_value(type = "lazy_struct")]
+impl<'v> StarlarkValue<'v> for LazyStruct<'v> {
+    fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
+        match self.fields.get(attribute)? {
+            LazyField::Value(v) => Some(*v),
+            LazyField::Thunk(thunk, cell) => Some(*cell.get_or_init(|| thunk(heap))),
+            LazyField::Callable(_, cell) => cell.get().copied(),
+        }
+    }
+
+    fn get_attr_eval(
+        &self,
+        attribute: &str,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Option<Value<'v>>> {
+        match self.fields.get(attribute) {
+            Some(LazyField::Callable(callable, cell)) => {
+                let v = eval.eval_function(*callable, &[], &[])?;
+                // The callable may have read the field recursively, keep the first value.
+                Ok(Some(*cell.get_or_init(|| v)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn has_attr(&self, attribute: &str, _heap: &'v Heap) -> bool {
+        self.fields.contains_key(attribute)
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        self.fields.keys().cloned().collect()
+    }
+}
+
+impl<'v> AllocValue<'v> for LazyStruct<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex_no_freeze(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use starlark_syntax::syntax::AstModule;
+    use starlark_syntax::syntax::Dialect;
+
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::values::structs::LazyStruct;
+    use crate::values::ValueLike;
+
+    #[test]
+    fn test_thunk_evaluated_once() {
+        let calls = Rc::new(Cell::new(0));
+        let module = Module::new();
+        let heap = module.heap();
+        let s = heap.alloc(LazyStruct::new().field("a", heap.alloc(1)).lazy_field(
+            "b",
+            Box::new({
+                let calls = calls.clone();
+                move |heap| {
+                    calls.set(calls.get() + 1);
+                    heap.alloc(vec![2])
+                }
+            }),
+        ));
+        module.set("s", s);
+        assert_eq!("lazy_struct(a=1, b=<lazy>)", s.to_repr());
+        assert_eq!(
+            Some(false),
+            s.downcast_ref::<LazyStruct>().unwrap().is_evaluated("b")
+        );
+
+        let mut eval = Evaluator::new(&module);
+        let ast = AstModule::parse(
+            "lazy.star",
+            "s.b.append(3)\n(s.a, s.b, hasattr(s, 'c'), dir(s))".to_owned(),
+            &Dialect::Standard,
+        )
+        .unwrap();
+        let res = eval.eval_module(ast, &Globals::standard()).unwrap();
+        assert_eq!("(1, [2, 3], False, [\"a\", \"b\"])", res.to_repr());
+        assert_eq!(1, calls.get());
+        assert_eq!("lazy_struct(a=1, b=[2, 3])", s.to_repr());
+        assert_eq!(
+            Some(true),
+            s.downcast_ref::<LazyStruct>().unwrap().is_evaluated("b")
+        );
+        assert_eq!(
+            None,
+            s.downcast_ref::<LazyStruct>().unwrap().is_evaluated("c")
+        );
+    }
+
+    #[test]
+    fn test_callable_evaluated_once() {
+        let module = Module::new();
+        let globals = Globals::standard();
+        let eval_code = |code: &str| {
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse("lazy.star", code.to_owned(), &Dialect::Standard).unwrap();
+            eval.eval_module(ast, &globals)
+        };
+        eval_code(
+            r#"
+calls = []
+def compute():
+    calls.append(1)
+    return [len(calls)]
+def broken():
+    fail("broken field")
+def method():
+    return lambda: "called"
+"#,
+        )
+        .unwrap();
+        let heap = module.heap();
+        let s = heap.alloc(
+            LazyStruct::new()
+                .lazy_field_callable("b", module.get("compute").unwrap())
+                .lazy_field_callable("e", module.get("broken").unwrap())
+                .lazy_field_callable("m", module.get("method").unwrap()),
+        );
+        module.set("s", s);
+        assert_eq!("lazy_struct(b=<lazy>, e=<lazy>, m=<lazy>)", s.to_repr());
+        // Rust code has no evaluator to call the function.
+        assert!(s.get_attr("b", heap).unwrap().is_none());
+
+        let res = eval_code(
+            "s.b.append(2)\n(getattr(s, 'b'), s.b, calls, hasattr(s, 'b'), s.m(), s.m())",
+        )
+        .unwrap();
+        assert_eq!(
+            "([1, 2], [1, 2], [1], True, \"called\", \"called\")",
+            res.to_repr()
+        );
+        assert!(s
+            .to_repr()
+            .starts_with("lazy_struct(b=[1, 2], e=<lazy>, m="));
+        assert_eq!(
+            Some(true),
+            s.downcast_ref::<LazyStruct>().unwrap().is_evaluated("b")
+        );
+
+        let err = eval_code("s.e").unwrap_err();
+        assert!(err.to_string().contains("broken field"), "{}", err);
+        assert_eq!(
+            Some(false),
+            s.downcast_ref::<LazyStruct>().unwrap().is_evaluated("e")
+        );
+    }
+
+    #[test]
+    fn test_cannot_freeze() {
+        let module = Module::new();
+        let heap = module.heap();
+        module.set("s", heap.alloc(LazyStruct::new().field("a", heap.alloc(1))));
+        assert!(module.freeze().is_err());
+    }
+}
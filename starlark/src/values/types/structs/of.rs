@@ -0,0 +1,145 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use starlark_map::small_map::SmallMap;
+
+use crate::typing::Ty;
+use crate::values::structs::StructRef;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::StringValue;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+/// Unpack a struct where every field value unpacks to `V`.
+///
+/// All fields are checked when unpacking, but values are only unpacked again when
+/// requested, with [`get_field`](StructOf::get_field) for a single field or
+/// [`to_map`](StructOf::to_map) for all of them. Field values like lists can be
+/// modified after the check, so these return an error if a value no longer unpacks to `V`.
+pub struct StructOf<'v, V: UnpackValue<'v>> {
+    value: StructRef<'v>,
+    _marker: PhantomData<V>,
+}
+
+impl<'v, V: UnpackValue<'v>> fmt::Debug for StructOf<'v, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StructOf").field(&self.value).finish()
+    }
+}
+
+impl<'v, V: UnpackValue<'v>> StructOf<'v, V> {
+    /// The struct.
+    pub fn as_struct(&self) -> StructRef<'v> {
+        self.value
+    }
+
+    /// Unpack the field `name`, or return `None` if there is no such field.
+    ///
+    /// Errors only if a mutable field value has been modified since the struct
+    /// was unpacked so that it no longer unpacks to `V`.
+    pub fn get_field(&self, name: &str) -> anyhow::Result<Option<V>> {
+        self.value.get_field_typed(name)
+    }
+
+    /// Unpack all the fields, in field order.
+    ///
+    /// Errors like [`get_field`](StructOf::get_field).
+    pub fn to_map(&self) -> anyhow::Result<SmallMap<StringValue<'v>, V>> {
+        self.value
+            .iter()
+            .map(|(k, v)| Ok((k, StructRef::unpack_field(k.as_str(), v)?)))
+            .collect()
+    }
+}
+
+impl<'v, V: UnpackValue<'v>> StarlarkTypeRepr for StructOf<'v, V> {
+    type Canonical = StructRef<'v>;
+
+    fn starlark_type_repr() -> Ty {
+        StructRef::starlark_type_repr()
+    }
+}
+
+impl<'v, V: UnpackValue<'v>> UnpackValue<'v> for StructOf<'v, V> {
+    fn unpack_value(value: Value<'v>) -> Option<Self> {
+        let s = StructRef::from_value(value)?;
+        for (_, v) in s.iter() {
+            V::unpack_value(v)?;
+        }
+        Some(StructOf {
+            value: s,
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::list::value::ListData;
+    use crate::values::list::AllocList;
+    use crate::values::list::UnpackList;
+    use crate::values::structs::AllocStruct;
+    use crate::values::structs::StructOf;
+    use crate::values::Heap;
+    use crate::values::UnpackValue;
+
+    #[test]
+    fn test_struct_of() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("b", 2), ("a", 1)]));
+        let s = StructOf::<i32>::unpack_value(v).unwrap();
+        assert_eq!(Some(1), s.get_field("a").unwrap());
+        assert_eq!(None, s.get_field("c").unwrap());
+        assert_eq!(
+            vec![("b", 2), ("a", 1)],
+            s.to_map()
+                .unwrap()
+                .iter()
+                .map(|(k, v)| (k.as_str(), *v))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, s.as_struct().iter().len());
+    }
+
+    #[test]
+    fn test_struct_of_rejected() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("a", heap.alloc(1)), ("b", heap.alloc("x"))]));
+        assert!(StructOf::<i32>::unpack_value(v).is_none());
+        assert!(StructOf::<i32>::unpack_value(heap.alloc(1)).is_none());
+        assert!(StructOf::<&str>::unpack_value(heap.alloc(AllocStruct([("a", "x")]))).is_some());
+    }
+
+    #[test]
+    fn test_struct_of_field_modified_after_unpack() {
+        let heap = Heap::new();
+        let list = heap.alloc(AllocList([1]));
+        let v = heap.alloc(AllocStruct([("a", list)]));
+        let s = StructOf::<UnpackList<i32>>::unpack_value(v).unwrap();
+        ListData::from_value_mut(list)
+            .unwrap()
+            .push(heap.alloc("x"), &heap);
+        assert_eq!(
+            "Expected `list[int]` for struct field `a`, got value of type `list`",
+            s.get_field("a").unwrap_err().to_string()
+        );
+        assert!(s.to_map().is_err());
+    }
+}
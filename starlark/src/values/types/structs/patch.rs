@@ -0,0 +1,243 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::values::structs::value::Struct;
+use crate::values::structs::StructRef;
+use crate::values::Heap;
+use crate::values::StringValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum StructPatchError {
+    #[error("Cannot remove struct field `{0}`: no such field")]
+    RemoveMissing(String),
+    #[error("Cannot patch struct field `{0}`: no such field")]
+    PatchMissing(String),
+    #[error("Cannot patch struct field `{0}`: expected `struct`, got value of type `{1}`")]
+    PatchNotStruct(String, &'static str),
+}
+
+/// Operation on a single field of a [`StructPatch`].
+#[derive(Debug, Clone)]
+pub enum StructPatchOp<'v> {
+    /// Add the field, or replace its value.
+    Set(Value<'v>),
+    /// Remove the field.
+    Remove,
+    /// Patch the struct value of the field.
+    Patch(StructPatch<'v>),
+}
+
+/// Changes turning one struct into another, created with [`StructRef::diff_patch`].
+///
+/// Fields whose values are structs in both the old and the new struct are patched
+/// recursively, all other changed fields are replaced as a whole.
+#[derive(Debug, Clone, Default)]
+pub struct StructPatch<'v> {
+    ops: Vec<(StringValue<'v>, StructPatchOp<'v>)>,
+}
+
+impl<'v> StructPatch<'v> {
+    pub(crate) fn diff(old: StructRef<'v>, new: StructRef<'v>) -> crate::Result<StructPatch<'v>> {
+        let mut ops = Vec::new();
+        for (k, _) in old.0.iter_hashed() {
+            if new.0.get_hashed(k.key().get_hashed_str()).is_none() {
+                ops.push((*k.key(), StructPatchOp::Remove));
+            }
+        }
+        for (k, v) in new.0.iter_hashed() {
+            let op = match old.0.get_hashed(k.key().get_hashed_str()) {
+                None => StructPatchOp::Set(v),
+                Some(prev) => {
+                    if prev.equals(v)? {
+                        continue;
+                    }
+                    match (StructRef::from_value(prev), StructRef::from_value(v)) {
+                        (Some(prev), Some(v)) => StructPatchOp::Patch(StructPatch::diff(prev, v)?),
+                        _ => StructPatchOp::Set(v),
+                    }
+                }
+            };
+            ops.push((*k.key(), op));
+        }
+        Ok(StructPatch { ops })
+    }
+
+    /// The field operations, removals first, then other operations in the field order
+    /// of the new struct.
+    pub fn ops(&self) -> &[(StringValue<'v>, StructPatchOp<'v>)] {
+        &self.ops
+    }
+
+    /// Does the patch change nothing?
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply the patch to `base`, allocating the resulting struct.
+    ///
+    /// Fields of `base` keep their order, fields added by the patch come last.
+    /// Fails if the patch removes or patches a field missing from `base`,
+    /// or patches a field which is not a struct.
+    pub fn apply(&self, heap: &'v Heap, base: StructRef<'v>) -> anyhow::Result<Value<'v>> {
+        let mut fields = base.0.fields_map().into_owned();
+        for (k, op) in &self.ops {
+            let hashed = k.get_hashed();
+            match op {
+                StructPatchOp::Set(v) => {
+                    fields.insert_hashed(hashed, *v);
+                }
+                StructPatchOp::Remove => {
+                    if fields.remove_hashed(hashed.as_ref()).is_none() {
+                        return Err(StructPatchError::RemoveMissing(k.as_str().to_owned()).into());
+                    }
+                }
+                StructPatchOp::Patch(patch) => {
+                    let Some(v) = fields.get_mut_hashed(hashed.as_ref()) else {
+                        return Err(StructPatchError::PatchMissing(k.as_str().to_owned()).into());
+                    };
+                    let Some(s) = StructRef::from_value(*v) else {
+                        return Err(StructPatchError::PatchNotStruct(
+                            k.as_str().to_owned(),
+                            v.get_type(),
+                        )
+                        .into());
+                    };
+                    *v = patch.apply(heap, s)?;
+                }
+            }
+        }
+        Ok(heap.alloc(Struct::new(fields)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::structs::AllocStruct;
+    use crate::values::structs::StructPatchOp;
+    use crate::values::structs::StructRef;
+    use crate::values::Heap;
+    use crate::values::Value;
+
+    fn check_round_trip<'v>(heap: &'v Heap, a: Value<'v>, b: Value<'v>) {
+        let (sa, sb) = (
+            StructRef::from_value(a).unwrap(),
+            StructRef::from_value(b).unwrap(),
+        );
+        let patch = sa.diff_patch(sb).unwrap();
+        let applied = patch.apply(heap, sa).unwrap();
+        assert!(applied.equals(b).unwrap(), "{applied} != {b}");
+    }
+
+    #[test]
+    fn test_diff_patch() {
+        let heap = Heap::new();
+        let a = heap.alloc(AllocStruct([
+            ("x", heap.alloc(1)),
+            ("y", heap.alloc(AllocStruct([("p", 1), ("q", 2)]))),
+            ("z", heap.alloc("r")),
+        ]));
+        let b = heap.alloc(AllocStruct([
+            ("y", heap.alloc(AllocStruct([("p", 3), ("q", 2)]))),
+            ("z", heap.alloc("r")),
+            ("w", Value::new_none()),
+        ]));
+        let patch = StructRef::from_value(a)
+            .unwrap()
+            .diff_patch(StructRef::from_value(b).unwrap())
+            .unwrap();
+        let ops: Vec<String> = patch
+            .ops()
+            .iter()
+            .map(|(k, op)| match op {
+                StructPatchOp::Set(v) => format!("set {}={v}", k.as_str()),
+                StructPatchOp::Remove => format!("remove {}", k.as_str()),
+                StructPatchOp::Patch(p) => format!("patch {} ({} ops)", k.as_str(), p.ops().len()),
+            })
+            .collect();
+        assert_eq!(vec!["remove x", "patch y (1 ops)", "set w=None"], ops);
+        assert_eq!(
+            "struct(y=struct(p=3, q=2), z=\"r\", w=None)",
+            patch
+                .apply(&heap, StructRef::from_value(a).unwrap())
+                .unwrap()
+                .to_string()
+        );
+        check_round_trip(&heap, a, b);
+        check_round_trip(&heap, b, a);
+    }
+
+    #[test]
+    fn test_diff_patch_replaces_non_struct_fields() {
+        let heap = Heap::new();
+        let a = heap.alloc(AllocStruct([
+            ("x", heap.alloc(AllocStruct([("p", 1)]))),
+            ("y", heap.alloc(vec![1, 2])),
+        ]));
+        let b = heap.alloc(AllocStruct([
+            ("x", heap.alloc(5)),
+            ("y", heap.alloc(vec![1, 3])),
+        ]));
+        let sa = StructRef::from_value(a).unwrap();
+        let patch = sa.diff_patch(StructRef::from_value(b).unwrap()).unwrap();
+        assert!(patch
+            .ops()
+            .iter()
+            .all(|(_, op)| matches!(op, StructPatchOp::Set(_))));
+        check_round_trip(&heap, a, b);
+        check_round_trip(&heap, b, a);
+        assert!(sa.diff_patch(sa).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let heap = Heap::new();
+        let a = heap.alloc(AllocStruct([("x", heap.alloc(AllocStruct([("p", 1)])))]));
+        let b = heap.alloc(AllocStruct([("x", heap.alloc(AllocStruct([("p", 2)])))]));
+        let patch = StructRef::from_value(a)
+            .unwrap()
+            .diff_patch(StructRef::from_value(b).unwrap())
+            .unwrap();
+        let x_int = heap.alloc(AllocStruct([("x", 1)]));
+        assert_eq!(
+            "Cannot patch struct field `x`: expected `struct`, got value of type `int`",
+            patch
+                .apply(&heap, StructRef::from_value(x_int).unwrap())
+                .unwrap_err()
+                .to_string()
+        );
+        let empty = heap.alloc(AllocStruct::EMPTY);
+        assert_eq!(
+            "Cannot patch struct field `x`: no such field",
+            patch
+                .apply(&heap, StructRef::from_value(empty).unwrap())
+                .unwrap_err()
+                .to_string()
+        );
+        let remove = StructRef::from_value(a)
+            .unwrap()
+            .diff_patch(StructRef::from_value(empty).unwrap())
+            .unwrap();
+        assert_eq!(
+            "Cannot remove struct field `x`: no such field",
+            remove
+                .apply(&heap, StructRef::from_value(empty).unwrap())
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
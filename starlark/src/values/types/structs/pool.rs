@@ -0,0 +1,375 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Content-addressed pool of frozen structs, see [`StructPool`].
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use dupe::Dupe;
+use either::Either;
+use starlark_map::small_map::SmallMap;
+use starlark_map::Hashed;
+use starlark_map::StarlarkHasher;
+
+use crate::values::layout::heap::repr::AValueHeader;
+use crate::values::layout::heap::repr::AValueRepr;
+use crate::values::layout::heap::repr::ForwardPtr;
+use crate::values::layout::pointer::RawPointer;
+use crate::values::structs::value::FrozenStruct;
+use crate::values::structs::value::Struct;
+use crate::values::types::float::StarlarkFloat;
+use crate::values::Freezer;
+use crate::values::FrozenHeapRef;
+use crate::values::FrozenValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// Pool of frozen structs shared between module freezes, to deduplicate equal structs.
+///
+/// When a module is frozen with
+/// [`Module::freeze_with_struct_pool`](crate::environment::Module::freeze_with_struct_pool),
+/// each struct is looked up in the pool by content hash, and if a struct with
+/// the same fields in the same order and identical values was frozen before,
+/// that struct is reused instead of allocating a new one.
+///
+/// Only structs made of strings, numbers, booleans, `None` and other such structs
+/// are pooled, other structs are frozen as usual.
+///
+/// The pool keeps every frozen heap it handed out structs from alive, even if all
+/// modules frozen into that heap are dropped, so memory grows with every module frozen
+/// with a new distinct struct. Nothing is evicted automatically: drop the pool, or call
+/// [`clear`](StructPool::clear), to release the heaps. Modules reusing a pooled struct
+/// keep its heap alive regardless.
+/// The pool is cheap to clone, clones share the pooled structs, and it can be used
+/// from multiple threads concurrently.
+#[derive(Clone, Dupe, Default)]
+pub struct StructPool(Arc<Mutex<StructPoolData>>);
+
+#[derive(Default)]
+struct StructPoolData {
+    structs: HashMap<u64, Vec<(FrozenValue, FrozenHeapRef)>>,
+}
+
+impl StructPool {
+    /// Create an empty pool.
+    pub fn new() -> StructPool {
+        StructPool::default()
+    }
+
+    /// Number of structs in the pool.
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().structs.values().map(Vec::len).sum()
+    }
+
+    /// Is the pool empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all structs from the pool, releasing the frozen heaps not otherwise referenced.
+    ///
+    /// Modules frozen later do not share structs with modules frozen before.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().structs.clear();
+    }
+}
+
+/// [`StructPool`] being used by a [`Freezer`].
+pub(crate) struct StructPoolFreezer {
+    pool: StructPool,
+    /// Structs allocated by this freezer by content hash,
+    /// added to the pool once the heap is frozen.
+    added: RefCell<HashMap<u64, Vec<FrozenValue>>>,
+    /// Memoized [`is_poolable`] results for structs, so nested structs are walked once.
+    poolable: RefCell<HashMap<RawPointer, bool>>,
+}
+
+impl StructPoolFreezer {
+    pub(crate) fn new(pool: StructPool) -> StructPoolFreezer {
+        StructPoolFreezer {
+            pool,
+            added: RefCell::new(HashMap::new()),
+            poolable: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Add the structs allocated while freezing to the pool.
+    pub(crate) fn commit(self, heap: &FrozenHeapRef) {
+        let mut data = self.pool.0.lock().unwrap();
+        for (hash, values) in self.added.into_inner() {
+            data.structs
+                .entry(hash)
+                .or_default()
+                .extend(values.into_iter().map(|value| (value, heap.dupe())));
+        }
+    }
+
+    /// Freeze the value if it is a poolable struct, reusing an equal pooled struct if possible.
+    ///
+    /// Returns `None` if the value is not a poolable struct.
+    ///
+    /// `header` is a raw pointer because the value is overwritten with a forward:
+    /// writing through a shared reference argument is undefined behavior,
+    /// and release builds may drop the write, so the struct was dropped twice.
+    pub(crate) unsafe fn freeze(
+        &self,
+        header: *mut AValueHeader,
+        freezer: &Freezer,
+    ) -> anyhow::Result<Option<FrozenValue>> {
+        let Some(s) = (*header).unpack().downcast_ref::<Struct>() else {
+            return Ok(None);
+        };
+        // Values other than leaves and structs may reference this struct back,
+        // in which case this struct must be forwarded before its fields are frozen.
+        // Shared fields are read from another struct, which may be frozen already.
+        if s.is_shared() || !s.iter().all(|(_, v)| self.is_poolable(v)) {
+            return Ok(None);
+        }
+
+        let mut fields = SmallMap::with_capacity(s.len());
+        for (k, v) in s.iter_hashed() {
+            let k = Hashed::new_unchecked(k.hash(), k.key().freeze(freezer)?);
+            fields.insert_hashed_unique_unchecked(k, freezer.freeze(v)?);
+        }
+        let frozen = FrozenStruct::new(fields);
+        let mut hasher = StarlarkHasher::new();
+        frozen
+            .write_hash_excluding(&[], &mut hasher)
+            .map_err(|e| e.into_anyhow())?;
+        let hash = hasher.finish();
+
+        let fv = match self.lookup(hash, &frozen, freezer) {
+            Some(fv) => fv,
+            None => {
+                let fv = freezer.frozen_heap().alloc_simple(frozen);
+                self.added.borrow_mut().entry(hash).or_default().push(fv);
+                fv
+            }
+        };
+        drop(AValueHeader::overwrite_with_forward::<Struct>(
+            header as *mut AValueRepr<Struct>,
+            ForwardPtr::new(fv.0.raw().ptr_value()),
+        ));
+        Ok(Some(fv))
+    }
+
+    fn lookup(&self, hash: u64, frozen: &FrozenStruct, freezer: &Freezer) -> Option<FrozenValue> {
+        if let Some(fv) = self.added.borrow().get(&hash).and_then(|added| {
+            added
+                .iter()
+                .find(|fv| same_fields(frozen, downcast_pooled(**fv)))
+        }) {
+            return Some(*fv);
+        }
+        let data = self.pool.0.lock().unwrap();
+        let (fv, heap) = data
+            .structs
+            .get(&hash)?
+            .iter()
+            .find(|(fv, _)| same_fields(frozen, downcast_pooled(*fv)))?;
+        freezer.frozen_heap().add_reference(heap);
+        Some(*fv)
+    }
+
+    /// Can the value be frozen before the struct which contains it, and compared with [`identical`].
+    fn is_poolable(&self, v: Value) -> bool {
+        let v = match v.unpack_frozen() {
+            Some(v) => v.to_value(),
+            None => match v.0.unpack_ptr().unwrap().unpack_overwrite() {
+                // Already frozen, but possibly not yet filled if it is being frozen right now,
+                // in which case it is a black hole and not any of the types below.
+                Either::Left(forward) => unsafe { forward.unpack_frozen_value() }.to_value(),
+                Either::Right(_) => v,
+            },
+        };
+        if v.is_none() || v.unpack_bool().is_some() || v.unpack_str().is_some() {
+            return true;
+        }
+        if v.unpack_num_ref().is_some() {
+            return true;
+        }
+        let Some(s) = Struct::from_value(v) else {
+            return false;
+        };
+        if let Some(poolable) = self.poolable.borrow().get(&v.ptr_value()) {
+            return *poolable;
+        }
+        let poolable = !s.is_shared() && s.iter().all(|(_, v)| self.is_poolable(v));
+        self.poolable.borrow_mut().insert(v.ptr_value(), poolable);
+        poolable
+    }
+}
+
+fn same_fields(frozen: &FrozenStruct, pooled: &FrozenStruct) -> bool {
+    frozen.len() == pooled.len()
+        && frozen
+            .iter_frozen()
+            .zip(pooled.iter_frozen())
+            .all(|((k1, v1), (k2, v2))| k1 == k2 && identical(v1, v2))
+}
+
+/// Stricter than equality: `1 == 1.0` and `0.0 == -0.0`, but they must not be deduplicated.
+fn identical(x: FrozenValue, y: FrozenValue) -> bool {
+    if x.to_value().ptr_eq(y.to_value()) {
+        return true;
+    }
+    if x.to_value().get_type() != y.to_value().get_type() {
+        return false;
+    }
+    if let (Some(x), Some(y)) = (downcast_struct(x), downcast_struct(y)) {
+        return same_fields(x, y);
+    }
+    if let (Some(x), Some(y)) = (
+        x.downcast_ref::<StarlarkFloat>(),
+        y.downcast_ref::<StarlarkFloat>(),
+    ) {
+        return x.0.to_bits() == y.0.to_bits();
+    }
+    x.to_value().equals(y.to_value()).unwrap_or(false)
+}
+
+fn downcast_struct(x: FrozenValue) -> Option<&'static FrozenStruct> {
+    x.downcast_ref::<FrozenStruct>()
+}
+
+fn downcast_pooled(x: FrozenValue) -> &'static FrozenStruct {
+    downcast_struct(x).expect("pool only contains structs")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::FrozenModule;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::list::ListRef;
+    use crate::values::structs::StructPool;
+    use crate::values::FrozenValue;
+
+    fn eval(code: &str, pool: Option<&StructPool>) -> FrozenModule {
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse("x.star", code.to_owned(), &Dialect::Extended).unwrap();
+            eval.eval_module(ast, &Globals::extended_internal())
+                .unwrap();
+        }
+        match pool {
+            Some(pool) => module.freeze_with_struct_pool(pool).unwrap(),
+            None => module.freeze().unwrap(),
+        }
+    }
+
+    fn get(module: &FrozenModule, name: &str) -> FrozenValue {
+        unsafe { module.get(name).unwrap().unchecked_frozen_value() }
+    }
+
+    #[test]
+    fn test_struct_pool_reuses_equal_structs() {
+        let pool = StructPool::new();
+        let code = "x = struct(a = 1, b = struct(c = 'x' * 10, d = [1][0] + 0.5))";
+        let m1 = eval(code, Some(&pool));
+        let m2 = eval(code, Some(&pool));
+        assert!(get(&m1, "x").to_value().ptr_eq(get(&m2, "x").to_value()));
+        assert_eq!(2, pool.len());
+        drop(m1);
+        assert_eq!(
+            "struct(a=1, b=struct(c=\"xxxxxxxxxx\", d=1.5))",
+            get(&m2, "x").to_value().to_string()
+        );
+    }
+
+    #[test]
+    fn test_struct_pool_clear() {
+        let pool = StructPool::new();
+        let code = "x = struct(a = 'x' * 10)";
+        let m1 = eval(code, Some(&pool));
+        assert_eq!(1, pool.len());
+        pool.clear();
+        assert!(pool.is_empty());
+        let m2 = eval(code, Some(&pool));
+        assert!(!get(&m1, "x").to_value().ptr_eq(get(&m2, "x").to_value()));
+        assert_eq!(1, pool.len());
+    }
+
+    #[test]
+    fn test_struct_pool_distinguishes_identical_values() {
+        let pool = StructPool::new();
+        let m = eval(
+            r#"
+x = [struct(a = 1), struct(a = 1.0), struct(a = -0.0), struct(a = 0.0), struct(a = 1, b = 2), struct(b = 2, a = 1)]
+y = struct(a = 1)
+"#,
+            Some(&pool),
+        );
+        assert_eq!(
+            "[struct(a=1), struct(a=1.0), struct(a=-0.0), struct(a=0.0), struct(a=1, b=2), struct(b=2, a=1)]",
+            get(&m, "x").to_value().to_string()
+        );
+        assert_eq!(6, pool.len());
+        let x = ListRef::from_value(get(&m, "x").to_value()).unwrap();
+        assert!(x[0].ptr_eq(get(&m, "y").to_value()));
+    }
+
+    #[test]
+    fn test_struct_pool_skips_structs_with_mutable_fields() {
+        let pool = StructPool::new();
+        let m = eval(
+            r#"
+l = []
+s = struct(l = l, t = struct(a = "a" + "b"))
+l.append(s)
+"#,
+            Some(&pool),
+        );
+        assert_eq!(
+            "struct(l=[struct(...)], t=struct(a=\"ab\"))",
+            get(&m, "s").to_value().to_string()
+        );
+        // Only the inner struct.
+        assert_eq!(1, pool.len());
+    }
+
+    #[test]
+    fn test_struct_pool_reduces_memory() {
+        let code = r#"
+configs = [
+    struct(name = "target" + str(i % 4), deps = struct(a = 1, b = 2.5, c = None), tags = struct(x = "x" * 100))
+    for i in range(100)
+]
+"#;
+        let without_pool: usize = (0..10)
+            .map(|_| eval(code, None).frozen_heap().allocated_bytes())
+            .sum();
+        let pool = StructPool::new();
+        let modules: Vec<FrozenModule> = (0..10).map(|_| eval(code, Some(&pool))).collect();
+        let with_pool: usize = modules
+            .iter()
+            .map(|m| m.frozen_heap().allocated_bytes())
+            .sum();
+        assert!(
+            with_pool * 2 < without_pool,
+            "with pool: {with_pool}, without pool: {without_pool}"
+        );
+    }
+}
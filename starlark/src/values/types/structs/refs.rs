@@ -15,30 +15,86 @@
  * limitations under the License.
  */
 
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::Hasher;
+use std::io::Write;
+
 use dupe::Dupe;
+use serde::Serialize;
+use starlark_map::small_map::SmallMap;
+use starlark_map::Hashed;
+use starlark_map::StarlarkHasher;
 
 use crate::typing::Ty;
+use crate::values::dict::DictRef;
+use crate::values::float::StarlarkFloat;
+use crate::values::list::ListRef;
+use crate::values::stack_guard;
 use crate::values::starlark_type_id::StarlarkTypeId;
+use crate::values::structs::value::struct_methods;
 use crate::values::structs::value::FrozenStruct;
 use crate::values::structs::value::Struct;
+use crate::values::structs::StructPatch;
+use crate::values::tuple::TupleRef;
 use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::types::int_or_big::StarlarkIntRef;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
+use crate::values::Heap;
 use crate::values::StringValue;
 use crate::values::UnpackValue;
 use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(Debug, thiserror::Error)]
+enum FieldTypeError {
+    #[error("Expected `{1}` for struct field `{0}`, got value of type `{2}`")]
+    WrongType(String, Ty, &'static str),
+}
+
+#[derive(Debug, thiserror::Error)]
+enum KeyTransformError {
+    #[error("Struct fields `{0}` and `{1}` both transform to JSON key `{2}`")]
+    Collision(String, String, String),
+}
+
+#[derive(Debug, thiserror::Error)]
+enum FlatPropertiesError {
+    #[error("Cannot convert value of type `{1}` at `{0}` to a property")]
+    UnsupportedValue(String, &'static str),
+}
+
+/// Format of lines written by [`StructRef::write_jsonl`].
+#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq)]
+pub enum StructJsonLinesFormat {
+    /// Each line is a field value, field names are not written.
+    Values,
+    /// Each line is an object `{"key": <field name>, "value": <field value>}`.
+    Entries,
+}
+
+/// What an attribute name resolves to on a struct, see [`StructRef::resolve_attr_kind`].
+#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq)]
+pub enum StructAttrKind {
+    /// A user-defined field.
+    Field,
+    /// A builtin struct method like `to_json`.
+    Method,
+}
 
 /// Reference to a struct allocated on the heap.
 ///
 /// Struct implementation (for example, memory layout) may change,
 /// this type provides implementation agnostics API to it.
 #[derive(Debug, Clone, Copy, Dupe)]
-pub struct StructRef<'v>(&'v Struct<'v>);
+pub struct StructRef<'v>(pub(crate) &'v Struct<'v>, pub(crate) Value<'v>);
 
 impl<'v> StructRef<'v> {
     /// Downcast a value to a struct reference.
     pub fn from_value(value: Value<'v>) -> Option<StructRef<'v>> {
-        Struct::from_value(value).map(StructRef)
+        Struct::from_value(value).map(|s| StructRef(s, value))
     }
 
     #[inline]
@@ -51,6 +107,411 @@ impl<'v> StructRef<'v> {
     pub fn iter(&self) -> impl ExactSizeIterator<Item = (StringValue<'v>, Value<'v>)> + '_ {
         self.0.iter()
     }
+
+    /// The field at position `i` in field order, or `None` if `i` is out of range.
+    pub fn nth_field(&self, i: usize) -> Option<(&'v str, Value<'v>)> {
+        let (k, v) = self.0.get_index(i)?;
+        Some((k.as_str(), v))
+    }
+
+    /// Position of the field `name` in field order, or `None` if there is no such field.
+    ///
+    /// Consistent with [`nth_field`](StructRef::nth_field).
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.0.get_index_of_hashed(Hashed::new(name))
+    }
+
+    /// Check whether `name` resolves to a field or to a builtin method,
+    /// or `None` if the attribute does not exist.
+    ///
+    /// Fields shadow methods with the same name, matching `getattr` semantics.
+    pub fn resolve_attr_kind(&self, name: &str) -> Option<StructAttrKind> {
+        if self.0.get(name).is_some() {
+            Some(StructAttrKind::Field)
+        } else if struct_methods().is_some_and(|methods| methods.get(name).is_some()) {
+            Some(StructAttrKind::Method)
+        } else {
+            None
+        }
+    }
+
+    /// Get a field by name given as a string value, for dynamic lookups.
+    ///
+    /// Returns `None` if there is no such field, and an error if `key` is not a string.
+    pub fn get_attr_value(&self, key: Value<'v>) -> anyhow::Result<Option<Value<'v>>> {
+        let key = key.unpack_or_err::<&str>()?;
+        Ok(self.0.get(key))
+    }
+
+    /// Get a field by name, unpacked to `V`.
+    ///
+    /// Returns `None` if there is no such field, and an error naming the field and
+    /// the expected type if the field value cannot be unpacked to `V`.
+    pub fn get_field_typed<V: UnpackValue<'v>>(&self, name: &str) -> anyhow::Result<Option<V>> {
+        let Some(value) = self.0.get(name) else {
+            return Ok(None);
+        };
+        Ok(Some(Self::unpack_field(name, value)?))
+    }
+
+    pub(crate) fn unpack_field<V: UnpackValue<'v>>(
+        name: &str,
+        value: Value<'v>,
+    ) -> anyhow::Result<V> {
+        match V::unpack_value(value) {
+            Some(v) => Ok(v),
+            None => Err(FieldTypeError::WrongType(
+                name.to_owned(),
+                V::starlark_type_repr(),
+                value.get_type(),
+            )
+            .into()),
+        }
+    }
+
+    /// Get a field by name, unpacked to `V`, or `default` if there is no such field.
+    ///
+    /// Like [`get_field_typed`](StructRef::get_field_typed), a field which is present
+    /// but cannot be unpacked to `V` is an error, rather than replaced by the default.
+    pub fn get_or<V: UnpackValue<'v>>(&self, name: &str, default: V) -> anyhow::Result<V> {
+        Ok(self.get_field_typed(name)?.unwrap_or(default))
+    }
+
+    /// Hash the struct contents like `hash()` would, but ignoring the fields named in `skip`.
+    ///
+    /// Like struct hashing, the result does not depend on field order. Useful for
+    /// cache keys which should not include volatile fields like timestamps.
+    pub fn content_hash_excluding(
+        &self,
+        skip: &[&str],
+        hasher: &mut StarlarkHasher,
+    ) -> crate::Result<()> {
+        self.0.write_hash_excluding(skip, hasher)
+    }
+
+    /// Short deterministic fingerprint of the struct contents: the struct hash
+    /// as 16 lowercase hex digits.
+    ///
+    /// Does not depend on field order. Fails if any field value is not hashable.
+    pub fn fingerprint(&self) -> crate::Result<String> {
+        let mut hasher = StarlarkHasher::new();
+        self.0.write_hash_excluding(&[], &mut hasher)?;
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+
+    /// All struct fields as a map from field name to value, in field order.
+    pub fn attributes(&self) -> SmallMap<&'v str, Value<'v>> {
+        self.0
+            .iter_hashed()
+            .map(|(k, v)| (Hashed::new_unchecked(k.hash(), k.key().as_str()), v))
+            .collect()
+    }
+
+    /// Fields sorted by value in ascending order of [`Value::compare`].
+    ///
+    /// The sort is stable, fields with equal values keep their struct order.
+    /// Fails if the values are not mutually comparable (for example, an `int` and a `str`),
+    /// use [`iter_sorted_by_value_by`](StructRef::iter_sorted_by_value_by) with
+    /// [`Value::total_cmp`] to order values of any types.
+    pub fn iter_sorted_by_value(&self) -> crate::Result<Vec<(&'v str, Value<'v>)>> {
+        let mut compare_ok = Ok(());
+        let fields = self.iter_sorted_by_value_by(|x, y| match x.compare(y) {
+            Ok(r) => r,
+            Err(e) => {
+                compare_ok = Err(e);
+                Ordering::Equal // does not matter
+            }
+        });
+        compare_ok?;
+        Ok(fields)
+    }
+
+    /// Fields sorted by value with the given comparator. The sort is stable.
+    pub fn iter_sorted_by_value_by(
+        &self,
+        mut cmp: impl FnMut(Value<'v>, Value<'v>) -> Ordering,
+    ) -> Vec<(&'v str, Value<'v>)> {
+        let mut fields: Vec<(&'v str, Value<'v>)> =
+            self.0.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        fields.sort_by(|(_, x), (_, y)| cmp(*x, *y));
+        fields
+    }
+
+    /// Split the struct into two new structs: fields for which `pred` returns `true`,
+    /// and all the other fields.
+    ///
+    /// Field order is preserved in both halves.
+    pub fn partition(
+        &self,
+        heap: &'v Heap,
+        mut pred: impl FnMut(&str, Value<'v>) -> bool,
+    ) -> (Value<'v>, Value<'v>) {
+        let mut matching = SmallMap::new();
+        let mut rest = SmallMap::new();
+        for (k, v) in self.0.iter_hashed() {
+            if pred(k.key().as_str(), v) {
+                matching.insert_hashed_unique_unchecked(k, v);
+            } else {
+                rest.insert_hashed_unique_unchecked(k, v);
+            }
+        }
+        (
+            heap.alloc(Struct::new(matching)),
+            heap.alloc(Struct::new(rest)),
+        )
+    }
+
+    /// Functional update: a new struct with field `name` set to `value`.
+    ///
+    /// An existing field keeps its position, a new field is appended.
+    /// `self` is not modified. The new struct of a large struct shares the fields
+    /// of `self` and only stores the changed ones, so repeated updates of a large struct
+    /// do not copy all the fields each time.
+    pub fn with_field(&self, heap: &'v Heap, name: &str, value: Value<'v>) -> Value<'v> {
+        let name = heap.alloc_str(name).get_hashed();
+        heap.alloc(self.0.with_field(self.1, name, value))
+    }
+
+    /// Copy of the struct with values of fields named in `secret_fields`
+    /// replaced with the `placeholder` string, for example before logging.
+    ///
+    /// Field order is preserved. If `recursive`, fields of nested structs
+    /// (but not structs inside lists or dicts) are redacted too.
+    pub fn redact(
+        &self,
+        heap: &'v Heap,
+        secret_fields: &[&str],
+        placeholder: &str,
+        recursive: bool,
+    ) -> Value<'v> {
+        let placeholder = heap.alloc_str(placeholder).to_value();
+        self.redact_impl(heap, secret_fields, placeholder, recursive)
+    }
+
+    fn redact_impl(
+        &self,
+        heap: &'v Heap,
+        secret_fields: &[&str],
+        placeholder: Value<'v>,
+        recursive: bool,
+    ) -> Value<'v> {
+        let mut fields = SmallMap::with_capacity(self.0.len());
+        for (k, v) in self.0.iter_hashed() {
+            let v = if secret_fields.contains(&k.key().as_str()) {
+                placeholder
+            } else {
+                match StructRef::from_value(v) {
+                    Some(s) if recursive => {
+                        s.redact_impl(heap, secret_fields, placeholder, recursive)
+                    }
+                    _ => v,
+                }
+            };
+            fields.insert_hashed_unique_unchecked(k, v);
+        }
+        heap.alloc(Struct::new(fields))
+    }
+
+    /// Union of fields of two structs, failing if any field is present in both
+    /// structs with unequal values.
+    ///
+    /// Fields of `self` come first, followed by fields only present in `other`.
+    /// On conflict, the inner result is the list of conflicting field names
+    /// in the order they appear in `other`.
+    /// The outer error is returned if comparison of field values fails.
+    pub fn merge_strict(
+        &self,
+        other: StructRef<'v>,
+        heap: &'v Heap,
+    ) -> crate::Result<Result<Value<'v>, Vec<String>>> {
+        let mut fields = self.0.fields_map().into_owned();
+        let mut conflicts = Vec::new();
+        for (k, v) in other.0.iter_hashed() {
+            match fields.get_hashed(k.as_ref()) {
+                Some(prev) => {
+                    if !prev.equals(v)? {
+                        conflicts.push(k.key().as_str().to_owned());
+                    }
+                }
+                None => {
+                    fields.insert_hashed_unique_unchecked(k, v);
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            Ok(Ok(heap.alloc(Struct::new(fields))))
+        } else {
+            Ok(Err(conflicts))
+        }
+    }
+
+    /// Compute the patch which turns `self` into `new`, see [`StructPatch`].
+    ///
+    /// The error is returned if comparison of field values fails.
+    pub fn diff_patch(&self, new: StructRef<'v>) -> crate::Result<StructPatch<'v>> {
+        StructPatch::diff(*self, new)
+    }
+
+    /// Names of fields present in exactly one of the two structs.
+    ///
+    /// Fields of `self` missing from `other` come first, followed by fields
+    /// of `other` missing from `self`, each in field order. Field values are ignored,
+    /// so the result is empty if both structs have the same shape.
+    pub fn key_symmetric_difference(&self, other: StructRef<'v>) -> Vec<String> {
+        let only_in = |a: &StructRef<'v>, b: &StructRef<'v>| {
+            a.0.iter()
+                .map(|(k, _)| k)
+                .filter(|k| b.0.get(k.as_str()).is_none())
+                .map(|k| k.as_str().to_owned())
+                .collect::<Vec<_>>()
+        };
+        let mut keys = only_in(self, &other);
+        keys.extend(only_in(&other, self));
+        keys
+    }
+
+    /// Flatten the struct into `key=value` properties.
+    ///
+    /// Nested structs produce dotted keys (`server.port`), list and tuple elements
+    /// produce indexed keys (`hosts.0`, `hosts.1`). Leaves are stringified:
+    /// strings as is, and `int`, `float` and `bool` values as by `str()`.
+    /// Fails on any other leaf value, including `None` and dicts,
+    /// and on cyclic lists.
+    pub fn to_flat_properties(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        fn flatten(
+            key: String,
+            value: Value,
+            properties: &mut BTreeMap<String, String>,
+        ) -> anyhow::Result<()> {
+            let _guard = stack_guard::stack_guard()?;
+            let child = |name: &dyn fmt::Display| format!("{}.{}", key, name);
+            if let Some(s) = StructRef::from_value(value) {
+                for (k, v) in s.iter() {
+                    flatten(child(&k.as_str()), v, properties)?;
+                }
+            } else if let Some(xs) = ListRef::from_value(value) {
+                for (i, x) in xs.content().iter().enumerate() {
+                    flatten(child(&i), *x, properties)?;
+                }
+            } else if let Some(xs) = TupleRef::from_value(value) {
+                for (i, x) in xs.content().iter().enumerate() {
+                    flatten(child(&i), *x, properties)?;
+                }
+            } else if let Some(s) = value.unpack_str() {
+                properties.insert(key, s.to_owned());
+            } else if value.unpack_bool().is_some()
+                || StarlarkIntRef::unpack_value(value).is_some()
+                || value.downcast_ref::<StarlarkFloat>().is_some()
+            {
+                properties.insert(key, value.to_str());
+            } else {
+                return Err(FlatPropertiesError::UnsupportedValue(key, value.get_type()).into());
+            }
+            Ok(())
+        }
+
+        let mut properties = BTreeMap::new();
+        for (k, v) in self.iter() {
+            flatten(k.as_str().to_owned(), v, &mut properties)?;
+        }
+        Ok(properties)
+    }
+
+    /// Write struct fields as [JSON Lines](https://jsonlines.org/),
+    /// one line per field in field order, each line terminated with `\n`.
+    pub fn write_jsonl(
+        &self,
+        mut w: impl Write,
+        format: StructJsonLinesFormat,
+    ) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Entry<'a, 'v> {
+            key: &'a str,
+            value: Value<'v>,
+        }
+
+        for (k, v) in self.0.iter() {
+            match format {
+                StructJsonLinesFormat::Values => serde_json::to_writer(&mut w, &v)?,
+                StructJsonLinesFormat::Entries => serde_json::to_writer(
+                    &mut w,
+                    &Entry {
+                        key: k.as_str(),
+                        value: v,
+                    },
+                )?,
+            }
+            w.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Convert the struct to JSON like `to_json`, passing each field name through `f`.
+    ///
+    /// Nested structs, including structs in lists, tuples and dict values, are
+    /// transformed too. Dict keys are not transformed.
+    /// Fails if two fields of one struct transform to the same key,
+    /// and on cyclic lists or dicts.
+    pub fn to_json_with_key_transform(&self, f: impl Fn(&str) -> String) -> anyhow::Result<String> {
+        struct Transform<'a, 'v> {
+            value: Value<'v>,
+            f: &'a dyn Fn(&str) -> String,
+        }
+
+        struct TransformStruct<'a, 'v> {
+            s: StructRef<'v>,
+            f: &'a dyn Fn(&str) -> String,
+        }
+
+        impl<'a, 'v> Serialize for Transform<'a, 'v> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let _guard = stack_guard::stack_guard().map_err(serde::ser::Error::custom)?;
+                let nested = |value| Transform { value, f: self.f };
+                if let Some(s) = StructRef::from_value(self.value) {
+                    TransformStruct { s, f: self.f }.serialize(serializer)
+                } else if let Some(xs) = ListRef::from_value(self.value) {
+                    serializer.collect_seq(xs.content().iter().map(|x| nested(*x)))
+                } else if let Some(xs) = TupleRef::from_value(self.value) {
+                    serializer.collect_seq(xs.content().iter().map(|x| nested(*x)))
+                } else if let Some(xs) = DictRef::from_value(self.value) {
+                    serializer.collect_map(xs.iter().map(|(k, v)| (k, nested(v))))
+                } else {
+                    self.value.serialize(serializer)
+                }
+            }
+        }
+
+        impl<'a, 'v> Serialize for TransformStruct<'a, 'v> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let fields = self.s.0;
+                let mut keys: SmallMap<String, &str> = SmallMap::with_capacity(fields.len());
+                for (k, _) in fields.iter() {
+                    let key = (self.f)(k.as_str());
+                    if let Some(prev) = keys.insert(key.clone(), k.as_str()) {
+                        return Err(serde::ser::Error::custom(KeyTransformError::Collision(
+                            prev.to_owned(),
+                            k.as_str().to_owned(),
+                            key,
+                        )));
+                    }
+                }
+                serializer.collect_map(keys.into_keys().zip(fields.iter().map(|(_, v)| {
+                    Transform {
+                        value: v,
+                        f: self.f,
+                    }
+                })))
+            }
+        }
+
+        Ok(serde_json::to_string(&TransformStruct { s: *self, f: &f })?)
+    }
 }
 
 impl<'v> StarlarkTypeRepr for StructRef<'v> {
@@ -68,6 +529,10 @@ impl<'v> UnpackValue<'v> for StructRef<'v> {
 }
 
 /// Reference to the frozen struct.
+///
+/// Frozen structs are deeply immutable: all the field values are frozen too,
+/// so neither the struct nor any value reachable from it can be mutated.
+/// See also [`Value::is_deeply_frozen`](crate::values::Value::is_deeply_frozen).
 #[derive(Debug)]
 pub struct FrozenStructRef<'f>(pub(crate) &'f FrozenStruct);
 
@@ -84,3 +549,582 @@ impl<'f> FrozenStructRef<'f> {
             .map(|f| FrozenStructRef(f.as_ref()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use starlark_derive::starlark_module;
+    use starlark_map::small_map::SmallMap;
+    use starlark_map::StarlarkHasher;
+
+    use crate as starlark;
+    use crate::assert;
+    use crate::environment::GlobalsBuilder;
+    use crate::environment::Module;
+    use crate::values::dict::AllocDict;
+    use crate::values::structs::AllocStruct;
+    use crate::values::structs::StructAttrKind;
+    use crate::values::structs::StructJsonLinesFormat;
+    use crate::values::structs::StructRef;
+    use crate::values::Heap;
+    use crate::values::Value;
+    use crate::values::ValueLike;
+
+    #[test]
+    fn test_attributes() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("b", 1), ("a", 2), ("c", 3)]));
+        let s = StructRef::from_value(v).unwrap();
+        let attributes = s.attributes();
+        // `Value::dir_attr` returns sorted names, so compare as unordered maps.
+        let expected: SmallMap<String, Value> = v
+            .dir_attr()
+            .into_iter()
+            .filter(|name| s.resolve_attr_kind(name) == Some(StructAttrKind::Field))
+            .map(|name| {
+                let value = v.get_attr(&name, &heap).unwrap().unwrap();
+                (name, value)
+            })
+            .collect();
+        assert_eq!(
+            expected,
+            attributes
+                .iter()
+                .map(|(k, v)| ((*k).to_owned(), *v))
+                .collect::<SmallMap<_, _>>()
+        );
+        assert_eq!(
+            vec!["b", "a", "c"],
+            attributes.keys().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(&heap.alloc(2)), attributes.get("a"));
+    }
+
+    #[test]
+    fn test_partition() {
+        let heap = Heap::new();
+        let s = heap.alloc(AllocStruct([("a", 1), ("_b", 2), ("c", 3), ("_d", 4)]));
+        let s = StructRef::from_value(s).unwrap();
+        let (private, public) = s.partition(&heap, |k, _| k.starts_with('_'));
+        assert_eq!("struct(_b=2, _d=4)", private.to_repr());
+        assert_eq!("struct(a=1, c=3)", public.to_repr());
+    }
+
+    #[test]
+    fn test_key_symmetric_difference() {
+        let heap = Heap::new();
+        fn diff<'v>(a: Value<'v>, b: Value<'v>) -> Vec<String> {
+            StructRef::from_value(a)
+                .unwrap()
+                .key_symmetric_difference(StructRef::from_value(b).unwrap())
+        }
+        let ab = heap.alloc(AllocStruct([("a", 1), ("b", 2)]));
+        let ba = heap.alloc(AllocStruct([("b", 3), ("a", 4)]));
+        let cd = heap.alloc(AllocStruct([("c", 1), ("d", 2)]));
+        let bc = heap.alloc(AllocStruct([("c", 1), ("b", 2)]));
+        assert!(diff(ab, ba).is_empty());
+        assert_eq!(vec!["a", "b", "c", "d"], diff(ab, cd));
+        assert_eq!(vec!["a", "c"], diff(ab, bc));
+        assert_eq!(vec!["c", "a"], diff(bc, ab));
+    }
+
+    #[test]
+    fn test_nth_field() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("c", 3), ("a", 1), ("b", 2)]));
+        let s = StructRef::from_value(v).unwrap();
+        let fields: Vec<(&str, i32)> = (0..)
+            .map_while(|i| s.nth_field(i))
+            .map(|(k, v)| (k, v.unpack_i32().unwrap()))
+            .collect();
+        assert_eq!(vec![("c", 3), ("a", 1), ("b", 2)], fields);
+        for (i, (name, _)) in fields.iter().enumerate() {
+            assert_eq!(Some(i), s.field_index(name));
+        }
+        assert_eq!(None, s.nth_field(3));
+        assert_eq!(None, s.field_index("d"));
+    }
+
+    #[test]
+    fn test_get_field_typed() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([
+            ("port", heap.alloc(80)),
+            ("host", heap.alloc("h")),
+        ]));
+        let s = StructRef::from_value(v).unwrap();
+        assert_eq!(Some(80), s.get_field_typed::<u32>("port").unwrap());
+        assert_eq!(Some("h"), s.get_field_typed::<&str>("host").unwrap());
+        assert_eq!(None, s.get_field_typed::<u32>("missing").unwrap());
+        assert_eq!(
+            "Expected `int` for struct field `host`, got value of type `string`",
+            s.get_field_typed::<u32>("host").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_get_or() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([
+            ("port", heap.alloc(80)),
+            ("host", heap.alloc("h")),
+        ]));
+        let s = StructRef::from_value(v).unwrap();
+        assert_eq!(80, s.get_or::<u32>("port", 8080).unwrap());
+        assert_eq!(8080, s.get_or::<u32>("missing", 8080).unwrap());
+        assert_eq!(
+            "Expected `int` for struct field `host`, got value of type `string`",
+            s.get_or::<u32>("host", 8080).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_with_field() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("a", 1), ("b", 2)]));
+        let s = StructRef::from_value(v).unwrap();
+        assert_eq!(
+            "struct(a=3, b=2)",
+            s.with_field(&heap, "a", heap.alloc(3)).to_repr()
+        );
+        assert_eq!(
+            "struct(a=1, b=2, c=4)",
+            s.with_field(&heap, "c", heap.alloc(4)).to_repr()
+        );
+        assert_eq!("struct(a=1, b=2)", v.to_repr());
+    }
+
+    #[test]
+    fn test_with_field_shares_fields() {
+        let module = Module::new();
+        let heap = module.heap();
+        let mut expected: Vec<(String, i32)> = (0..100).map(|i| (format!("f{}", i), i)).collect();
+        let mut v = heap.alloc(AllocStruct(expected.clone()));
+        let mut shared = 0;
+        for step in 0..60 {
+            // Replace existing fields, including appended ones, and append new fields.
+            let name = match step % 3 {
+                0 => format!("f{}", step * 7 % 100),
+                1 => format!("g{}", step / 3 % 5),
+                _ => format!("g{}", step),
+            };
+            match expected.iter_mut().find(|(k, _)| *k == name) {
+                Some(field) => field.1 = step,
+                None => expected.push((name.clone(), step)),
+            }
+            v = StructRef::from_value(v)
+                .unwrap()
+                .with_field(heap, &name, heap.alloc(step));
+            module.set(&format!("s{}", step), v);
+
+            let s = StructRef::from_value(v).unwrap();
+            if s.0.is_shared() {
+                shared += 1;
+            }
+            let flat = heap.alloc(AllocStruct(expected.clone()));
+            assert_eq!(flat.to_repr(), v.to_repr());
+            assert!(flat.equals(v).unwrap());
+            assert!(v.equals(flat).unwrap());
+            assert_eq!(
+                flat.get_hashed().unwrap().hash(),
+                v.get_hashed().unwrap().hash()
+            );
+            assert_eq!(expected.len(), s.iter().len());
+            for (i, (k, x)) in expected.iter().enumerate() {
+                assert_eq!(Some(i), s.field_index(k));
+                assert_eq!(Some((k.as_str(), heap.alloc(*x))), s.nth_field(i));
+                assert_eq!(Some(*x), v.get_attr(k, heap).unwrap().unwrap().unpack_i32());
+            }
+            assert_eq!(None, s.nth_field(expected.len()));
+            assert_eq!(None, s.field_index("h"));
+        }
+        // Most results share fields, but the changes are flattened from time to time.
+        assert!(shared > 40, "{}", shared);
+        assert!(shared < 60, "{}", shared);
+
+        let repr = v.to_repr();
+        let module = module.freeze().unwrap();
+        let v = module.get("s59").unwrap();
+        assert_eq!(repr, v.value().to_repr());
+        assert!(v.value().is_deeply_frozen());
+        let heap = Heap::new();
+        let s = StructRef::from_value(v.value()).unwrap();
+        let updated = s.with_field(&heap, "f0", heap.alloc(-1));
+        assert_eq!(repr.replacen("f0=0,", "f0=-1,", 1), updated.to_repr());
+    }
+
+    #[starlark_module]
+    fn with_field_globals(builder: &mut GlobalsBuilder) {
+        fn with_field<'v>(
+            s: StructRef<'v>,
+            name: &str,
+            value: Value<'v>,
+            heap: &'v Heap,
+        ) -> anyhow::Result<Value<'v>> {
+            Ok(s.with_field(heap, name, value))
+        }
+    }
+
+    #[test]
+    fn test_with_field_shared_gc() {
+        let mut a = assert::Assert::new();
+        a.globals_add(with_field_globals);
+        a.pass(
+            r#"
+s = struct(**{"f{}".format(i): [i] for i in range(100)})
+t = with_field(s, "f1", "x")
+u = with_field(t, "g", [])
+garbage_collect()
+assert_eq(struct(**dict(s.to_dict(), f1 = "x", g = [])), u)
+assert_eq(s.fields() + ["g"], u.fields())
+assert_eq([1], s.f1)
+u.f2.append(-2)
+assert_eq([2, -2], s.f2)
+assert_eq(struct(**dict(s.to_dict(), f1 = "x")), t)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        let module = assert::pass_module(
+            r#"
+x = struct(
+    user = "admin",
+    password = "hunter2",
+    db = struct(host = "localhost", password = "secret", token = [1]),
+    token = 17,
+)
+"#,
+        );
+        let heap = Heap::new();
+        let x = module.get("x").unwrap();
+        let x = StructRef::from_value(x.value()).unwrap();
+        assert_eq!(
+            concat!(
+                r#"struct(user="admin", password="***", "#,
+                r#"db=struct(host="localhost", password="secret", token=[1]), token="***")"#
+            ),
+            x.redact(&heap, &["password", "token"], "***", false)
+                .to_repr()
+        );
+        assert_eq!(
+            concat!(
+                r#"struct(user="admin", password="***", "#,
+                r#"db=struct(host="localhost", password="***", token="***"), token="***")"#
+            ),
+            x.redact(&heap, &["password", "token"], "***", true)
+                .to_repr()
+        );
+        // The original struct is not modified.
+        assert!(x
+            .iter()
+            .any(|(k, v)| k.as_str() == "password" && v.unpack_str() == Some("hunter2")));
+    }
+
+    #[test]
+    fn test_merge_strict() {
+        let heap = Heap::new();
+        let a = heap.alloc(AllocStruct([("x", 1), ("y", 2), ("z", 3)]));
+        let b = heap.alloc(AllocStruct([("w", 0)]));
+        let c = heap.alloc(AllocStruct([("y", 2), ("w", 0)]));
+        let d = heap.alloc(AllocStruct([("z", 30), ("w", 0), ("x", 10)]));
+        let [a, b, c, d] = [a, b, c, d].map(|s| StructRef::from_value(s).unwrap());
+
+        // Disjoint.
+        let ab = a.merge_strict(b, &heap).unwrap().unwrap();
+        assert_eq!("struct(x=1, y=2, z=3, w=0)", ab.to_repr());
+        // Duplicate keys with equal values.
+        let ac = a.merge_strict(c, &heap).unwrap().unwrap();
+        assert_eq!("struct(x=1, y=2, z=3, w=0)", ac.to_repr());
+        // Conflicting keys.
+        assert_eq!(
+            vec!["z".to_owned(), "x".to_owned()],
+            a.merge_strict(d, &heap).unwrap().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_write_jsonl() {
+        let heap = Heap::new();
+        let s = heap.alloc(AllocStruct([
+            ("a", heap.alloc(1)),
+            ("b", heap.alloc("x")),
+            ("c", heap.alloc(AllocStruct([("d", true)]))),
+        ]));
+        let s = StructRef::from_value(s).unwrap();
+
+        let mut values = Vec::new();
+        s.write_jsonl(&mut values, StructJsonLinesFormat::Values)
+            .unwrap();
+        let values = String::from_utf8(values).unwrap();
+        let values: Vec<serde_json::Value> = values
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                serde_json::json!(1),
+                serde_json::json!("x"),
+                serde_json::json!({"d": true}),
+            ],
+            values
+        );
+
+        let mut entries = Vec::new();
+        s.write_jsonl(&mut entries, StructJsonLinesFormat::Entries)
+            .unwrap();
+        let entries = String::from_utf8(entries).unwrap();
+        assert_eq!(
+            r#"{"key":"a","value":1}
+{"key":"b","value":"x"}
+{"key":"c","value":{"d":true}}
+"#,
+            entries
+        );
+    }
+
+    #[test]
+    fn test_to_json_with_key_transform() {
+        fn camel_case(name: &str) -> String {
+            let mut parts = name.split('_');
+            let mut res = parts.next().unwrap_or_default().to_owned();
+            for part in parts {
+                let mut chars = part.chars();
+                if let Some(c) = chars.next() {
+                    res.extend(c.to_uppercase());
+                    res.push_str(chars.as_str());
+                }
+            }
+            res
+        }
+
+        let heap = Heap::new();
+        let s = heap.alloc(AllocStruct([
+            ("server_name", heap.alloc("x")),
+            (
+                "listen_port",
+                heap.alloc(AllocStruct([("port_number", 80), ("max_conns", 10)])),
+            ),
+            (
+                "upstream_hosts",
+                heap.alloc(vec![heap.alloc(AllocStruct([("host_name", "y")]))]),
+            ),
+            (
+                "extra_labels",
+                heap.alloc(AllocDict([
+                    ("snake_key", heap.alloc(1)),
+                    (
+                        "nested_struct",
+                        heap.alloc(AllocStruct([("inner_name", "z")])),
+                    ),
+                ])),
+            ),
+        ]));
+        let s = StructRef::from_value(s).unwrap();
+        assert_eq!(
+            r#"{"serverName":"x","listenPort":{"portNumber":80,"maxConns":10},"upstreamHosts":[{"hostName":"y"}],"extraLabels":{"snake_key":1,"nested_struct":{"innerName":"z"}}}"#,
+            s.to_json_with_key_transform(camel_case).unwrap()
+        );
+
+        let s = heap.alloc(AllocStruct([("a_b", 1), ("aB", 2)]));
+        let s = StructRef::from_value(s).unwrap();
+        assert_eq!(
+            "Struct fields `a_b` and `aB` both transform to JSON key `aB`",
+            s.to_json_with_key_transform(camel_case)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_to_json_with_key_transform_cyclic() {
+        let module = assert::pass_module("l = [1]\nl.append(l)\nx = struct(l = l)");
+        let x = module.get("x").unwrap();
+        assert_eq!(
+            "Too many recursion levels",
+            StructRef::from_value(x.value())
+                .unwrap()
+                .to_json_with_key_transform(|k| k.to_owned())
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_resolve_attr_kind() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("a", 1)]));
+        let s = StructRef::from_value(v).unwrap();
+        assert_eq!(Some(StructAttrKind::Field), s.resolve_attr_kind("a"));
+        assert_eq!(Some(StructAttrKind::Method), s.resolve_attr_kind("to_json"));
+        assert_eq!(None, s.resolve_attr_kind("b"));
+    }
+
+    #[test]
+    fn test_resolve_attr_kind_field_shadows_method() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("to_json", 1)]));
+        let s = StructRef::from_value(v).unwrap();
+        assert_eq!(Some(StructAttrKind::Field), s.resolve_attr_kind("to_json"));
+        assert_eq!(Some(1), v.get_attr_typed::<i32>("to_json", &heap).unwrap());
+    }
+
+    #[test]
+    fn test_content_hash_excluding() {
+        fn hash(v: Value, skip: &[&str]) -> u64 {
+            let mut hasher = StarlarkHasher::new();
+            StructRef::from_value(v)
+                .unwrap()
+                .content_hash_excluding(skip, &mut hasher)
+                .unwrap();
+            hasher.finish()
+        }
+
+        let heap = Heap::new();
+        let a = heap.alloc(AllocStruct([("x", 1), ("time", 100)]));
+        let b = heap.alloc(AllocStruct([("time", 200), ("x", 1)]));
+        let c = heap.alloc(AllocStruct([("x", 2), ("time", 100)]));
+        assert_eq!(hash(a, &["time"]), hash(b, &["time"]));
+        assert_ne!(hash(a, &[]), hash(b, &[]));
+        assert_ne!(hash(a, &["time"]), hash(c, &["time"]));
+        // Without skipped fields, same as regular struct hash.
+        let mut hasher = StarlarkHasher::new();
+        a.write_hash(&mut hasher).unwrap();
+        assert_eq!(hasher.finish(), hash(a, &[]));
+    }
+
+    #[test]
+    fn test_iter_sorted_by_value() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("a", 2), ("b", 3), ("c", 1), ("d", 2)]));
+        let s = StructRef::from_value(v).unwrap();
+        let sorted = |fields: Vec<(&str, Value)>| {
+            fields
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!("c=1 a=2 d=2 b=3", sorted(s.iter_sorted_by_value().unwrap()));
+        // Descending.
+        assert_eq!(
+            "b=3 a=2 d=2 c=1",
+            sorted(s.iter_sorted_by_value_by(|x, y| y.compare(x).unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_iter_sorted_by_value_incomparable() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("a", heap.alloc(1)), ("b", heap.alloc("x"))]));
+        let s = StructRef::from_value(v).unwrap();
+        assert!(s.iter_sorted_by_value().is_err());
+        // Total order sorts by type name first.
+        let fields = s.iter_sorted_by_value_by(|x, y| x.total_cmp(y));
+        assert_eq!(
+            vec!["a", "b"],
+            fields.iter().map(|(k, _)| *k).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_to_flat_properties() {
+        let module = assert::pass_module(
+            r#"
+x = struct(
+    name = "web",
+    server = struct(host = "localhost", port = 80, tls = struct(enabled = False)),
+    hosts = ["a", "b"],
+    weights = (0.5, [1, 2]),
+    empty = struct(),
+)
+"#,
+        );
+        let x = module.get("x").unwrap();
+        let properties = StructRef::from_value(x.value())
+            .unwrap()
+            .to_flat_properties()
+            .unwrap();
+        let properties: Vec<String> = properties
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        assert_eq!(
+            vec![
+                "hosts.0=a",
+                "hosts.1=b",
+                "name=web",
+                "server.host=localhost",
+                "server.port=80",
+                "server.tls.enabled=False",
+                "weights.0=0.5",
+                "weights.1.0=1",
+                "weights.1.1=2",
+            ],
+            properties
+        );
+    }
+
+    #[test]
+    fn test_to_flat_properties_unsupported() {
+        let module = assert::pass_module("x = struct(a = struct(b = [1, None]))");
+        let x = module.get("x").unwrap();
+        assert_eq!(
+            "Cannot convert value of type `NoneType` at `a.b.1` to a property",
+            StructRef::from_value(x.value())
+                .unwrap()
+                .to_flat_properties()
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_to_flat_properties_cyclic() {
+        let module = assert::pass_module("l = [1]\nl.append(l)\nx = struct(l = l)");
+        let x = module.get("x").unwrap();
+        assert_eq!(
+            "Too many recursion levels",
+            StructRef::from_value(x.value())
+                .unwrap()
+                .to_flat_properties()
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let heap = Heap::new();
+        let fingerprint = |v: Value| StructRef::from_value(v).unwrap().fingerprint().unwrap();
+        let a = heap.alloc(AllocStruct([("x", 1), ("y", 2)]));
+        let b = heap.alloc(AllocStruct([("y", 2), ("x", 1)]));
+        let c = heap.alloc(AllocStruct([("x", 1), ("y", 3)]));
+        assert_eq!(fingerprint(a), fingerprint(b));
+        assert_ne!(fingerprint(a), fingerprint(c));
+        assert_eq!(16, fingerprint(a).len());
+        let unhashable = heap.alloc(AllocStruct([("x", heap.alloc(Vec::<i32>::new()))]));
+        assert!(StructRef::from_value(unhashable)
+            .unwrap()
+            .fingerprint()
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_attr_value() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("a", 1)]));
+        let s = StructRef::from_value(v).unwrap();
+        assert_eq!(
+            Some(heap.alloc(1)),
+            s.get_attr_value(heap.alloc("a")).unwrap()
+        );
+        assert_eq!(None, s.get_attr_value(heap.alloc("b")).unwrap());
+        assert_eq!(
+            "Expected value of type `str` but got value of type `int`",
+            s.get_attr_value(heap.alloc(1)).unwrap_err().to_string()
+        );
+    }
+}
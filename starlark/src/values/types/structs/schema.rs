@@ -0,0 +1,329 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Runtime schemas for validating structs.
+
+use std::fmt;
+
+use dupe::Dupe;
+
+use crate::values::float::StarlarkFloat;
+use crate::values::list::ListRef;
+use crate::values::structs::StructRef;
+use crate::values::types::int_or_big::StarlarkIntRef;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// Expected type of a struct field, see [`Schema`].
+#[derive(Debug, Clone)]
+pub enum SchemaType {
+    /// Any value.
+    Any,
+    /// `bool`.
+    Bool,
+    /// `int`.
+    Int,
+    /// `float` or `int`, like the `float` type annotation.
+    Float,
+    /// `str`.
+    Str,
+    /// `list` with all elements of the given type.
+    List(Box<SchemaType>),
+    /// Struct matching the nested schema.
+    Struct(Schema),
+}
+
+impl fmt::Display for SchemaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaType::Any => write!(f, "any"),
+            SchemaType::Bool => write!(f, "bool"),
+            SchemaType::Int => write!(f, "int"),
+            SchemaType::Float => write!(f, "float"),
+            SchemaType::Str => write!(f, "str"),
+            SchemaType::List(t) => write!(f, "list[{}]", t),
+            SchemaType::Struct(_) => write!(f, "struct"),
+        }
+    }
+}
+
+/// What to do with struct fields not declared in a [`Schema`].
+#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq)]
+pub enum UnknownFields {
+    /// Report unknown fields as errors (the default).
+    Reject,
+    /// Ignore unknown fields.
+    Allow,
+}
+
+/// Violation found by [`Schema::validate`].
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+pub enum SchemaError {
+    /// Required field is not present.
+    #[error("{0}: missing required field")]
+    MissingField(String),
+    /// Field is not declared in the schema.
+    #[error("{0}: unknown field")]
+    UnknownField(String),
+    /// Value is not of the expected type.
+    #[error("{path}: expected `{expected}`, got value of type `{actual}`")]
+    WrongType {
+        /// Path to the value, like `$.server.port`.
+        path: String,
+        /// Expected type.
+        expected: String,
+        /// Actual type.
+        actual: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct SchemaField {
+    name: String,
+    ty: SchemaType,
+    required: bool,
+}
+
+/// Description of expected struct fields and their types, built at runtime.
+///
+/// # Example
+///
+/// ```
+/// use starlark::values::structs::Schema;
+/// use starlark::values::structs::SchemaType;
+/// # use starlark::values::Value;
+///
+/// # fn validate(config: Value) {
+/// let schema = Schema::new()
+///     .required("name", SchemaType::Str)
+///     .optional("port", SchemaType::Int)
+///     .required(
+///         "tls",
+///         SchemaType::Struct(Schema::new().required("enabled", SchemaType::Bool)),
+///     );
+/// if let Err(errors) = schema.validate(config) {
+///     for error in errors {
+///         eprintln!("{}", error);
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Schema {
+    fields: Vec<SchemaField>,
+    unknown_fields: UnknownFields,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema::new()
+    }
+}
+
+impl Schema {
+    /// Schema with no fields, rejecting unknown fields.
+    pub fn new() -> Schema {
+        Schema {
+            fields: Vec::new(),
+            unknown_fields: UnknownFields::Reject,
+        }
+    }
+
+    /// Add a field which must be present.
+    pub fn required(self, name: &str, ty: SchemaType) -> Schema {
+        self.field(name, ty, true)
+    }
+
+    /// Add a field which may be absent. If present, it must be of the given type.
+    pub fn optional(self, name: &str, ty: SchemaType) -> Schema {
+        self.field(name, ty, false)
+    }
+
+    fn field(mut self, name: &str, ty: SchemaType, required: bool) -> Schema {
+        self.fields.retain(|f| f.name != name);
+        self.fields.push(SchemaField {
+            name: name.to_owned(),
+            ty,
+            required,
+        });
+        self
+    }
+
+    /// Set the policy for fields which are not declared in this schema.
+    /// Applies only to this schema, not to nested schemas.
+    pub fn unknown_fields(mut self, unknown_fields: UnknownFields) -> Schema {
+        self.unknown_fields = unknown_fields;
+        self
+    }
+
+    /// Check the value is a struct matching the schema.
+    ///
+    /// All violations are collected, in field order of the schema,
+    /// followed by unknown fields in field order of the struct.
+    /// Paths start with `$`, like `$.server.port` or `$.hosts[1]`.
+    pub fn validate(&self, value: Value) -> Result<(), Vec<SchemaError>> {
+        let mut errors = Vec::new();
+        self.validate_impl("$", value, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_impl(&self, path: &str, value: Value, errors: &mut Vec<SchemaError>) {
+        let Some(s) = StructRef::from_value(value) else {
+            errors.push(wrong_type(path, "struct", value));
+            return;
+        };
+        let fields = s.attributes();
+        for field in &self.fields {
+            let field_path = format!("{}.{}", path, field.name);
+            match fields.get(field.name.as_str()) {
+                Some(v) => field.ty.validate(&field_path, *v, errors),
+                None if field.required => errors.push(SchemaError::MissingField(field_path)),
+                None => {}
+            }
+        }
+        if self.unknown_fields == UnknownFields::Reject {
+            for name in fields.keys() {
+                if !self.fields.iter().any(|f| f.name == *name) {
+                    errors.push(SchemaError::UnknownField(format!("{}.{}", path, name)));
+                }
+            }
+        }
+    }
+}
+
+impl SchemaType {
+    fn validate(&self, path: &str, value: Value, errors: &mut Vec<SchemaError>) {
+        let ok = match self {
+            SchemaType::Any => true,
+            SchemaType::Bool => value.unpack_bool().is_some(),
+            SchemaType::Int => StarlarkIntRef::unpack_value(value).is_some(),
+            SchemaType::Float => {
+                value.downcast_ref::<StarlarkFloat>().is_some()
+                    || StarlarkIntRef::unpack_value(value).is_some()
+            }
+            SchemaType::Str => value.unpack_str().is_some(),
+            SchemaType::List(t) => match ListRef::from_value(value) {
+                Some(xs) => {
+                    for (i, x) in xs.content().iter().enumerate() {
+                        t.validate(&format!("{}[{}]", path, i), *x, errors);
+                    }
+                    true
+                }
+                None => false,
+            },
+            SchemaType::Struct(schema) => {
+                schema.validate_impl(path, value, errors);
+                true
+            }
+        };
+        if !ok {
+            errors.push(wrong_type(path, &self.to_string(), value));
+        }
+    }
+}
+
+fn wrong_type(path: &str, expected: &str, value: Value) -> SchemaError {
+    SchemaError::WrongType {
+        path: path.to_owned(),
+        expected: expected.to_owned(),
+        actual: value.get_type().to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::values::structs::Schema;
+    use crate::values::structs::SchemaType;
+    use crate::values::structs::UnknownFields;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .required("name", SchemaType::Str)
+            .optional("port", SchemaType::Int)
+            .optional("ratio", SchemaType::Float)
+            .required("hosts", SchemaType::List(Box::new(SchemaType::Str)))
+            .required(
+                "tls",
+                SchemaType::Struct(
+                    Schema::new()
+                        .required("enabled", SchemaType::Bool)
+                        .optional("extra", SchemaType::Any)
+                        .unknown_fields(UnknownFields::Allow),
+                ),
+            )
+    }
+
+    fn validate(program: &str) -> Vec<String> {
+        let module = assert::pass_module(&format!("x = {}", program));
+        let x = module.get("x").unwrap();
+        match schema().validate(x.value()) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid() {
+        assert!(
+            validate("struct(name = 'a', hosts = [], tls = struct(enabled = True))").is_empty()
+        );
+        assert!(
+            validate(
+                "struct(tls = struct(enabled = False, other = 1), ratio = 1, port = 80, hosts = ['h'], name = 'a')"
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_multiple_violations() {
+        assert_eq!(
+            vec![
+                "$.name: missing required field",
+                "$.port: expected `int`, got value of type `string`",
+                "$.hosts[1]: expected `str`, got value of type `int`",
+                "$.tls.enabled: missing required field",
+                "$.unknown: unknown field",
+            ],
+            validate(
+                "struct(port = '80', hosts = ['a', 1], tls = struct(other = 1), unknown = None)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_not_struct() {
+        assert_eq!(
+            vec![
+                "$.name: missing required field",
+                "$.hosts: expected `list[str]`, got value of type `tuple`",
+                "$.tls: expected `struct`, got value of type `dict`",
+            ],
+            validate("struct(hosts = ('a',), tls = {})")
+        );
+        assert_eq!(
+            vec!["$: expected `struct`, got value of type `list`"],
+            validate("[]")
+        );
+    }
+}
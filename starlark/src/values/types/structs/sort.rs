@@ -0,0 +1,149 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cmp::Ordering;
+
+use starlark_map::Hashed;
+
+use crate::values::structs::value::Struct;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum SortByStructFieldError {
+    #[error("Cannot sort by field `{0}`: expected a struct, got a value of type `{1}`")]
+    NotStruct(String, &'static str),
+    #[error("Cannot sort by field `{0}`: struct has no such field: {1}")]
+    MissingField(String, String),
+}
+
+/// Sort struct values by the value of the field `field`.
+///
+/// This is equivalent to `sorted(values, key = lambda x: x.field)`,
+/// but the field is looked up from Rust, without calling a Starlark function per element.
+/// The sort is stable.
+///
+/// Fails if any element is not a struct or does not have the field,
+/// or if the field values cannot be compared.
+pub fn sort_by_struct_field<'v>(values: &mut [Value<'v>], field: &str) -> crate::Result<()> {
+    let field_hashed = Hashed::new(field);
+    let mut keyed = Vec::with_capacity(values.len());
+    for value in values.iter() {
+        let s = Struct::from_value(*value).ok_or_else(|| {
+            crate::Error::new_other(SortByStructFieldError::NotStruct(
+                field.to_owned(),
+                value.get_type(),
+            ))
+        })?;
+        let key = s.get_hashed(field_hashed).ok_or_else(|| {
+            crate::Error::new_other(SortByStructFieldError::MissingField(
+                field.to_owned(),
+                value.to_repr(),
+            ))
+        })?;
+        keyed.push((key, *value));
+    }
+
+    let mut compare_ok = Ok(());
+    keyed.sort_by(
+        |x: &(Value, Value), y: &(Value, Value)| match x.0.compare(y.0) {
+            Ok(r) => r,
+            Err(e) => {
+                compare_ok = Err(e);
+                Ordering::Equal // does not matter
+            }
+        },
+    );
+    compare_ok?;
+
+    for (value, (_, sorted)) in values.iter_mut().zip(keyed) {
+        *value = sorted;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::structs::sort_by_struct_field;
+    use crate::values::structs::AllocStruct;
+    use crate::values::Heap;
+    use crate::values::Value;
+
+    fn names<'v>(values: &[Value<'v>], heap: &'v Heap) -> Vec<String> {
+        values
+            .iter()
+            .map(|v| {
+                v.get_attr("name", heap)
+                    .unwrap()
+                    .unwrap()
+                    .unpack_str()
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_by_struct_field() {
+        let heap = Heap::new();
+        let mut values = vec![
+            heap.alloc(AllocStruct([
+                ("name", heap.alloc("c")),
+                ("n", heap.alloc(2)),
+            ])),
+            heap.alloc(AllocStruct([
+                ("name", heap.alloc("a")),
+                ("n", heap.alloc(1)),
+            ])),
+            heap.alloc(AllocStruct([
+                ("name", heap.alloc("b")),
+                ("n", heap.alloc(2)),
+            ])),
+        ];
+        sort_by_struct_field(&mut values, "n").unwrap();
+        // Stable: `c` is before `b`.
+        assert_eq!(vec!["a", "c", "b"], names(&values, &heap));
+        sort_by_struct_field(&mut values, "name").unwrap();
+        assert_eq!(vec!["a", "b", "c"], names(&values, &heap));
+    }
+
+    #[test]
+    fn test_sort_by_struct_field_errors() {
+        let heap = Heap::new();
+        let s = heap.alloc(AllocStruct([("n", 1)]));
+
+        let mut values = vec![s, heap.alloc(1)];
+        let err = sort_by_struct_field(&mut values, "n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected a struct, got a value of type `int`"),
+            "{}",
+            err
+        );
+
+        let mut values = vec![s, heap.alloc(AllocStruct([("m", 1)]))];
+        let err = sort_by_struct_field(&mut values, "n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("struct has no such field: struct(m=1)"),
+            "{}",
+            err
+        );
+
+        let mut values = vec![s, heap.alloc(AllocStruct([("n", "x")]))];
+        assert!(sort_by_struct_field(&mut values, "n").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::marker::PhantomData;
+
+use crate::typing::Ty;
+use crate::values::structs::StructRef;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+/// Name of the single field of a struct unpacked with [`TaggedField`].
+pub trait TaggedFieldName {
+    /// The field name.
+    const NAME: &'static str;
+}
+
+/// Field name `value`, for structs like `struct(value=x)`.
+pub struct ValueField;
+
+impl TaggedFieldName for ValueField {
+    const NAME: &'static str = "value";
+}
+
+/// Unpack a struct with exactly one field named `N::NAME`, unpacking the field to `T`.
+///
+/// Structs with any other fields, or with the field value not matching `T`, are rejected.
+///
+/// # Example
+///
+/// ```
+/// use starlark::values::structs::TaggedField;
+/// use starlark::values::structs::ValueField;
+/// # use starlark::values::UnpackValue;
+/// # use starlark::values::Value;
+///
+/// # fn unpack(value: Value) {
+/// // Accepts `struct(value=1)`, rejects `struct(value=1, other=2)`.
+/// let x = TaggedField::<ValueField, i32>::unpack_value(value);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TaggedField<N: TaggedFieldName, T> {
+    /// The unpacked field value.
+    pub value: T,
+    _name: PhantomData<N>,
+}
+
+impl<N: TaggedFieldName, T> TaggedField<N, T> {
+    /// Get the unpacked field value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<N: TaggedFieldName, T: StarlarkTypeRepr> StarlarkTypeRepr for TaggedField<N, T> {
+    type Canonical = <StructRef<'static> as StarlarkTypeRepr>::Canonical;
+
+    fn starlark_type_repr() -> Ty {
+        StructRef::starlark_type_repr()
+    }
+}
+
+impl<'v, N: TaggedFieldName, T: UnpackValue<'v>> UnpackValue<'v> for TaggedField<N, T> {
+    fn unpack_value(value: Value<'v>) -> Option<Self> {
+        let s = StructRef::from_value(value)?;
+        let mut fields = s.iter();
+        let (name, field) = fields.next()?;
+        if name.as_str() != N::NAME || fields.next().is_some() {
+            return None;
+        }
+        Some(TaggedField {
+            value: T::unpack_value(field)?,
+            _name: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::structs::AllocStruct;
+    use crate::values::structs::TaggedField;
+    use crate::values::structs::TaggedFieldName;
+    use crate::values::structs::ValueField;
+    use crate::values::Heap;
+    use crate::values::UnpackValue;
+
+    struct NameField;
+
+    impl TaggedFieldName for NameField {
+        const NAME: &'static str = "name";
+    }
+
+    #[test]
+    fn test_unpack() {
+        let heap = Heap::new();
+        let v = heap.alloc(AllocStruct([("value", 17)]));
+        let x = TaggedField::<ValueField, i32>::unpack_value(v).unwrap();
+        assert_eq!(17, x.into_inner());
+        let v = heap.alloc(AllocStruct([("name", "x")]));
+        assert_eq!(
+            "x",
+            TaggedField::<NameField, &str>::unpack_value(v)
+                .unwrap()
+                .value
+        );
+    }
+
+    #[test]
+    fn test_unpack_rejected() {
+        let heap = Heap::new();
+        // Extra field.
+        let v = heap.alloc(AllocStruct([("value", 1), ("other", 2)]));
+        assert!(TaggedField::<ValueField, i32>::unpack_value(v).is_none());
+        // Wrong field name.
+        let v = heap.alloc(AllocStruct([("name", 1)]));
+        assert!(TaggedField::<ValueField, i32>::unpack_value(v).is_none());
+        // No fields.
+        let v = heap.alloc(AllocStruct::EMPTY);
+        assert!(TaggedField::<ValueField, i32>::unpack_value(v).is_none());
+        // Wrong field type.
+        let v = heap.alloc(AllocStruct([("value", "x")]));
+        assert!(TaggedField::<ValueField, i32>::unpack_value(v).is_none());
+        // Not a struct.
+        assert!(TaggedField::<ValueField, i32>::unpack_value(heap.alloc(1)).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversion of structs to owned Rust data.
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::values::dict::DictRef;
+use crate::values::structs::walk::walk;
+use crate::values::structs::walk::walk_fields;
+use crate::values::structs::walk::StructWalk;
+use crate::values::structs::StructRef;
+use crate::values::types::int_or_big::StarlarkIntRef;
+
+#[derive(Debug, thiserror::Error)]
+enum TaggedValueError {
+    #[error("Cannot convert value of type `{1}` at `{0}` to a tagged value")]
+    UnsupportedValue(String, &'static str),
+}
+
+/// Value tagged with its kind, produced by [`StructRef::to_tagged_entries`].
+///
+/// Holds owned Rust data, so it does not reference the heap the value came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaggedValue {
+    /// `None`.
+    Unit,
+    /// `bool`.
+    Bool(bool),
+    /// `int` which fits into `i64`.
+    Int(i64),
+    /// `int` which does not fit into `i64`.
+    BigInt(BigInt),
+    /// `float`.
+    Float(f64),
+    /// `str`.
+    String(String),
+    /// `list`.
+    List(Vec<TaggedValue>),
+    /// `tuple`.
+    Tuple(Vec<TaggedValue>),
+    /// `dict`, entries in dict order.
+    Dict(Vec<(TaggedValue, TaggedValue)>),
+    /// Nested struct, fields in field order.
+    Struct(Vec<(String, TaggedValue)>),
+}
+
+impl<'v> StructRef<'v> {
+    /// Convert the struct into field names and tagged values in field order,
+    /// recursively converting nested values.
+    ///
+    /// An integer is [`TaggedValue::Int`] if it fits into `i64`, and
+    /// [`TaggedValue::BigInt`] otherwise, so integers are never rounded.
+    /// Fails on values of any type not covered by [`TaggedValue`], like functions,
+    /// and on cyclic lists or dicts.
+    pub fn to_tagged_entries(&self) -> anyhow::Result<Vec<(String, TaggedValue)>> {
+        walk_fields("", *self)
+    }
+}
+
+impl StructWalk for TaggedValue {
+    fn none() -> Self {
+        TaggedValue::Unit
+    }
+
+    fn bool(b: bool) -> Self {
+        TaggedValue::Bool(b)
+    }
+
+    fn int(i: StarlarkIntRef) -> Self {
+        let i = i.to_big();
+        match i.to_i64() {
+            Some(i) => TaggedValue::Int(i),
+            None => TaggedValue::BigInt(i),
+        }
+    }
+
+    fn float(f: f64) -> Self {
+        TaggedValue::Float(f)
+    }
+
+    fn string(s: &str) -> Self {
+        TaggedValue::String(s.to_owned())
+    }
+
+    fn list(items: Vec<Self>) -> Self {
+        TaggedValue::List(items)
+    }
+
+    fn tuple(items: Vec<Self>) -> Self {
+        TaggedValue::Tuple(items)
+    }
+
+    fn struct_fields(fields: Vec<(String, Self)>) -> Self {
+        TaggedValue::Struct(fields)
+    }
+
+    fn dict(path: &str, dict: &DictRef) -> anyhow::Result<Self> {
+        dict.iter()
+            .map(|(k, v)| {
+                let path = format!("{}[{}]", path, k);
+                Ok((walk(path.clone(), k)?, walk(path, v)?))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(TaggedValue::Dict)
+    }
+
+    fn unsupported(path: String, ty: &'static str) -> anyhow::Error {
+        TaggedValueError::UnsupportedValue(path, ty).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+
+    use crate::assert;
+    use crate::values::structs::StructRef;
+    use crate::values::structs::TaggedValue;
+
+    fn to_tagged_entries(program: &str) -> anyhow::Result<Vec<(String, TaggedValue)>> {
+        let module = assert::pass_module(program);
+        let x = module.get("x").unwrap();
+        StructRef::from_value(x.value())
+            .unwrap()
+            .to_tagged_entries()
+    }
+
+    fn s(x: &str) -> TaggedValue {
+        TaggedValue::String(x.to_owned())
+    }
+
+    #[test]
+    fn test_to_tagged_entries() {
+        let actual = to_tagged_entries(
+            "x = struct(name = 'a', port = 80, ratio = 0.5, big = 1 << 70, \
+             min = -9223372036854775808, on = True, nothing = None, \
+             hosts = ['h', (1,)], tls = struct(enabled = False, ca = struct(path = 'p')), \
+             labels = {'k': 'v', 2: [None]})",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                ("name".to_owned(), s("a")),
+                ("port".to_owned(), TaggedValue::Int(80)),
+                ("ratio".to_owned(), TaggedValue::Float(0.5)),
+                (
+                    "big".to_owned(),
+                    TaggedValue::BigInt(BigInt::from(1u128 << 70))
+                ),
+                ("min".to_owned(), TaggedValue::Int(i64::MIN)),
+                ("on".to_owned(), TaggedValue::Bool(true)),
+                ("nothing".to_owned(), TaggedValue::Unit),
+                (
+                    "hosts".to_owned(),
+                    TaggedValue::List(vec![s("h"), TaggedValue::Tuple(vec![TaggedValue::Int(1)])])
+                ),
+                (
+                    "tls".to_owned(),
+                    TaggedValue::Struct(vec![
+                        ("enabled".to_owned(), TaggedValue::Bool(false)),
+                        (
+                            "ca".to_owned(),
+                            TaggedValue::Struct(vec![("path".to_owned(), s("p"))])
+                        ),
+                    ])
+                ),
+                (
+                    "labels".to_owned(),
+                    TaggedValue::Dict(vec![
+                        (s("k"), s("v")),
+                        (
+                            TaggedValue::Int(2),
+                            TaggedValue::List(vec![TaggedValue::Unit])
+                        ),
+                    ])
+                ),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_to_tagged_entries_errors() {
+        assert_eq!(
+            "Cannot convert value of type `function` at `a.b[1]` to a tagged value",
+            to_tagged_entries("x = struct(a = struct(b = [1, len]))")
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(to_tagged_entries("l = [1]\nl.append(l)\nx = struct(l = l)").is_err());
+    }
+}
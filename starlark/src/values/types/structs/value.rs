@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Display;
@@ -22,10 +23,11 @@ use std::hash::Hash;
 use std::hash::Hasher;
 
 use allocative::Allocative;
+use anyhow::Context;
 use display_container::fmt_keyed_container;
+use either::Either;
 use serde::Serialize;
 use starlark_derive::starlark_value;
-use starlark_derive::Freeze;
 use starlark_derive::StarlarkDocs;
 use starlark_derive::Trace;
 use starlark_map::small_map::SmallMap;
@@ -34,12 +36,16 @@ use starlark_map::StarlarkHasher;
 
 use crate as starlark;
 use crate::any::ProvidesStaticType;
+use crate::cast::transmute;
 use crate::coerce::coerce;
 use crate::coerce::Coerce;
 use crate::docs::DocItem;
 use crate::docs::DocMember;
 use crate::docs::DocObject;
 use crate::docs::DocProperty;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::private::Private;
 use crate::starlark_complex_value;
 use crate::typing::Ty;
 use crate::typing::TyStruct;
@@ -47,6 +53,8 @@ use crate::values::comparison::compare_small_map;
 use crate::values::comparison::equals_small_map;
 use crate::values::layout::heap::profile::arc_str::ArcStr;
 use crate::values::structs::unordered_hasher::UnorderedHasher;
+use crate::values::Freeze;
+use crate::values::Freezer;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
 use crate::values::Heap;
@@ -63,17 +71,209 @@ impl<'v, V: ValueLike<'v>> StructGen<'v, V> {
 
     /// Create a new [`Struct`].
     pub(crate) fn new(fields: SmallMap<V::String, V>) -> Self {
-        Self { fields }
+        Self {
+            fields,
+            shared: None,
+        }
+    }
+
+    /// The struct whose fields this struct shares, if any.
+    fn base(&self) -> Option<(&'v Struct<'v>, &SmallMap<StringValue<'v>, Value<'v>>)> {
+        let shared = self.shared.as_ref()?;
+        let base = Struct::from_value(shared.base.to_value()).expect("base is a struct");
+        Some((base, coerce(&shared.replaced)))
+    }
+
+    /// Number of fields.
+    pub(crate) fn len(&self) -> usize {
+        match self.base() {
+            None => self.fields.len(),
+            Some((base, _)) => base.fields.len() + self.fields.len(),
+        }
+    }
+
+    /// Get the value of a field.
+    pub(crate) fn get_hashed(&self, name: Hashed<&str>) -> Option<Value<'v>> {
+        if let Some((base, replaced)) = self.base() {
+            if let Some(v) = replaced
+                .get_hashed(name)
+                .or_else(|| base.fields.get_hashed(name))
+            {
+                return Some(*v);
+            }
+        }
+        coerce(&self.fields).get_hashed(name).copied()
+    }
+
+    /// Get the value of a field.
+    pub(crate) fn get(&self, name: &str) -> Option<Value<'v>> {
+        self.get_hashed(Hashed::new(name))
+    }
+
+    /// Get the position of a field.
+    pub(crate) fn get_index_of_hashed(&self, name: Hashed<&str>) -> Option<usize> {
+        let fields: &SmallMap<StringValue<'v>, Value<'v>> = coerce(&self.fields);
+        match self.base() {
+            None => fields.get_index_of_hashed(name),
+            Some((base, _)) => base.fields.get_index_of_hashed(name).or_else(|| {
+                let i = fields.get_index_of_hashed(name)?;
+                Some(base.fields.len() + i)
+            }),
+        }
+    }
+
+    /// Get the field at a position.
+    pub(crate) fn get_index(&self, index: usize) -> Option<(StringValue<'v>, Value<'v>)> {
+        let fields = match self.base() {
+            None => coerce(&self.fields),
+            Some((base, replaced)) => match base.fields.get_index(index) {
+                Some((k, v)) => {
+                    let v = replaced.get_hashed(k.get_hashed().as_ref()).unwrap_or(v);
+                    return Some((*k, *v));
+                }
+                None => {
+                    let (k, v) = self.fields.get_index(index - base.fields.len())?;
+                    return Some((k.to_string_value(), v.to_value()));
+                }
+            },
+        };
+        fields.get_index(index).map(|(k, v)| (*k, *v))
+    }
+
+    /// Iterate over the elements in the struct, along with the hashes of the names.
+    pub(crate) fn iter_hashed<'a>(
+        &'a self,
+    ) -> impl ExactSizeIterator<Item = (Hashed<StringValue<'v>>, Value<'v>)> + 'a
+    where
+        'v: 'a,
+    {
+        match self.shared {
+            None => {
+                let fields: &'a SmallMap<StringValue<'v>, Value<'v>> = coerce(&self.fields);
+                Either::Left(fields.iter_hashed().map(|(k, v)| (k.copied(), *v)))
+            }
+            Some(_) => Either::Right((0..self.len()).map(|i| {
+                let (k, v) = self.get_index(i).unwrap();
+                (k.get_hashed(), v)
+            })),
+        }
     }
 
     /// Iterate over the elements in the struct.
-    pub(crate) fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = (StringValue<'v>, V)> + 'a
+    pub(crate) fn iter<'a>(
+        &'a self,
+    ) -> impl ExactSizeIterator<Item = (StringValue<'v>, Value<'v>)> + 'a
     where
         'v: 'a,
     {
-        self.fields
-            .iter()
-            .map(|(name, value)| (name.to_string_value(), *value))
+        self.iter_hashed().map(|(k, v)| (k.into_key(), v))
+    }
+
+    /// Does the struct share fields with another struct.
+    pub(crate) fn is_shared(&self) -> bool {
+        self.shared.is_some()
+    }
+
+    /// All the fields in one map, which is copied if the struct shares fields.
+    pub(crate) fn fields_map(&self) -> Cow<'_, SmallMap<StringValue<'v>, Value<'v>>> {
+        match self.shared {
+            None => Cow::Borrowed(coerce(&self.fields)),
+            Some(_) => {
+                let mut fields = SmallMap::with_capacity(self.len());
+                for (k, v) in self.iter_hashed() {
+                    fields.insert_hashed_unique_unchecked(k, v);
+                }
+                Cow::Owned(fields)
+            }
+        }
+    }
+
+    /// Hash the struct like `write_hash`, but ignore fields named in `skip`.
+    pub(crate) fn write_hash_excluding(
+        &self,
+        skip: &[&str],
+        hasher: &mut StarlarkHasher,
+    ) -> crate::Result<()> {
+        // Must use unordered hash because equality is unordered,
+        // and `a = b  =>  hash(a) = hash(b)`.
+        let mut unordered_hasher = UnorderedHasher::new();
+
+        for (k, v) in self.iter_hashed() {
+            if skip.contains(&k.key().as_str()) {
+                continue;
+            }
+            // Should hash key and value together, so two structs
+            // `a=1 b=2` and `a=2 b=1` would produce different hashes.
+            let mut entry_hasher = StarlarkHasher::new();
+            k.hash().hash(&mut entry_hasher);
+            v.write_hash(&mut entry_hasher)?;
+            unordered_hasher.write_hash(entry_hasher.finish());
+        }
+
+        hasher.write_u64(unordered_hasher.finish());
+
+        Ok(())
+    }
+}
+
+impl<'v> Struct<'v> {
+    /// Structs with fewer fields are copied by `with_field`, not shared.
+    const WITH_FIELD_SHARE_MIN_LEN: usize = 32;
+
+    /// Struct `me` (which is `self`) with field `name` set to `value`.
+    ///
+    /// A large struct is shared, not copied: the result stores the changed fields only,
+    /// and the fields of the struct without shared fields it was derived from.
+    /// Once the changes outnumber the square root of the number of fields,
+    /// the result is flat again, so access is not slowed down by a long history,
+    /// and the amortized cost of an update is `O(sqrt(n))`.
+    pub(crate) fn with_field(
+        &self,
+        me: Value<'v>,
+        name: Hashed<StringValue<'v>>,
+        value: Value<'v>,
+    ) -> Struct<'v> {
+        let (base_value, base, mut replaced, mut appended) = match self.base() {
+            Some((base, replaced)) => (
+                self.shared.as_ref().unwrap().base,
+                base,
+                replaced.clone(),
+                self.fields.clone(),
+            ),
+            None if self.fields.len() >= Self::WITH_FIELD_SHARE_MIN_LEN => {
+                (me, self, SmallMap::new(), SmallMap::new())
+            }
+            None => {
+                let mut fields = self.fields.clone();
+                fields.insert_hashed(name, value);
+                return Struct::new(fields);
+            }
+        };
+        if base.fields.contains_key_hashed(name.as_ref()) {
+            replaced.insert_hashed(name, value);
+        } else {
+            appended.insert_hashed(name, value);
+        }
+
+        let changed = replaced.len() + appended.len();
+        if changed * changed > base.fields.len() {
+            let mut fields = SmallMap::with_capacity(base.fields.len() + appended.len());
+            for (k, v) in base.fields.iter_hashed() {
+                let v = replaced.get_hashed(k).unwrap_or(v);
+                fields.insert_hashed_unique_unchecked(k.copied(), *v);
+            }
+            for (k, v) in appended.into_iter_hashed() {
+                fields.insert_hashed_unique_unchecked(k, v);
+            }
+            return Struct::new(fields);
+        }
+        Struct {
+            fields: appended,
+            shared: Some(Box::new(StructShared {
+                base: base_value,
+                replaced,
+            })),
+        }
     }
 }
 
@@ -81,19 +281,29 @@ impl StructGen<'static, FrozenValue> {
     pub(crate) fn iter_frozen(
         &self,
     ) -> impl ExactSizeIterator<Item = (FrozenStringValue, FrozenValue)> + '_ {
-        self.fields.iter().map(|(name, value)| (*name, *value))
+        // Fields of frozen structs are frozen, including shared ones.
+        self.iter().map(|(name, value)| {
+            (
+                name.unpack_frozen().unwrap(),
+                value.unpack_frozen().unwrap(),
+            )
+        })
     }
 }
 
 starlark_complex_value!(pub(crate) Struct<'v>);
 
+pub(crate) fn struct_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::structs::struct_methods)
+}
+
 /// The result of calling `struct()`.
 #[derive(
     Clone,
     Default,
     Debug,
     Trace,
-    Freeze,
     ProvidesStaticType,
     StarlarkDocs,
     Allocative
@@ -101,12 +311,88 @@ starlark_complex_value!(pub(crate) Struct<'v>);
 #[starlark_docs(builtin = "extension")]
 #[repr(C)]
 pub(crate) struct StructGen<'v, V: ValueLike<'v>> {
-    /// The fields in a struct.
-    pub(crate) fields: SmallMap<V::String, V>,
+    /// The fields in a struct, or the fields appended to the shared ones.
+    fields: SmallMap<V::String, V>,
+    /// Fields shared with another struct.
+    shared: Option<Box<StructShared<'v, V>>>,
+}
+
+/// Fields of a large struct created by [`StructRef::with_field`], stored
+/// as changes to the struct it was created from, so the field table is not copied.
+///
+/// [`StructRef::with_field`]: crate::values::structs::StructRef::with_field
+#[derive(Clone, Debug, Trace, Allocative)]
+#[repr(C)]
+struct StructShared<'v, V: ValueLike<'v>> {
+    /// Struct without shared fields, its fields come first.
+    base: V,
+    /// New values of the fields of `base`.
+    replaced: SmallMap<V::String, V>,
 }
 
 unsafe impl<'v> Coerce<StructGen<'v, Value<'v>>> for StructGen<'static, FrozenValue> {}
 
+impl<'v> Freeze for Struct<'v> {
+    type Frozen = FrozenStruct;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<FrozenStruct> {
+        // Structs built entirely from frozen values, e.g. by combining frozen structs,
+        // keep their map, so neither the entries nor the index are rebuilt.
+        if self.shared.is_none()
+            && self
+                .fields
+                .iter()
+                .all(|(k, v)| k.to_value().unpack_frozen().is_some() && v.unpack_frozen().is_some())
+        {
+            // Scary part: `SmallMap` has the same repr for frozen and unfrozen values,
+            // and we just checked above that all keys and values are frozen.
+
+            fn _assert_coerce<'v>(
+                s: SmallMap<FrozenStringValue, FrozenValue>,
+            ) -> SmallMap<StringValue<'v>, Value<'v>> {
+                coerce(s)
+            }
+
+            let fields = unsafe {
+                transmute!(
+                    SmallMap<StringValue, Value>,
+                    SmallMap<FrozenStringValue, FrozenValue>,
+                    self.fields
+                )
+            };
+            return Ok(FrozenStruct::new(fields));
+        }
+
+        // The base is a value, so structs sharing it still share it after freezing.
+        let shared = match self.shared {
+            None => None,
+            Some(shared) => Some(Box::new(StructShared {
+                base: shared.base.freeze(freezer)?,
+                replaced: freeze_fields(shared.replaced, freezer)?,
+            })),
+        };
+        Ok(FrozenStruct {
+            fields: freeze_fields(self.fields, freezer)?,
+            shared,
+        })
+    }
+}
+
+fn freeze_fields(
+    fields: SmallMap<StringValue, Value>,
+    freezer: &Freezer,
+) -> anyhow::Result<SmallMap<FrozenStringValue, FrozenValue>> {
+    let mut frozen = SmallMap::with_capacity(fields.len());
+    for (k, v) in fields.into_iter_hashed() {
+        let k = k.freeze(freezer)?;
+        let v = v
+            .freeze(freezer)
+            .with_context(|| format!("failed to freeze field `{}`", k.key().as_str()))?;
+        frozen.insert_hashed_unique_unchecked(k, v);
+    }
+    Ok(frozen)
+}
+
 impl<'v, V: ValueLike<'v>> Display for StructGen<'v, V> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_keyed_container(
@@ -124,6 +410,12 @@ impl<'v, V: ValueLike<'v>> StarlarkValue<'v> for StructGen<'v, V>
 where
     Self: ProvidesStaticType<'v>,
 {
+    fn get_methods() -> Option<&'static Methods> {
+        struct_methods()
+    }
+
+    fn attrs_shadow_methods(_: Private) {}
+
     fn collect_repr_cycle(&self, collector: &mut String) {
         collector.push_str("struct(...)");
     }
@@ -132,7 +424,7 @@ where
         match Struct::from_value(other) {
             None => Ok(false),
             Some(other) => {
-                equals_small_map(coerce(&self.fields), &other.fields, |x, y| x.equals(*y))
+                equals_small_map(&self.fields_map(), &other.fields_map(), |x, y| x.equals(*y))
             }
         }
     }
@@ -140,49 +432,63 @@ where
     fn compare(&self, other: Value<'v>) -> crate::Result<Ordering> {
         match Struct::from_value(other) {
             None => ValueError::unsupported_with(self, "cmp()", other),
+            // Fields are compared sorted by name, so ordering agrees with `equals`,
+            // which ignores field order.
             Some(other) => compare_small_map(
-                coerce(&self.fields),
-                &other.fields,
+                &self.fields_map(),
+                &other.fields_map(),
                 |k| k.as_str(),
                 |x, y| x.compare(*y),
             ),
         }
     }
 
+    fn add(&self, rhs: Value<'v>, heap: &'v Heap) -> Option<crate::Result<Value<'v>>> {
+        let rhs = Struct::from_value(rhs)?;
+        // Fields of `self` keep their positions, even if overridden by `rhs`,
+        // and fields only present in `rhs` are appended.
+        let mut fields = self.fields_map().into_owned();
+        for (k, v) in rhs.iter_hashed() {
+            fields.insert_hashed(k, v);
+        }
+        Some(Ok(heap.alloc(Struct::new(fields))))
+    }
+
     fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
         self.get_attr_hashed(Hashed::new(attribute), heap)
     }
 
     fn get_attr_hashed(&self, attribute: Hashed<&str>, _heap: &'v Heap) -> Option<Value<'v>> {
-        coerce(&self.fields).get_hashed(attribute).copied()
+        self.get_hashed(attribute)
     }
 
     fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
-        // Must use unordered hash because equality is unordered,
-        // and `a = b  =>  hash(a) = hash(b)`.
-        let mut unordered_hasher = UnorderedHasher::new();
+        self.write_hash_excluding(&[], hasher)
+    }
 
-        for (k, v) in self.fields.iter_hashed() {
-            // Should hash key and value together, so two structs
-            // `a=1 b=2` and `a=2 b=1` would produce different hashes.
-            let mut entry_hasher = StarlarkHasher::new();
-            k.hash().hash(&mut entry_hasher);
-            v.write_hash(&mut entry_hasher)?;
-            unordered_hasher.write_hash(entry_hasher.finish());
-        }
+    // Structs are immutable, so unlike dicts, iteration needs no borrow.
+    unsafe fn iterate(&self, me: Value<'v>, _heap: &'v Heap) -> crate::Result<Value<'v>> {
+        Ok(me)
+    }
 
-        hasher.write_u64(unordered_hasher.finish());
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        debug_assert!(index <= self.len());
+        let rem = self.len() - index;
+        (rem, Some(rem))
+    }
 
-        Ok(())
+    unsafe fn iter_next(&self, index: usize, _heap: &'v Heap) -> Option<Value<'v>> {
+        self.get_index(index).map(|(k, _)| k.to_value())
     }
 
+    unsafe fn iter_stop(&self) {}
+
     fn dir_attr(&self) -> Vec<String> {
-        self.fields.keys().map(|x| x.as_str().to_owned()).collect()
+        self.iter().map(|(k, _)| k.as_str().to_owned()).collect()
     }
 
     fn documentation(&self) -> Option<DocItem> {
         let members = self
-            .fields
             .iter()
             .map(|(k, v)| {
                 let name = k.as_str().to_owned();
@@ -211,7 +517,6 @@ where
     fn typechecker_ty(&self) -> Option<Ty> {
         Some(Ty::custom(TyStruct {
             fields: self
-                .fields
                 .iter()
                 .map(|(name, value)| (ArcStr::from(name.as_str()), Ty::of_value(value.to_value())))
                 .collect(),
@@ -231,8 +536,33 @@ impl<'v, V: ValueLike<'v>> Serialize for StructGen<'v, V> {
 
 #[cfg(test)]
 mod tests {
+    use allocative::Allocative;
+    use starlark_derive::starlark_value;
+    use starlark_derive::NoSerialize;
+    use starlark_derive::Trace;
 
+    use crate as starlark;
+    use crate::any::ProvidesStaticType;
     use crate::assert;
+    use crate::environment::Module;
+    use crate::values::dict::AllocDict;
+    use crate::values::list::AllocList;
+    use crate::values::list::ListRef;
+    use crate::values::structs::AllocStruct;
+    use crate::values::structs::StructRef;
+    use crate::values::Freeze;
+    use crate::values::Freezer;
+    use crate::values::Heap;
+    use crate::values::StarlarkValue;
+    use crate::values::Value;
+
+    fn field_names(s: Value) -> Vec<String> {
+        StructRef::from_value(s)
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k.as_str().to_owned())
+            .collect()
+    }
 
     #[test]
     fn test_repr() {
@@ -285,6 +615,124 @@ json.encode(struct(foo = [struct(bar = "some")])) == '{"foo":[{"bar":"some"}]}'
         );
     }
 
+    #[test]
+    fn test_to_json_value_struct() {
+        let v = assert::pass("struct(z = 1, a = struct(y = [True], b = None))");
+        let json = v.value().to_json_value().unwrap();
+        assert_eq!(
+            serde_json::json!({"z": 1, "a": {"y": [true], "b": null}}),
+            json
+        );
+        // `serde_json::Map` sorts keys unless `preserve_order` is enabled.
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(v.value().to_json().unwrap(), json.to_string());
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(r#"{"a":{"b":null,"y":[true]},"z":1}"#, json.to_string());
+    }
+
+    #[test]
+    fn test_to_json_pretty() {
+        let v = assert::pass("struct(a = 1, b = [struct(c = 'x'), {'d': None}], e = [])");
+        assert_eq!(
+            r#"{
+  "a": 1,
+  "b": [
+    {
+      "c": "x"
+    },
+    {
+      "d": null
+    }
+  ],
+  "e": []
+}"#,
+            v.value().to_json_pretty(2).unwrap()
+        );
+        assert_eq!(
+            "{\n\"a\": 1,\n\"b\": [\n{\n\"c\": \"x\"\n},\n{\n\"d\": null\n}\n],\n\"e\": []\n}",
+            v.value().to_json_pretty(0).unwrap()
+        );
+        for scalar in ["1", "'x\\n'", "None", "True", "1.5", "12345678901234567890"] {
+            let v = assert::pass(scalar);
+            assert_eq!(
+                v.value().to_json().unwrap(),
+                v.value().to_json_pretty(4).unwrap()
+            );
+        }
+        assert::pass("l = [1]; l.append(l); l")
+            .value()
+            .to_json_pretty(2)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_to_json_method() {
+        assert::all_true(
+            r#"
+struct(foo = 42, bar = "some").to_json() == '{"foo":42,"bar":"some"}'
+struct(foo = struct(bar = "some")).to_json() == json.encode(struct(foo = struct(bar = "some")))
+"to_json" in dir(struct(a = 1))
+hasattr(struct(), "to_json")
+struct(to_json = 1).to_json == 1
+len([k for k in dir(struct(to_json = 1)) if k == "to_json"]) == 1
+json.decode('{"to_json": 1}', objects_as_structs = True).to_json == 1
+"#,
+        );
+    }
+
+    #[test]
+    fn test_to_dict_method() {
+        assert::pass(
+            r#"
+s = struct(b = [1], a = struct(c = 2))
+d = s.to_dict()
+assert_eq({"b": [1], "a": struct(c = 2)}, d)
+assert_eq(["b", "a"], list(d))
+assert_eq(s.fields(sorted = True), sorted(d))
+d["x"] = 3
+assert_eq(struct(b = [1], a = struct(c = 2)), s)
+assert_eq(s, struct(**s.to_dict()))
+assert_eq(1, struct(to_dict = 1).to_dict)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_add() {
+        assert::all_true(
+            r#"
+struct(a = 1) + struct(b = 2) == struct(a = 1, b = 2)
+struct(a = 1, b = 2) + struct(a = 3, c = 4) == struct(a = 3, b = 2, c = 4)
+(struct(a = 1, b = 2) + struct(a = 3, c = 4)).fields() == ["a", "b", "c"]
+struct() + struct() == struct()
+struct(a = [1]) + struct() == struct(a = [1])
+"#,
+        );
+        assert::fail("struct(a = 1) + {'b': 2}", "not supported");
+        assert::fail("{'b': 2} + struct(a = 1)", "not supported");
+    }
+
+    #[test]
+    fn test_fields_method() {
+        assert::all_true(
+            r#"
+struct(b = 1, c = 2, a = 3).fields() == ["b", "c", "a"]
+struct(b = 1, c = 2, a = 3).fields(sorted = True) == ["a", "b", "c"]
+struct(B = 1, a = 2, _c = 3).fields(sorted = True) == ["B", "_c", "a"]
+struct().fields(sorted = True) == []
+struct(fields = [1]).fields == [1]
+struct(fields = 1, a = 2).to_dict() == {"fields": 1, "a": 2}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_reversed() {
+        assert::eq("list(reversed(struct(a=1, b=2)))", "['b', 'a']");
+        assert::eq("reversed(struct())", "[]");
+        assert::eq("list(struct(a=1))", "['a']");
+    }
+
     #[test]
     fn test_comparison_bug() {
         // TODO(nga): this should be false, because `a < b`,
@@ -294,4 +742,221 @@ json.encode(struct(foo = [struct(bar = "some")])) == '{"foo":[{"bar":"some"}]}'
         //   (because it is too expensive to sort keys on each comparison).
         assert::is_true("struct(b=1) < struct(a=1, x=1)")
     }
+
+    #[test]
+    fn test_compare_ignores_field_order() {
+        assert::pass(
+            r#"
+assert_eq(struct(a=1, b=2), struct(b=2, a=1))
+assert_eq(False, struct(a=1, b=2) < struct(b=2, a=1))
+assert_eq(False, struct(a=1, b=2) > struct(b=2, a=1))
+assert_eq(True, struct(a=1, b=2) <= struct(b=2, a=1))
+assert_eq(True, struct(a=1, b=2) < struct(b=3, a=1))
+assert_eq(True, struct(b=2, a=1) < struct(a=2, b=1))
+assert_eq([struct(a=1, b=2), struct(b=2, a=1)], sorted([struct(b=2, a=1), struct(a=1, b=2)]))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_nested_equality_ignores_order() {
+        assert::pass(
+            r#"
+a = struct(m = {"a": 1, "b": 2}, s = struct(x = 1, y = (2, 3)))
+b = struct(s = struct(y = (2, 3), x = 1), m = {"b": 2, "a": 1})
+assert_eq(a, b)
+assert_ne(a, struct(m = {"a": 1, "b": 3}, s = struct(x = 1, y = (2, 3))))
+"#,
+        );
+
+        let module = assert::pass_module(
+            r#"
+# Dicts are not hashable, so compare hashes of structs without dicts.
+a = struct(n = struct(x = 1, y = (2, "z")), k = "v")
+b = struct(k = "v", n = struct(y = (2, "z"), x = 1))
+c = struct(m = {"a": 1, "b": 2})
+d = struct(m = {"b": 2, "a": 1})
+"#,
+        );
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| module.get(name).unwrap());
+        assert!(c.value().equals(d.value()).unwrap());
+        assert_eq!(
+            a.value().get_hashed().unwrap().hash(),
+            b.value().get_hashed().unwrap().hash()
+        );
+        // Values with nested dicts fail to hash, regardless of the entry order.
+        assert!(c.value().get_hashed().is_err());
+        assert!(d.value().get_hashed().is_err());
+    }
+
+    #[test]
+    fn test_freeze_cycle_through_list() {
+        // Freezing forwards each value before freezing its contents,
+        // so cycles through mutable values are preserved rather than rejected.
+        let s = assert::pass("l = []; s = struct(x = l); l.append(s); s");
+        let s = s.value();
+        let l = StructRef::from_value(s).unwrap().iter().next().unwrap().1;
+        assert!(s.ptr_eq(ListRef::from_value(l).unwrap()[0]));
+        assert_eq!("struct(x=[struct(...)])", s.to_repr());
+    }
+
+    #[test]
+    fn test_freeze_all_frozen_fields() {
+        // Field names are literals and values are ints, so all fields are frozen
+        // before the struct is, and the struct map is reused when freezing.
+        let fields: Vec<String> = (0..100)
+            .map(|i| format!("f{} = {}", i * 37 % 100, i))
+            .collect();
+        let s = assert::pass(&format!("struct({})", fields.join(", ")));
+        let s = s.value();
+        assert_eq!(
+            (0..100)
+                .map(|i| format!("f{}", i * 37 % 100))
+                .collect::<Vec<_>>(),
+            field_names(s)
+        );
+        let heap = Heap::new();
+        for i in 0..100 {
+            let v = s.get_attr(&format!("f{}", i * 37 % 100), &heap).unwrap();
+            assert_eq!(Some(i), v.and_then(|v| v.unpack_i32()));
+        }
+        assert!(s.is_deeply_frozen());
+    }
+
+    #[test]
+    fn test_field_order_preserved_by_freeze() {
+        let module = Module::new();
+        // Neither sorted nor reverse sorted, and large enough to have an index.
+        let s = module.heap().alloc(AllocStruct(
+            (0..100).map(|i| (format!("f{}", i * 37 % 100), i)),
+        ));
+        let names = field_names(s);
+        let repr = s.to_repr();
+        let json = s.to_json().unwrap();
+        module.set("s", s);
+
+        let module = module.freeze().unwrap();
+        let s = module.get("s").unwrap();
+        assert_eq!(names, field_names(s.value()));
+        assert_eq!(repr, s.value().to_repr());
+        assert_eq!(json, s.value().to_json().unwrap());
+    }
+
+    #[derive(
+        Debug,
+        derive_more::Display,
+        Trace,
+        ProvidesStaticType,
+        NoSerialize,
+        Allocative
+    )]
+    #[display(fmt = "unfreezable")]
+    struct Unfreezable;
+
+    #[starlark_value(type = "unfreezable")]
+    impl<'v> StarlarkValue<'v> for Unfreezable {}
+
+    impl Freeze for Unfreezable {
+        type Frozen = Unfreezable;
+
+        fn freeze(self, _freezer: &Freezer) -> anyhow::Result<Unfreezable> {
+            Err(anyhow::anyhow!("this value cannot be frozen"))
+        }
+    }
+
+    #[test]
+    fn test_freeze_error_names_field() {
+        let module = Module::new();
+        let heap = module.heap();
+        let callback = heap.alloc_complex(Unfreezable);
+        module.set(
+            "s",
+            heap.alloc(AllocStruct([("a", heap.alloc(1)), ("callback", callback)])),
+        );
+        let err = module.freeze().unwrap_err();
+        let err = format!("{:#}", err);
+        assert!(
+            err.contains("failed to freeze field `callback`: this value cannot be frozen"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_duplicate_field_names() {
+        assert::fail(
+            "struct(a = 1, **{'b': 2, 'a': 3})",
+            "Argument `a` occurs more than once",
+        );
+        assert::fail("struct(a = 1, a = 2)", "repeated named argument");
+    }
+
+    #[test]
+    fn test_iterate() {
+        assert::all_true(
+            r#"
+[k for k in struct(b = 1, a = 2)] == ["b", "a"]
+list(struct()) == []
+sorted(struct(y = 1, x = 2)) == ["x", "y"]
+"#,
+        );
+        assert::is_true(
+            r#"
+s = struct(a = 1, b = 2)
+res = []
+for k in s:
+    res.append(getattr(s, k))
+res == [1, 2]
+"#,
+        );
+    }
+
+    #[test]
+    fn test_freeze_error_names_path() {
+        let module = Module::new();
+        let heap = module.heap();
+        let callback = heap.alloc_complex(Unfreezable);
+        let tuple = heap.alloc((1, 2, callback));
+        let dict = heap.alloc(AllocDict([("k", tuple)]));
+        let s = heap.alloc(AllocStruct([("foo", dict)]));
+        module.set("x", heap.alloc(AllocList([heap.alloc(0), s])));
+        let err = module.freeze().unwrap_err();
+        assert_eq!(
+            "failed to freeze variable `x`: failed to freeze index 1: \
+                failed to freeze field `foo`: failed to freeze value of key \"k\": \
+                failed to freeze index 2: this value cannot be frozen",
+            format!("{:#}", err)
+        );
+    }
+
+    #[derive(
+        Debug,
+        derive_more::Display,
+        ProvidesStaticType,
+        NoSerialize,
+        Allocative
+    )]
+    #[display(fmt = "uncomparable")]
+    struct Uncomparable;
+
+    #[starlark_value(type = "uncomparable")]
+    impl<'v> StarlarkValue<'v> for Uncomparable {
+        fn equals(&self, _other: Value<'v>) -> crate::Result<bool> {
+            Err(crate::Error::from(anyhow::anyhow!("cannot compare")))
+        }
+    }
+
+    #[test]
+    fn test_equals_different_len_compares_no_fields() {
+        let heap = Heap::new();
+        let x = heap.alloc(AllocStruct([("a", heap.alloc_simple(Uncomparable))]));
+        let y = heap.alloc(AllocStruct([
+            ("a", heap.alloc_simple(Uncomparable)),
+            ("b", heap.alloc(1)),
+        ]));
+        let z = heap.alloc(AllocStruct([("a", heap.alloc_simple(Uncomparable))]));
+        assert!(!x.equals(y).unwrap());
+        assert!(!y.equals(x).unwrap());
+        assert!(x.equals(z).is_err());
+    }
 }
@@ -0,0 +1,105 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Recursive conversion of struct fields to Rust data.
+
+use crate::values::dict::DictRef;
+use crate::values::float::StarlarkFloat;
+use crate::values::list::ListRef;
+use crate::values::stack_guard;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::types::int_or_big::StarlarkIntRef;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// Output of a conversion from a tree of `None`, `bool`, `int`, `float`, `str`,
+/// `list`, `tuple`, `dict` and `struct` values.
+///
+/// Paths are used in error messages: `a.b` is field `b` of field `a`,
+/// `a[0]` is an item of a list, tuple or dict.
+pub(crate) trait StructWalk: Sized {
+    fn none() -> Self;
+    fn bool(b: bool) -> Self;
+    fn int(i: StarlarkIntRef) -> Self;
+    fn float(f: f64) -> Self;
+    fn string(s: &str) -> Self;
+    fn list(items: Vec<Self>) -> Self;
+    fn tuple(items: Vec<Self>) -> Self;
+    fn struct_fields(fields: Vec<(String, Self)>) -> Self;
+    /// Convert a dict, calling [`walk`] for nested values.
+    fn dict(path: &str, dict: &DictRef) -> anyhow::Result<Self>;
+    /// Error for a value of any other type.
+    fn unsupported(path: String, ty: &'static str) -> anyhow::Error;
+}
+
+/// Path of a struct field.
+pub(crate) fn field_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Convert the fields of a struct.
+pub(crate) fn walk_fields<W: StructWalk>(
+    path: &str,
+    s: StructRef,
+) -> anyhow::Result<Vec<(String, W)>> {
+    s.iter()
+        .map(|(k, v)| {
+            Ok((
+                k.as_str().to_owned(),
+                walk(field_path(path, k.as_str()), v)?,
+            ))
+        })
+        .collect()
+}
+
+/// Convert a value. Fails on cyclic values instead of overflowing the stack.
+pub(crate) fn walk<W: StructWalk>(path: String, value: Value) -> anyhow::Result<W> {
+    let _guard = stack_guard::stack_guard()?;
+    let items = |path: &str, xs: &[Value]| {
+        xs.iter()
+            .enumerate()
+            .map(|(i, x)| walk(format!("{}[{}]", path, i), *x))
+            .collect::<anyhow::Result<Vec<_>>>()
+    };
+    if value.is_none() {
+        Ok(W::none())
+    } else if let Some(b) = value.unpack_bool() {
+        Ok(W::bool(b))
+    } else if let Some(i) = StarlarkIntRef::unpack_value(value) {
+        Ok(W::int(i))
+    } else if let Some(f) = value.downcast_ref::<StarlarkFloat>() {
+        Ok(W::float(f.0))
+    } else if let Some(s) = value.unpack_str() {
+        Ok(W::string(s))
+    } else if let Some(s) = StructRef::from_value(value) {
+        Ok(W::struct_fields(walk_fields(&path, s)?))
+    } else if let Some(d) = DictRef::from_value(value) {
+        W::dict(&path, &d)
+    } else if let Some(xs) = ListRef::from_value(value) {
+        Ok(W::list(items(&path, xs.content())?))
+    } else if let Some(xs) = TupleRef::from_value(value) {
+        Ok(W::tuple(items(&path, xs.content())?))
+    } else {
+        Err(W::unsupported(path, value.get_type()))
+    }
+}
@@ -33,7 +33,9 @@ mod module;
 mod serde;
 mod starlark_type_repr;
 mod starlark_value;
+mod struct_access;
 mod trace;
+mod unpack_struct;
 mod unpack_value;
 mod util;
 mod v_lifetime;
@@ -192,6 +194,33 @@ pub fn derive_starlark_attrs(input: proc_macro::TokenStream) -> proc_macro::Toke
     attrs::derive_attrs(input)
 }
 
+/// Derive getters which unpack fields of a Starlark struct on first access,
+/// and cache the result. See `starlark::values::structs::StructAccessField`.
+#[proc_macro_derive(StructAccess, attributes(starlark))]
+pub fn derive_struct_access(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    struct_access::derive_struct_access(input)
+}
+
+/// Derive `UnpackValue` for a Rust struct with named fields, unpacking each field
+/// from the Starlark struct field of the same name, or the name given with
+/// `#[starlark(field = "...")]`.
+///
+/// Unpacking fails if the value is not a struct, or if any field is missing
+/// or does not unpack to the field type. Extra struct fields are ignored.
+///
+/// ```ignore
+/// #[derive(UnpackStruct)]
+/// struct ServerConfig<'v> {
+///     host: &'v str,
+///     #[starlark(field = "port")]
+///     port_number: u32,
+/// }
+/// ```
+#[proc_macro_derive(UnpackStruct, attributes(starlark))]
+pub fn derive_unpack_struct(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    unpack_struct::derive_unpack_struct(input)
+}
+
 /// Generate an accessor function on the provided type that returns its documentation
 /// based on `StarlarkValue::get_methods()`. This macro requires that the type implements
 /// `starlark::StarlarkValue`.
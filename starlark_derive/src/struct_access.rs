@@ -0,0 +1,177 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Fields;
+use syn::GenericArgument;
+use syn::Ident;
+use syn::LitStr;
+use syn::PathArguments;
+use syn::Result;
+use syn::Type;
+
+pub fn derive_struct_access(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_struct_access(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Options of a field from `#[starlark(...)]` attributes.
+#[derive(Default)]
+struct FieldOptions {
+    /// `#[starlark(value)]`: the field holds the struct value.
+    value: bool,
+    /// `#[starlark(field = "name")]`: name of the struct field.
+    field: Option<LitStr>,
+}
+
+fn field_options(field: &syn::Field) -> Result<FieldOptions> {
+    let mut opts = FieldOptions::default();
+    for attr in &field.attrs {
+        if !attr.path().is_ident("starlark") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("value") {
+                opts.value = true;
+                Ok(())
+            } else if meta.path.is_ident("field") {
+                opts.field = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expecting `value` or `field = \"...\"`"))
+            }
+        })?;
+    }
+    if opts.value && opts.field.is_some() {
+        return Err(Error::new(
+            field.span(),
+            "`value` and `field` cannot be used together",
+        ));
+    }
+    Ok(opts)
+}
+
+/// Extract `T` from `StructAccessField<T>`.
+fn accessed_type(ty: &Type) -> Result<&Type> {
+    if let Type::Path(p) = ty {
+        if let Some(last) = p.path.segments.last() {
+            if last.ident == "StructAccessField" {
+                if let PathArguments::AngleBracketed(args) = &last.arguments {
+                    if let [GenericArgument::Type(t)] = args.args.iter().collect::<Vec<_>>()[..] {
+                        return Ok(t);
+                    }
+                }
+            }
+        }
+    }
+    Err(Error::new(
+        ty.span(),
+        "#[derive(StructAccess)] fields must be `StructAccessField<T>`, \
+        except the field marked `#[starlark(value)]`",
+    ))
+}
+
+fn expand_struct_access(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.span(),
+                    "#[derive(StructAccess)] requires named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "#[derive(StructAccess)] can only be used on structs",
+            ));
+        }
+    };
+
+    let mut value_field: Option<(&Ident, &Type)> = None;
+    let mut accessors = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let opts = field_options(field)?;
+        if opts.value {
+            if value_field.is_some() {
+                return Err(Error::new(
+                    field.span(),
+                    "only one field can be marked `#[starlark(value)]`",
+                ));
+            }
+            value_field = Some((ident, &field.ty));
+        } else {
+            let field_name = match opts.field {
+                Some(name) => name.value(),
+                None => {
+                    let s = ident.to_string();
+                    s.strip_prefix("r#").unwrap_or(&s).to_owned()
+                }
+            };
+            accessors.push((ident, accessed_type(&field.ty)?, field_name));
+        }
+    }
+    let Some((value_ident, value_ty)) = value_field else {
+        return Err(Error::new(
+            input.span(),
+            "#[derive(StructAccess)] requires a field marked `#[starlark(value)]`",
+        ));
+    };
+
+    let field_idents = accessors.iter().map(|(ident, _, _)| ident);
+    let getters = accessors.iter().map(|(ident, ty, field_name)| {
+        let doc = format!(
+            "Field `{}` of the struct, unpacked on first access.",
+            field_name
+        );
+        quote! {
+            #[doc = #doc]
+            pub fn #ident(&self) -> starlark::__derive_refs::anyhow::Result<&#ty> {
+                self.#ident.get(self.#value_ident, #field_name)
+            }
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Accessor for the given struct value. Fails if the value is not a struct.
+            /// Fields are not unpacked until accessed.
+            pub fn new(value: #value_ty) -> starlark::__derive_refs::anyhow::Result<Self> {
+                starlark::__derive_refs::struct_ref(value)?;
+                std::result::Result::Ok(#name {
+                    #value_ident: value,
+                    #(#field_idents: std::default::Default::default(),)*
+                })
+            }
+
+            #(#getters)*
+        }
+    })
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Fields;
+use syn::LitStr;
+use syn::Result;
+
+use crate::v_lifetime::find_v_lifetime;
+
+pub fn derive_unpack_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_unpack_struct(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Name of the struct field from `#[starlark(field = "name")]`, if any.
+fn field_name_option(field: &syn::Field) -> Result<Option<LitStr>> {
+    let mut name = None;
+    for attr in &field.attrs {
+        if !attr.path().is_ident("starlark") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("field") {
+                name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expecting `field = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(name)
+}
+
+fn expand_unpack_struct(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.span(),
+                    "#[derive(UnpackStruct)] requires named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "#[derive(UnpackStruct)] can only be used on structs",
+            ));
+        }
+    };
+
+    let mut idents = Vec::new();
+    let mut field_names = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let field_name = match field_name_option(field)? {
+            Some(name) => name.value(),
+            None => {
+                let s = ident.to_string();
+                s.strip_prefix("r#").unwrap_or(&s).to_owned()
+            }
+        };
+        idents.push(ident);
+        field_names.push(field_name);
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut generics = input.generics.clone();
+    if find_v_lifetime(&input.generics)?.is_none() {
+        generics.params.push(syn::parse_quote! { 'v });
+    }
+    let (unpack_impl_generics, _, _) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics starlark::values::type_repr::StarlarkTypeRepr for #name #ty_generics #where_clause {
+            type Canonical = Self;
+
+            fn starlark_type_repr() -> starlark::typing::Ty {
+                <starlark::values::structs::StructRef<'static>
+                    as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr()
+            }
+        }
+
+        impl #unpack_impl_generics starlark::values::UnpackValue<'v> for #name #ty_generics #where_clause {
+            fn unpack_value(value: starlark::values::Value<'v>) -> std::option::Option<Self> {
+                let s = starlark::values::structs::StructRef::from_value(value)?;
+                std::option::Option::Some(#name {
+                    #(#idents: starlark::__derive_refs::unpack_struct_field_opt(&s, #field_names)?,)*
+                })
+            }
+
+            fn unpack_value_err(value: starlark::values::Value<'v>) -> starlark::__derive_refs::anyhow::Result<Self> {
+                let s = starlark::__derive_refs::struct_ref(value)?;
+                std::result::Result::Ok(#name {
+                    #(#idents: starlark::__derive_refs::unpack_struct_field(&s, #field_names)?,)*
+                })
+            }
+
+            fn unpack_param(value: starlark::values::Value<'v>) -> starlark::__derive_refs::anyhow::Result<Self> {
+                Self::unpack_value_err(value)
+            }
+
+            fn unpack_named_param(
+                value: starlark::values::Value<'v>,
+                param_name: &str,
+            ) -> starlark::__derive_refs::anyhow::Result<Self> {
+                Self::unpack_value_err(value)
+                    .map_err(|e| starlark::__derive_refs::anyhow::anyhow!("Parameter `{}`: {}", param_name, e))
+            }
+        }
+    })
+}
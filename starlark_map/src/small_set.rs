@@ -244,6 +244,17 @@ impl<T> SmallSet<T> {
         self.0.remove(key).is_some()
     }
 
+    /// Remove the element with the given hash from the set if it is present.
+    ///
+    /// Time complexity of this operation is *O(N)* where *N* is the number of entries in the set.
+    #[inline]
+    pub fn remove_hashed<Q>(&mut self, key: Hashed<&Q>) -> bool
+    where
+        Q: ?Sized + Equivalent<T>,
+    {
+        self.0.remove_hashed(key).is_some()
+    }
+
     /// Insert entry if it doesn't exist.
     ///
     /// Return the resulting entry in the map.
@@ -712,6 +723,14 @@ mod tests {
         assert!(!s.remove(&17));
     }
 
+    #[test]
+    fn test_remove_hashed() {
+        let mut s: SmallSet<u32> = SmallSet::from_iter([17, 19, 23]);
+        assert!(s.remove_hashed(Hashed::new(&19)));
+        assert!(!s.remove_hashed(Hashed::new(&19)));
+        assert_eq!(vec![17, 23], Vec::from_iter(s.iter().copied()));
+    }
+
     #[test]
     fn test_difference() {
         let a = SmallSet::from_iter([1, 2, 3]);